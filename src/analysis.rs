@@ -0,0 +1,766 @@
+//! Module used for analysing the structure of packed spheres.
+use std::f64::consts::PI;
+
+use nalgebra::Point3;
+use rand::Rng;
+use serde::Serialize;
+use spherical_cow::shapes::Sphere;
+
+use grid::Grid;
+
+use crate::parsing::Spheres;
+
+pub(crate) mod grid;
+
+/// Number of slices along each axis used to integrate the volume of a sphere which is only
+/// partly inside a box.
+const CLIP_SLICES: usize = 64;
+
+/// Estimate the volume fraction `packed` would have with periodic boundaries instead of the walls
+/// of the cuboid container with `half_extents`.
+///
+/// Spheres pack less densely near a wall, so the fraction is measured only inside a window which
+/// excludes a layer two of the largest radii thick next to each wall, where the spheres are
+/// arranged as they would be in the bulk of a periodic system. The volume of each sphere crossing
+/// the window's boundary is clipped to the window by numerical integration. This approximates,
+/// rather than simulates, periodic boundaries: the window holds fewer spheres than the container,
+/// so the estimate is noisier than the wall-bounded fraction. Returns None if the container is too
+/// thin for the window to exist.
+pub fn periodic_volume_fraction(packed: &[Sphere], half_extents: [f32; 3]) -> Option<f64> {
+    let max_radius = packed.iter().map(|s| s.radius).fold(0., f32::max) as f64;
+    let mut window = [0.; 3];
+    for (w, h) in window.iter_mut().zip(half_extents.iter()) {
+        *w = *h as f64 - 2. * max_radius;
+        if *w <= 0. {
+            return None;
+        }
+    }
+    let filled: f64 = packed.iter().map(|s| clipped_volume(s, window)).sum();
+    Some(filled / (8. * window[0] * window[1] * window[2]))
+}
+
+/// Estimate the bulk volume fraction of `packed` from `fraction`, the volume fraction it fills of
+/// the cuboid container with `half_extents`, by correcting for the spheres packing less densely
+/// next to the walls.
+///
+/// The packing next to a flat wall is taken to be missing the volume of a layer half the mean
+/// radius thick, so `fraction` is divided by the fraction `1 - r̄ / 2 · A / V` of the container
+/// outside that layer, where `A / V = 1/x + 1/y + 1/z` is the ratio of the surface area of the
+/// walls to the volume for half extents `x`, `y` and `z`. This corrects a slab or channel, whose
+/// ratio is dominated by its thin dimensions, far more than a cube of the same volume. It is a
+/// first order correction which assumes the layer is thin compared to the container, and returns
+/// None if `packed` is empty or the container is too thin for any of it to be outside the layer.
+pub fn wall_corrected_volume_fraction(
+    packed: &[Sphere],
+    half_extents: [f32; 3],
+    fraction: f64,
+) -> Option<f64> {
+    if packed.is_empty() {
+        return None;
+    }
+    let mean_radius = packed.iter().map(|s| s.radius as f64).sum::<f64>() / packed.len() as f64;
+    let sa_to_vol: f64 = half_extents.iter().map(|h| 1. / *h as f64).sum();
+    let outside = 1. - mean_radius / 2. * sa_to_vol;
+    (outside > 0.).then(|| fraction / outside)
+}
+
+/// Provides the fraction of each of `slices` equally thick slabs of the cuboid container with
+/// `half_extents`, stacked along the z axis from the bottom up, filled by `packed`.
+///
+/// The volume of a sphere crossing the boundary between slabs is divided between them exactly, by
+/// integrating the areas of its cross-sections over each slab's height.
+pub fn density_profile(packed: &[Sphere], half_extents: [f32; 3], slices: usize) -> Vec<f64> {
+    let [x, y, z] = half_extents.map(|h| h as f64);
+    let thickness = 2. * z / slices as f64;
+    let mut filled = vec![0.; slices];
+    for sphere in packed {
+        let (center, radius) = (sphere.center.z as f64, sphere.radius as f64);
+        // The volume of the sphere below height `t` above its center.
+        let below = |t: f64| {
+            let t = t.clamp(-radius, radius);
+            PI * (radius.powi(2) * (t + radius) - (t.powi(3) + radius.powi(3)) / 3.)
+        };
+        let slab = |height: f64| ((height + z) / thickness).floor().max(0.) as usize;
+        for (i, volume) in filled
+            .iter_mut()
+            .enumerate()
+            .take(slab(center + radius) + 1)
+            .skip(slab(center - radius))
+        {
+            let bottom = -z + i as f64 * thickness;
+            *volume += below(bottom + thickness - center) - below(bottom - center);
+        }
+    }
+    let slab_volume = 4. * x * y * thickness;
+    filled.into_iter().map(|v| v / slab_volume).collect()
+}
+
+/// The binning of a radial distribution function.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RdfBins {
+    /// Number of equal-width bins
+    pub bins: usize,
+    /// Center distance up to which pairs are counted
+    pub r_max: f64,
+}
+
+/// A radial distribution function sampled at the centers of its bins.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Rdf {
+    /// Distance at the center of each bin
+    pub r: Vec<f64>,
+    /// Value of the radial distribution function in each bin
+    pub g: Vec<f64>,
+}
+
+/// Compute the radial distribution function g(r) of the centers of `packed` in the cuboid container
+/// with `half_extents`, histogramming center distances up to `binning.r_max` into `binning.bins`
+/// bins.
+///
+/// g(r) is normalized so that it tends to 1 for uncorrelated centers at the container's mean number
+/// density ρ = N / V: the count in the bin from r₁ to r₂ is divided by N_ref ρ 4/3 π (r₂³ - r₁³),
+/// where N_ref is the number of reference spheres. To correct for the finite container, only
+/// spheres whose centers are at least `r_max` from every wall are used as references, so that every
+/// shell around a reference is entirely inside the container. Returns None if no sphere is that far
+/// from the walls.
+pub fn radial_distribution(
+    packed: &[Sphere],
+    half_extents: [f32; 3],
+    binning: RdfBins,
+) -> Option<Rdf> {
+    let RdfBins { bins, r_max } = binning;
+    let [x, y, z] = half_extents.map(|h| h as f64);
+    let density = packed.len() as f64 / (8. * x * y * z);
+    let is_reference = |s: &Sphere| {
+        let center = [s.center.x, s.center.y, s.center.z];
+        center
+            .iter()
+            .zip(half_extents.iter())
+            .all(|(c, h)| (c.abs() as f64) + r_max <= *h as f64)
+    };
+    let grid = Grid::new(packed, half_extents, r_max);
+    let width = r_max / bins as f64;
+    let mut counts = vec![0usize; bins];
+    let mut references = 0;
+    for (i, sphere) in packed.iter().enumerate().filter(|(_, s)| is_reference(s)) {
+        references += 1;
+        for j in grid.near(sphere).filter(|&j| j != i) {
+            let distance = nalgebra::distance(&sphere.center, &packed[j].center) as f64;
+            if distance < r_max {
+                counts[((distance / width) as usize).min(bins - 1)] += 1;
+            }
+        }
+    }
+    if references == 0 {
+        return None;
+    }
+    let (r, g) = counts
+        .iter()
+        .enumerate()
+        .map(|(k, &count)| {
+            let (lo, hi) = (k as f64 * width, (k + 1) as f64 * width);
+            let shell = 4. / 3. * PI * (hi.powi(3) - lo.powi(3));
+            (
+                lo + width / 2.,
+                count as f64 / (references as f64 * density * shell),
+            )
+        })
+        .unzip();
+    Some(Rdf { r, g })
+}
+
+/// Number of equal-width bins in the histogram of pore sizes.
+pub const PORE_BINS: usize = 20;
+
+/// The distribution of the sizes of the empty space between the spheres of a packing, measured at
+/// randomly sampled points.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PoreStats {
+    /// Number of points sampled in the container
+    pub samples: usize,
+    /// Fraction of the sampled points outside every sphere
+    pub empty_fraction: f64,
+    /// Mean distance from a point outside every sphere to the nearest sphere surface
+    pub mean_distance: f64,
+    /// Distance at the center of each equal-width bin, up to the largest distance found
+    pub r: Vec<f64>,
+    /// Fraction of the points outside every sphere whose distance is in each bin
+    pub fraction: Vec<f64>,
+}
+
+/// Sample `samples` points uniformly at random in the cuboid container with `half_extents`, drawn
+/// from `rng`, and histogram into `PORE_BINS` bins the distance from each point outside every
+/// sphere of `packed` to the nearest sphere surface, which is the radius of the largest empty ball
+/// centered there.
+///
+/// Only the spheres bound the pores, not the container's walls. The nearest sphere is found among
+/// those in the neighbouring cells of a grid with a reach of twice the largest radius, which is
+/// exact whenever the nearest surface found is within the largest radius of the point, and
+/// otherwise among every sphere. Returns None if no sampled point is outside every sphere.
+pub fn pore_stats<R: Rng>(
+    packed: &[Sphere],
+    half_extents: [f32; 3],
+    samples: usize,
+    rng: &mut R,
+) -> Option<PoreStats> {
+    let max_radius = packed.iter().map(|s| s.radius).fold(0., f32::max);
+    let grid = Grid::new(packed, half_extents, 2. * max_radius as f64);
+    let surface_distance = |point: &Point3<f32>, i: usize| {
+        nalgebra::distance(point, &packed[i].center) - packed[i].radius
+    };
+    let mut distances = Vec::new();
+    for _ in 0..samples {
+        let point = Point3::from(half_extents.map(|h| rng.gen_range(-h..=h)));
+        let near = grid
+            .near_point(&point)
+            .map(|i| surface_distance(&point, i))
+            .fold(f32::INFINITY, f32::min);
+        let nearest = if near <= max_radius {
+            near
+        } else {
+            (0..packed.len())
+                .map(|i| surface_distance(&point, i))
+                .fold(f32::INFINITY, f32::min)
+        };
+        if nearest > 0. {
+            distances.push(nearest as f64);
+        }
+    }
+    if distances.is_empty() {
+        return None;
+    }
+    let max_distance = distances.iter().copied().fold(0., f64::max);
+    let width = max_distance / PORE_BINS as f64;
+    let mut counts = [0usize; PORE_BINS];
+    for distance in &distances {
+        counts[((distance / width) as usize).min(PORE_BINS - 1)] += 1;
+    }
+    let empty = distances.len() as f64;
+    Some(PoreStats {
+        samples,
+        empty_fraction: empty / samples as f64,
+        mean_distance: distances.iter().sum::<f64>() / empty,
+        r: (0..PORE_BINS).map(|k| (k as f64 + 0.5) * width).collect(),
+        fraction: counts.iter().map(|&count| count as f64 / empty).collect(),
+    })
+}
+
+/// The coordination numbers of a packing: how many other spheres each sphere touches.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Coordination {
+    /// Mean number of spheres each sphere touches
+    pub mean: f64,
+    /// Number of spheres touching each number of others, from 0 up to the most any sphere touches
+    pub distribution: Vec<usize>,
+}
+
+/// Count how many others each of `packed`, in the cuboid container with `half_extents`, touches,
+/// where two spheres touch if the distance between their centers is at most `1 + epsilon` times
+/// the sum of their radii.
+///
+/// Spheres placed tangent to each other are rarely exactly tangent once rounded, so `epsilon`
+/// should be a little larger than the packer's own tolerance. Returns None if `packed` is empty.
+pub fn coordination(
+    packed: &[Sphere],
+    half_extents: [f32; 3],
+    epsilon: f64,
+) -> Option<Coordination> {
+    if packed.is_empty() {
+        return None;
+    }
+    let grid = contact_grid(packed, half_extents, epsilon);
+    let mut distribution = Vec::new();
+    let mut contacts = 0;
+    for (i, sphere) in packed.iter().enumerate() {
+        let touching = grid
+            .near(sphere)
+            .filter(|&j| j != i && touches(sphere, &packed[j], epsilon))
+            .count();
+        if distribution.len() <= touching {
+            distribution.resize(touching + 1, 0);
+        }
+        distribution[touching] += 1;
+        contacts += touching;
+    }
+    Some(Coordination {
+        mean: contacts as f64 / packed.len() as f64,
+        distribution,
+    })
+}
+
+/// Provides the contact graph of `packed`, in the cuboid container with `half_extents`, as an edge
+/// list: the pair of indices `[i, j]`, with `i < j`, of every two spheres which touch as
+/// `coordination` counts them, sorted.
+///
+/// The number of edges each index is in is its sphere's coordination number, so the degrees of
+/// this graph have the distribution `coordination` reports.
+pub fn contact_graph(packed: &[Sphere], half_extents: [f32; 3], epsilon: f64) -> Vec<[usize; 2]> {
+    let grid = contact_grid(packed, half_extents, epsilon);
+    let mut edges = Vec::new();
+    for (i, sphere) in packed.iter().enumerate() {
+        let mut touching: Vec<usize> = grid
+            .near(sphere)
+            .filter(|&j| j > i && touches(sphere, &packed[j], epsilon))
+            .collect();
+        touching.sort_unstable();
+        edges.extend(touching.into_iter().map(|j| [i, j]));
+    }
+    edges
+}
+
+/// Build a grid over `packed` in the container with `half_extents` which finds every sphere which
+/// could touch another with contact epsilon `epsilon`.
+fn contact_grid(packed: &[Sphere], half_extents: [f32; 3], epsilon: f64) -> Grid {
+    let max_radius = packed.iter().map(|s| s.radius).fold(0., f32::max) as f64;
+    Grid::new(packed, half_extents, 2. * max_radius * (1. + epsilon))
+}
+
+/// Whether `a` and `b` touch: whether the distance between their centers is at most `1 + epsilon`
+/// times the sum of their radii.
+fn touches(a: &Sphere, b: &Sphere, epsilon: f64) -> bool {
+    let distance = nalgebra::distance(&a.center, &b.center) as f64;
+    distance <= (a.radius + b.radius) as f64 * (1. + epsilon)
+}
+
+/// Provides the skewness and kurtosis of the radii of `packed`: their third and fourth
+/// standardized moments, computed over the spheres as a population.
+///
+/// The kurtosis is not the excess kurtosis, so is 3 for normally distributed radii. Returns None
+/// if every radius is the same, where neither is defined.
+pub fn radius_moments(packed: &[Sphere]) -> Option<(f64, f64)> {
+    let radii: Vec<f64> = packed.iter().map(|s| s.radius as f64).collect();
+    if radii.windows(2).all(|pair| pair[0] == pair[1]) {
+        return None;
+    }
+    let n = radii.len() as f64;
+    let mean = radii.iter().sum::<f64>() / n;
+    let moment = |k: i32| radii.iter().map(|r| (r - mean).powi(k)).sum::<f64>() / n;
+    let variance = moment(2);
+    Some((moment(3) / variance.powf(1.5), moment(4) / variance.powi(2)))
+}
+
+/// Provides the smallest and largest radii of `packed`, or None if it's empty.
+///
+/// These can be narrower than the radii of the types packed, if no sphere of the largest (or
+/// smallest) type could be placed.
+pub fn radius_range(packed: &[Sphere]) -> Option<(f64, f64)> {
+    let radii = packed.iter().map(|s| s.radius as f64);
+    let min = radii.clone().reduce(f64::min)?;
+    Some((min, radii.fold(min, f64::max)))
+}
+
+/// Provides the index of the cell each of `packed` has its center in, for a grid of `cells` equal
+/// cells along each axis of the cuboid container with `half_extents`, so that 2 gives the octant.
+///
+/// Cell `(i, j, k)`, counted from the negative end of the x, y and z axes, has index
+/// `(k · cells + j) · cells + i`. A center on the boundary between cells is in the one on its
+/// positive side, and any center outside the container is in the nearest cell. For a two
+/// dimensional packing, whose container has no depth, every center is in a cell with `k = 0`.
+pub fn regions(packed: &[Sphere], half_extents: [f32; 3], cells: usize) -> Vec<usize> {
+    let cells = cells.max(1);
+    packed
+        .iter()
+        .map(|sphere| {
+            let center = [sphere.center.x, sphere.center.y, sphere.center.z];
+            let [i, j, k] = [0, 1, 2].map(|axis| {
+                let h = half_extents[axis] as f64;
+                let offset = (center[axis] as f64 + h) / (2. * h) * cells as f64;
+                // A container without depth gives NaN, which `max` replaces with 0.
+                (offset.max(0.) as usize).min(cells - 1)
+            });
+            (k * cells + j) * cells + i
+        })
+        .collect()
+}
+
+/// Ratio of the coarse to the fine radius of a bimodal mix past which the fine spheres fit through
+/// the gaps between the coarse ones, so that a larger ratio makes the mix little denser.
+///
+/// This and `DENSEST_COARSE_VOLUME_FRACTION` are the optimum McGeary found for binary mixtures of
+/// spheres, J. Am. Ceram. Soc. 44, 513 (1961), and are heuristics rather than exact values.
+pub const OPTIMAL_SIZE_RATIO: f64 = 7.;
+
+/// Share of the volume of a bimodal mix in its coarse spheres at which it packs most densely.
+pub const DENSEST_COARSE_VOLUME_FRACTION: f64 = 0.73;
+
+/// Adjustments to the two dominant sphere types of a mix which are expected to pack it more
+/// densely, following `OPTIMAL_SIZE_RATIO` and `DENSEST_COARSE_VOLUME_FRACTION`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BimodalSuggestion {
+    /// Name and radius of the coarser of the two types
+    pub coarse: (String, f64),
+    /// Name and radius of the finer of the two types
+    pub fine: (String, f64),
+    /// Ratio of the coarse radius to the fine radius
+    pub size_ratio: f64,
+    /// Share of the two types' volume in the coarse spheres, from their proportions by number
+    pub coarse_volume_fraction: f64,
+    /// Fine radius which would give `OPTIMAL_SIZE_RATIO`, if the ratio is smaller than it
+    pub suggested_fine_radius: Option<f64>,
+    /// Percentage by number of coarse spheres, among the two types, which would give
+    /// `DENSEST_COARSE_VOLUME_FRACTION` with the suggested fine radius (or the current one)
+    pub suggested_coarse_percentage: f64,
+}
+
+/// Suggest how to adjust the two dominant types of `spheres`, those making up the most volume
+/// given their proportions by number, to pack them more densely as a bimodal mix.
+///
+/// Returns None if `spheres` has fewer than two types or the two dominant types have the same
+/// radius. This only applies packing heuristics to the input, so it packs nothing.
+pub fn suggest_bimodal(spheres: &Spheres) -> Option<BimodalSuggestion> {
+    let volume = |radius: f64| 4. / 3. * PI * radius.powi(3);
+    let mut types: Vec<(String, f64, f64)> = spheres
+        .iter()
+        .map(|s| {
+            let share = s.proportion() * volume(s.radius());
+            (s.name().to_string(), s.radius(), share)
+        })
+        .collect();
+    types.sort_by(|a, b| b.2.total_cmp(&a.2));
+    let mut dominant: Vec<_> = types.into_iter().take(2).collect();
+    dominant.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let (fine, coarse) = (dominant.pop()?, dominant.pop()?);
+    if coarse.1 <= fine.1 {
+        return None;
+    }
+    let size_ratio = coarse.1 / fine.1;
+    let suggested_fine_radius =
+        (size_ratio < OPTIMAL_SIZE_RATIO).then(|| coarse.1 / OPTIMAL_SIZE_RATIO);
+    // Each type's number weight is its share of the volume over the volume of one sphere.
+    let coarse_weight = DENSEST_COARSE_VOLUME_FRACTION / volume(coarse.1);
+    let fine_weight =
+        (1. - DENSEST_COARSE_VOLUME_FRACTION) / volume(suggested_fine_radius.unwrap_or(fine.1));
+    Some(BimodalSuggestion {
+        coarse_volume_fraction: coarse.2 / (coarse.2 + fine.2),
+        coarse: (coarse.0, coarse.1),
+        fine: (fine.0, fine.1),
+        size_ratio,
+        suggested_fine_radius,
+        suggested_coarse_percentage: 100. * coarse_weight / (coarse_weight + fine_weight),
+    })
+}
+
+/// Provides the volume of `sphere` inside the origin-centered box with `half_extents`.
+fn clipped_volume(sphere: &Sphere, half_extents: [f64; 3]) -> f64 {
+    let r = sphere.radius as f64;
+    let c = [
+        sphere.center.x as f64,
+        sphere.center.y as f64,
+        sphere.center.z as f64,
+    ];
+    let inside = |i: usize| c[i].abs() + r <= half_extents[i];
+    let outside = |i: usize| c[i].abs() - r >= half_extents[i];
+    if (0..3).any(outside) {
+        return 0.;
+    }
+    if (0..3).all(inside) {
+        return 4. / 3. * PI * r.powi(3);
+    }
+    // Integrate the clipped chord length along x over a grid of (y, z) midpoints.
+    let step = 2. * r / CLIP_SLICES as f64;
+    let mut volume = 0.;
+    for k in 0..CLIP_SLICES {
+        let dz = -r + (k as f64 + 0.5) * step;
+        if (c[2] + dz).abs() > half_extents[2] {
+            continue;
+        }
+        for j in 0..CLIP_SLICES {
+            let dy = -r + (j as f64 + 0.5) * step;
+            let half_chord_sq = r * r - dy * dy - dz * dz;
+            if half_chord_sq <= 0. || (c[1] + dy).abs() > half_extents[1] {
+                continue;
+            }
+            let half_chord = half_chord_sq.sqrt();
+            let lo = (c[0] - half_chord).max(-half_extents[0]);
+            let hi = (c[0] + half_chord).min(half_extents[0]);
+            volume += (hi - lo).max(0.) * step * step;
+        }
+    }
+    volume
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use nalgebra::Point3;
+    use spherical_cow::shapes::Sphere;
+
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::analysis::{
+        clipped_volume, contact_graph, coordination, density_profile, periodic_volume_fraction,
+        pore_stats, radial_distribution, radius_moments, radius_range, regions, suggest_bimodal,
+        wall_corrected_volume_fraction, Coordination, RdfBins,
+    };
+    use crate::parsing::Spheres;
+
+    #[test]
+    fn clipped_half_sphere() {
+        let sphere = Sphere::new(Point3::new(1., 0., 0.), 1.).unwrap();
+        let half = 2. / 3. * PI;
+        assert!((clipped_volume(&sphere, [1., 5., 5.]) - half).abs() / half < 1e-2);
+        assert_eq!(
+            0.,
+            clipped_volume(&sphere, [1., 5., 5.].map(|h: f64| h - 5.5))
+        );
+    }
+
+    #[test]
+    fn periodic_fraction_ignores_walls() {
+        // A sphere touching the wall is excluded, while one in the center is counted in full.
+        let packed = vec![
+            Sphere::new(Point3::new(0., 0., 0.), 1.).unwrap(),
+            Sphere::new(Point3::new(9., 0., 0.), 1.).unwrap(),
+        ];
+        let fraction = periodic_volume_fraction(&packed, [10., 10., 10.]).unwrap();
+        assert!((fraction - 4. / 3. * PI / 4096.).abs() < 1e-9);
+        assert_eq!(None, periodic_volume_fraction(&packed, [1.5, 10., 10.]));
+    }
+
+    #[test]
+    fn wall_correction_follows_surface_to_volume() {
+        let packed = vec![Sphere::new(Point3::new(0., 0., 0.), 1.).unwrap()];
+        // A cube with A / V = 3 / 10 loses 15% of its volume to the wall layer.
+        let cube = wall_corrected_volume_fraction(&packed, [10., 10., 10.], 0.5).unwrap();
+        assert!((cube - 0.5 / 0.85).abs() < 1e-12);
+        // A slab of the same volume has a higher ratio, so is corrected more.
+        let slab = wall_corrected_volume_fraction(&packed, [20., 20., 2.5], 0.5).unwrap();
+        assert!(slab > cube);
+        assert!((slab - 0.5 / 0.75).abs() < 1e-12);
+        assert_eq!(
+            None,
+            wall_corrected_volume_fraction(&packed, [10., 10., 0.5], 0.5)
+        );
+        assert_eq!(
+            None,
+            wall_corrected_volume_fraction(&[], [10., 10., 10.], 0.5)
+        );
+    }
+
+    #[test]
+    fn profile_splits_spheres_between_slabs() {
+        // One sphere is split evenly between the two middle slabs, and the other is entirely in
+        // the top slab.
+        let packed = vec![
+            Sphere::new(Point3::new(0., 0., 0.), 1.).unwrap(),
+            Sphere::new(Point3::new(0., 0., 3.), 1.).unwrap(),
+        ];
+        let profile = density_profile(&packed, [2., 2., 4.], 4);
+        let sphere = 4. / 3. * PI;
+        let expected = [0., sphere / 2. / 32., sphere / 2. / 32., sphere / 32.];
+        for (slab, expected) in profile.iter().zip(expected) {
+            assert!((slab - expected).abs() < 1e-9, "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn rdf_uncorrelated_is_one() {
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let points: Vec<Sphere> = (0..4000)
+            .map(|_| {
+                let mut coord = || rng.gen_range(-10.0..10.0);
+                Sphere::new(Point3::new(coord(), coord(), coord()), 0.01).unwrap()
+            })
+            .collect();
+        let binning = RdfBins { bins: 4, r_max: 2. };
+        let rdf = radial_distribution(&points, [10., 10., 10.], binning).unwrap();
+        assert_eq!(vec![0.25, 0.75, 1.25, 1.75], rdf.r);
+        assert!(rdf.g.iter().all(|g| (g - 1.).abs() < 0.15), "{:?}", rdf.g);
+    }
+
+    #[test]
+    fn rdf_excludes_overlap_and_wall_references() {
+        let packed = vec![
+            Sphere::new(Point3::new(0., 0., 0.), 1.).unwrap(),
+            Sphere::new(Point3::new(2., 0., 0.), 1.).unwrap(),
+        ];
+        let binning = RdfBins { bins: 8, r_max: 4. };
+        let rdf = radial_distribution(&packed, [5., 5., 5.], binning).unwrap();
+        // Only the sphere at the origin is a reference, and it sees one neighbour at r = 2.
+        assert!(rdf.g[..4].iter().all(|&g| g == 0.));
+        assert!(rdf.g[4] > 0.);
+        assert_eq!(None, radial_distribution(&packed, [3., 5., 5.], binning));
+    }
+
+    #[test]
+    fn radius_moments_known() {
+        let spheres = |radii: &[f32]| -> Vec<Sphere> {
+            radii
+                .iter()
+                .map(|&r| Sphere::new(Point3::origin(), r).unwrap())
+                .collect()
+        };
+        // Radii of 1 and 2 in equal numbers are symmetric, with kurtosis 1; one radius of 4 among
+        // three of 1 has skewness 2 / sqrt(3) and kurtosis 7 / 3.
+        let (skewness, kurtosis) = radius_moments(&spheres(&[1., 2., 1., 2.])).unwrap();
+        assert!(skewness.abs() < 1e-12 && (kurtosis - 1.).abs() < 1e-12);
+        let (skewness, kurtosis) = radius_moments(&spheres(&[1., 1., 1., 4.])).unwrap();
+        assert!((skewness - 2. / 3f64.sqrt()).abs() < 1e-12);
+        assert!((kurtosis - 7. / 3.).abs() < 1e-12);
+        assert_eq!(None, radius_moments(&spheres(&[1., 1.])));
+        assert_eq!(None, radius_moments(&[]));
+    }
+
+    #[test]
+    fn pore_stats_match_brute_force() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let packed: Vec<Sphere> = (0..200)
+            .map(|_| {
+                let mut coord = || rng.gen_range(-5.0..5.0);
+                let center = Point3::new(coord(), coord(), coord());
+                Sphere::new(center, rng.gen_range(0.2..0.6)).unwrap()
+            })
+            .collect();
+        let half_extents = [5., 5., 5.];
+        let stats = pore_stats(
+            &packed,
+            half_extents,
+            2000,
+            &mut ChaCha8Rng::seed_from_u64(9),
+        )
+        .unwrap();
+        let mut replay = ChaCha8Rng::seed_from_u64(9);
+        let distances: Vec<f64> = (0..2000)
+            .filter_map(|_| {
+                let point = Point3::from(half_extents.map(|h: f32| replay.gen_range(-h..=h)));
+                let nearest = packed
+                    .iter()
+                    .map(|s| nalgebra::distance(&point, &s.center) - s.radius)
+                    .fold(f32::INFINITY, f32::min);
+                (nearest > 0.).then_some(nearest as f64)
+            })
+            .collect();
+        assert_eq!(2000, stats.samples);
+        assert_eq!(distances.len() as f64 / 2000., stats.empty_fraction);
+        let mean = distances.iter().sum::<f64>() / distances.len() as f64;
+        assert!((stats.mean_distance - mean).abs() < 1e-9);
+        let max = distances.iter().copied().fold(0., f64::max);
+        assert!((stats.r.last().unwrap() - max * (1. - 0.5 / 20.)).abs() < 1e-9);
+        assert!((stats.fraction.iter().sum::<f64>() - 1.).abs() < 1e-9);
+
+        let filled = [Sphere::new(Point3::new(0., 0., 0.), 10.).unwrap()];
+        assert_eq!(
+            None,
+            pore_stats(
+                &filled,
+                [1., 1., 1.],
+                100,
+                &mut ChaCha8Rng::seed_from_u64(9)
+            )
+        );
+    }
+
+    #[test]
+    fn radius_range_of_packed() {
+        let at = |radius| Sphere::new(Point3::new(0., 0., 0.), radius).unwrap();
+        assert_eq!(
+            Some((0.5, 3.)),
+            radius_range(&[at(1.), at(3.), at(0.5), at(2.)])
+        );
+        assert_eq!(Some((2., 2.)), radius_range(&[at(2.)]));
+        assert_eq!(None, radius_range(&[]));
+    }
+
+    #[test]
+    fn regions_of_centers() {
+        let at = |x, y, z| Sphere::new(Point3::new(x, y, z), 0.5).unwrap();
+        let packed = [
+            at(-1., -1., -1.),
+            at(1., -1., -1.),
+            at(-1., 1., 1.),
+            at(1., 1., 1.),
+            at(0., 0., 0.),
+            at(-5., 5., 0.),
+        ];
+        assert_eq!(vec![0, 1, 6, 7, 7, 6], regions(&packed, [2., 2., 2.], 2));
+        assert_eq!(
+            vec![0, 2, 24, 26, 13, 15],
+            regions(&packed, [2., 2., 2.], 3)
+        );
+        assert_eq!(vec![0; 6], regions(&packed, [2., 2., 2.], 1));
+        let disks = [at(-1., -1., 0.), at(1., 1., 0.)];
+        assert_eq!(vec![0, 3], regions(&disks, [2., 2., 0.], 2));
+    }
+
+    #[test]
+    fn radius_moments_exponential() {
+        // Exponentially distributed radii have skewness 2 and kurtosis 9.
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let packed: Vec<Sphere> = (0..200_000)
+            .map(|_| {
+                let radius = 1. - (1. - rng.gen::<f64>()).ln();
+                Sphere::new(Point3::origin(), radius as f32).unwrap()
+            })
+            .collect();
+        let (skewness, kurtosis) = radius_moments(&packed).unwrap();
+        assert!((skewness - 2.).abs() < 0.1, "{}", skewness);
+        assert!((kurtosis - 9.).abs() < 1., "{}", kurtosis);
+    }
+
+    #[test]
+    fn counts_touching_neighbours() {
+        let sphere = |x: f32, radius: f32| Sphere::new(Point3::new(x, 0., 0.), radius).unwrap();
+        // A chain of three touching spheres, one just short of touching the end of the chain, and
+        // one far from the rest.
+        let packed = [
+            sphere(-3., 1.),
+            sphere(-1., 1.),
+            sphere(0.5, 0.5),
+            sphere(1.6, 0.5),
+            sphere(8., 1.),
+        ];
+        assert_eq!(
+            Some(Coordination {
+                mean: 4. / 5.,
+                distribution: vec![2, 2, 1],
+            }),
+            coordination(&packed, [10., 2., 2.], 1e-4)
+        );
+        let loose = coordination(&packed, [10., 2., 2.], 0.2).unwrap();
+        assert_eq!(vec![1, 2, 2], loose.distribution);
+        assert_eq!(None, coordination(&[], [1., 1., 1.], 1e-4));
+        assert_eq!(
+            vec![[0, 1], [1, 2]],
+            contact_graph(&packed, [10., 2., 2.], 1e-4)
+        );
+        assert_eq!(
+            vec![[0, 1], [1, 2], [2, 3]],
+            contact_graph(&packed, [10., 2., 2.], 0.2)
+        );
+        assert!(contact_graph(&[], [1., 1., 1.], 1e-4).is_empty());
+    }
+
+    #[test]
+    fn suggests_bimodal_adjustments() {
+        let spheres: Spheres = r#"[
+            {"name": "dust", "radius": 0.1, "proportion": 10},
+            {"name": "small", "radius": 1.0, "proportion": 50},
+            {"name": "large", "radius": 2.0, "proportion": 40}
+        ]"#
+        .parse()
+        .unwrap();
+        let suggestion = suggest_bimodal(&spheres).unwrap();
+        assert_eq!((String::from("large"), 2.), suggestion.coarse);
+        assert_eq!((String::from("small"), 1.), suggestion.fine);
+        assert_eq!(2., suggestion.size_ratio);
+        assert!((suggestion.coarse_volume_fraction - 320. / 370.).abs() < 1e-12);
+        assert_eq!(Some(2. / 7.), suggestion.suggested_fine_radius);
+        let (coarse, fine) = (0.73 / 8., 0.27 * (7f64 / 2.).powi(3));
+        let expected = 100. * coarse / (coarse + fine);
+        assert!((suggestion.suggested_coarse_percentage - expected).abs() < 1e-9);
+
+        let wide: Spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 90},
+            {"name": "large", "radius": 10.0, "proportion": 10}
+        ]"#
+        .parse()
+        .unwrap();
+        assert_eq!(None, suggest_bimodal(&wide).unwrap().suggested_fine_radius);
+        let single: Spheres = r#"[{"name": "a", "radius": 1.0, "proportion": 100}]"#
+            .parse()
+            .unwrap();
+        assert_eq!(None, suggest_bimodal(&single));
+    }
+}
@@ -0,0 +1,108 @@
+//! A uniform grid over a cuboid container for finding the spheres near a point without comparing
+//! every pair of spheres.
+use nalgebra::Point3;
+use spherical_cow::shapes::Sphere;
+
+/// A grid of cells covering the origin-centered cuboid container with some half-extents, each
+/// listing the indices of the spheres whose centers are inside it.
+pub(crate) struct Grid {
+    half_extents: [f64; 3],
+    /// Side length of each cell along each axis, which is at least the reach the grid was built for
+    cell: [f64; 3],
+    dims: [usize; 3],
+    cells: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    /// Build a grid over `packed` in the container with `half_extents`, such that every sphere
+    /// whose center is within `reach` of a point is found by `Grid::near`.
+    ///
+    /// The number of cells is capped at a small multiple of the number of spheres, so a tiny
+    /// `reach` gives larger cells rather than using a lot of memory.
+    pub(crate) fn new(packed: &[Sphere], half_extents: [f32; 3], reach: f64) -> Self {
+        let half_extents = half_extents.map(|h| h as f64);
+        let max_dim = 2 * (packed.len() as f64).cbrt().ceil() as usize;
+        let dims = half_extents.map(|h| ((2. * h / reach) as usize).clamp(1, max_dim.max(1)));
+        let cell = [0, 1, 2].map(|i| 2. * half_extents[i] / dims[i] as f64);
+        let mut grid = Grid {
+            half_extents,
+            cell,
+            dims,
+            cells: vec![Vec::new(); dims[0] * dims[1] * dims[2]],
+        };
+        for (i, sphere) in packed.iter().enumerate() {
+            let index = grid.index(grid.cell_of(&sphere.center));
+            grid.cells[index].push(i);
+        }
+        grid
+    }
+
+    /// Provides the coordinates of the cell containing `point`, clamped to the grid.
+    fn cell_of(&self, point: &Point3<f32>) -> [usize; 3] {
+        let center = [point.x, point.y, point.z];
+        [0, 1, 2].map(|i| {
+            let offset = (center[i] as f64 + self.half_extents[i]) / self.cell[i];
+            (offset.max(0.) as usize).min(self.dims[i] - 1)
+        })
+    }
+
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (z * self.dims[1] + y) * self.dims[0] + x
+    }
+
+    /// Provides the indices of every sphere in the cells neighbouring the one containing the
+    /// center of `sphere`, which includes every sphere whose center is within the grid's reach of
+    /// it (and `sphere` itself, if it is in the grid).
+    pub(crate) fn near<'a>(&'a self, sphere: &Sphere) -> impl Iterator<Item = usize> + 'a {
+        self.near_point(&sphere.center)
+    }
+
+    /// Provides the indices of every sphere in the cells neighbouring the one containing `point`,
+    /// which includes every sphere whose center is within the grid's reach of it.
+    pub(crate) fn near_point<'a>(
+        &'a self,
+        point: &Point3<f32>,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let [cx, cy, cz] = self.cell_of(point);
+        let span = |c: usize, dim: usize| c.saturating_sub(1)..(c + 2).min(dim);
+        let (xs, ys) = (span(cx, self.dims[0]), span(cy, self.dims[1]));
+        span(cz, self.dims[2])
+            .flat_map(move |z| ys.clone().map(move |y| (y, z)))
+            .flat_map(move |(y, z)| xs.clone().map(move |x| [x, y, z]))
+            .flat_map(move |cell| self.cells[self.index(cell)].iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point3;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use spherical_cow::shapes::Sphere;
+
+    use crate::analysis::grid::Grid;
+
+    #[test]
+    fn near_matches_brute_force() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let packed: Vec<Sphere> = (0..500)
+            .map(|_| {
+                let mut coord = || rng.gen_range(-10.0..10.0);
+                Sphere::new(Point3::new(coord(), coord(), coord()), 0.1).unwrap()
+            })
+            .collect();
+        let reach = 1.5;
+        let grid = Grid::new(&packed, [10., 10., 10.], reach);
+        for sphere in &packed {
+            let mut found: Vec<usize> = grid
+                .near(sphere)
+                .filter(|&j| nalgebra::distance(&packed[j].center, &sphere.center) <= reach as f32)
+                .collect();
+            found.sort_unstable();
+            let expected: Vec<usize> = (0..packed.len())
+                .filter(|&j| nalgebra::distance(&packed[j].center, &sphere.center) <= reach as f32)
+                .collect();
+            assert_eq!(expected, found);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Simulates packing spheres of varying discrete proportions of different sizes into a container.
+use std::error::Error;
+
+pub mod analysis;
+pub mod output;
+pub mod packing;
+pub mod parsing;
+
+/// Parse `json` as spheres and pack them with the default `packing::PackOptions`, as the command
+/// line does when given no options and a seed of 0.
+///
+/// ```
+/// let output = sphere_pack_from_json::pack_str(
+///     r#"[
+///         {"name": "small", "radius": 1.0, "proportion": 60},
+///         {"name": "large", "radius": 2.0, "proportion": 40}
+///     ]"#,
+/// )
+/// .unwrap();
+/// assert!(output.spheres().len() > 100);
+/// assert!(output.volume_fraction() > 0.3);
+/// ```
+pub fn pack_str(json: &str) -> Result<packing::SimOutput, Box<dyn Error>> {
+    Ok(packing::pack(
+        &json.parse()?,
+        &packing::PackOptions::default(),
+    )?)
+}
@@ -1,24 +1,1370 @@
 use clap::clap_app;
 
-mod packing;
-mod parsing;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-use std::fs;
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+use serde::Serialize;
+use thiserror::Error;
+
+use sphere_pack_from_json::analysis::{self, RdfBins};
+use sphere_pack_from_json::output::{self, FieldMap, OutputFormat, SignificantDigits};
+use sphere_pack_from_json::packing::{self, Dimensionality, PackOptions, Precision, Sampler};
+use sphere_pack_from_json::parsing::{self, Format, Spheres};
+
+/// An enumeration of the errors that can occur while downloading an input, rather than parsing it.
+#[derive(Debug, Error)]
+enum FetchError {
+    #[error("failed to fetch '{0}'")]
+    Request(String, #[source] Box<ureq::Error>),
+    #[error("failed to read the response from '{0}'")]
+    Body(String, #[source] io::Error),
+}
+
+/// Download the body of the response to a GET request for `url`.
+fn fetch(url: &str) -> Result<String, FetchError> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| FetchError::Request(url.to_string(), Box::new(e)))?;
+    response
+        .into_string()
+        .map_err(|e| FetchError::Body(url.to_string(), e))
+}
+
+/// Read the contents of `path`, which is either a file or an `http://` or `https://` URL to
+/// download, providing them along with their format: `format`, or if `format` is None, the format
+/// declared by a `# format: <format>` first line (which is removed) or else implied by the
+/// extension, falling back to JSON.
+fn read_input(path: &str, format: Option<Format>) -> Result<(String, Format), Box<dyn Error>> {
+    let is_url = path.starts_with("http://") || path.starts_with("https://");
+    let contents = if is_url {
+        fetch(path)?
+    } else {
+        fs::read_to_string(path)?
+    };
+    // A URL's extension is that of its path, without any query or fragment.
+    let extension_path = if is_url {
+        path.split(['?', '#']).next().unwrap_or(path)
+    } else {
+        path
+    };
+    let (declared, contents) = Format::split_directive(&contents)?;
+    let format = format
+        .or(declared)
+        .or_else(|| Format::from_extension(Path::new(extension_path)))
+        .unwrap_or(Format::Json);
+    Ok((contents.to_string(), format))
+}
+
+/// Local JSON files at least this many bytes long are read with `Spheres::from_json_stream`, rather
+/// than whole.
+const STREAMING_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Read and validate spheres from `path` incrementally, if it's a local JSON file of at least
+/// `STREAMING_THRESHOLD` bytes, providing None for any other input, which is read whole instead.
+///
+/// A file starting with a `# format:` line is also read whole, as the line has to be removed.
+fn stream_spheres(path: &str, format: Option<Format>) -> Result<Option<Spheres>, Box<dyn Error>> {
+    let is_url = path.starts_with("http://") || path.starts_with("https://");
+    let format = format.or_else(|| Format::from_extension(Path::new(path)));
+    if is_url || format.unwrap_or(Format::Json) != Format::Json {
+        return Ok(None);
+    }
+    if fs::metadata(path)?.len() < STREAMING_THRESHOLD {
+        return Ok(None);
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'#') {
+        return Ok(None);
+    }
+    Ok(Some(Spheres::from_json_stream(reader)?))
+}
+
+/// How the proportions of an input are read.
+#[derive(Debug, Clone, Copy)]
+enum Proportions {
+    /// Percentages, which must sum to 100
+    Percentages,
+    /// Counts of spheres, which are normalized and must be whole numbers if `integer` is set
+    Counts { integer: bool },
+    /// Fractions of the spheres, which must sum to 1 to within `epsilon` and are normalized
+    Fractions { epsilon: f64 },
+}
+
+/// The spheres an input gives.
+enum Input {
+    /// Sphere types
+    Spheres(Spheres),
+    /// A continuous radius distribution, with the buckets its radii are reported in
+    Distribution(parsing::RadiusDistribution, Spheres),
+    /// Several scenarios of proportions for the same sphere types, each packed separately
+    Scenarios(Vec<Spheres>),
+}
+
+impl Input {
+    /// Provides the sphere types of this input, or what it is instead if it doesn't give one set of
+    /// them.
+    fn into_spheres(self) -> Result<Spheres, &'static str> {
+        match self {
+            Input::Spheres(spheres) => Ok(spheres),
+            Input::Distribution(..) => Err("a radius distribution"),
+            Input::Scenarios(_) => Err("several proportions scenarios"),
+        }
+    }
+}
+
+/// Parse `contents` as spheres in `format`, with proportions read as `proportions`. If `unchecked`
+/// is set, the spheres aren't validated.
+fn parse_spheres(
+    contents: &str,
+    format: Format,
+    proportions: Proportions,
+    unchecked: bool,
+) -> Result<Spheres, parsing::ParsingError> {
+    if let Proportions::Counts { integer } = proportions {
+        Spheres::from_reader_counts(contents.as_bytes(), format, integer)
+    } else if let Proportions::Fractions { epsilon } = proportions {
+        Spheres::from_reader_fractions(contents.as_bytes(), format, epsilon)
+    } else if unchecked {
+        Spheres::from_reader_unchecked(contents.as_bytes(), format)
+    } else {
+        Spheres::from_reader(contents.as_bytes(), format)
+    }
+}
+
+/// Read spheres from `path` as `read_input` does, with proportions read as `proportions`. If
+/// `unchecked` is set, the spheres aren't validated.
+///
+/// An input giving a continuous radius distribution rather than sphere types is read as the
+/// buckets its radii are reported in, provided along with the distribution, and one giving several
+/// proportions scenarios is read as the spheres of each, each read and validated on its own.
+fn read_spheres(
+    path: &str,
+    format: Option<Format>,
+    proportions: Proportions,
+    unchecked: bool,
+) -> Result<Input, Box<dyn Error>> {
+    let percentages = matches!(proportions, Proportions::Percentages);
+    if percentages && !unchecked {
+        if let Some(spheres) = stream_spheres(path, format)? {
+            return Ok(Input::Spheres(spheres));
+        }
+    }
+    let (contents, format) = read_input(path, format)?;
+    if let Some(distribution) = parsing::read_distribution(contents.as_bytes(), format)? {
+        let buckets = distribution.buckets();
+        return Ok(Input::Distribution(distribution, buckets));
+    }
+    if let Some(scenarios) = parsing::read_scenarios(contents.as_bytes(), format)? {
+        let scenarios = scenarios
+            .iter()
+            .enumerate()
+            .map(|(i, scenario)| {
+                parse_spheres(scenario, Format::Json, proportions, unchecked)
+                    .map_err(|e| format!("scenario {}: {}", i, e))
+            })
+            .collect::<Result<_, _>>()?;
+        return Ok(Input::Scenarios(scenarios));
+    }
+    Ok(Input::Spheres(parse_spheres(
+        &contents,
+        format,
+        proportions,
+        unchecked,
+    )?))
+}
+
+/// Options which change the sphere types of the input, so can't be used with a continuous radius
+/// distribution input.
+const TYPE_OPTIONS: [&str; 8] = [
+    "proportions_are_counts",
+    "proportions_are_fractions",
+    "no_validate",
+    "merge",
+    "scale",
+    "radius_filter",
+    "allowed_radii",
+    "two_stage",
+];
+
+/// Options which write or pack something other than one summary of each packing, so can't be used
+/// with several proportions scenarios.
+const SCENARIO_OPTIONS: [&str; 6] = [
+    "trials",
+    "estimate",
+    "manifest",
+    "snapshot_every",
+    "fill_container",
+    "include_positions",
+];
+
+/// Read radii from `radii` and proportions from `proportions`, each as `read_input` does, and join
+/// them by name into spheres, with proportions read as `kind`.
+///
+/// If `unchecked` is set, the joined spheres aren't validated.
+fn read_joined(
+    radii: &str,
+    proportions: &str,
+    format: Option<Format>,
+    kind: Proportions,
+    unchecked: bool,
+) -> Result<Spheres, Box<dyn Error>> {
+    let (contents, radii_format) = read_input(radii, format)?;
+    let radii = parsing::read_radii(contents.as_bytes(), radii_format)?;
+    let (contents, format) = read_input(proportions, format)?;
+    if let Proportions::Counts { integer } = kind {
+        let proportions = parsing::read_proportions(contents.as_bytes(), format)?;
+        Ok(Spheres::join_counts(radii, proportions, integer)?)
+    } else if let Proportions::Fractions { epsilon } = kind {
+        let proportions = parsing::read_proportions(contents.as_bytes(), format)?;
+        Ok(Spheres::join_fractions(radii, proportions, epsilon)?)
+    } else if unchecked {
+        let proportions = parsing::read_proportions(contents.as_bytes(), format)?;
+        Ok(Spheres::join_unchecked(radii, proportions)?)
+    } else {
+        let proportions = parsing::read_proportions(contents.as_bytes(), format)?;
+        Ok(Spheres::join(radii, proportions)?)
+    }
+}
+
+/// Parse `dims` of the form `x,y,z` into three positive half-side lengths, or for a two
+/// dimensional packing, of the form `x,y` into two followed by 0.
+fn parse_dims(dims: &str, dimensionality: Dimensionality) -> Result<[f32; 3], Box<dyn Error>> {
+    let parsed = dims
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<f32>, _>>()?;
+    let positive = parsed.iter().all(|d| d.is_finite() && *d > 0.0);
+    match (dimensionality, &parsed[..]) {
+        (Dimensionality::Three, &[x, y, z]) if positive => Ok([x, y, z]),
+        (Dimensionality::Two, &[x, y]) if positive => Ok([x, y, 0.]),
+        (Dimensionality::Three, _) => {
+            Err(format!("expected three positive lengths as x,y,z, got '{}'", dims).into())
+        }
+        (Dimensionality::Two, _) => {
+            Err(format!("expected two positive lengths as x,y, got '{}'", dims).into())
+        }
+    }
+}
+
+/// Parse `replicate` of the form `nx,ny,nz` (or `nx,ny` for two dimensional packings) into the
+/// positive number of copies along each axis.
+fn parse_replicate(
+    replicate: &str,
+    dimensionality: Dimensionality,
+) -> Result<[usize; 3], Box<dyn Error>> {
+    let parsed = replicate
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<usize>, _>>()?;
+    let positive = parsed.iter().all(|n| *n > 0);
+    match (dimensionality, &parsed[..]) {
+        (Dimensionality::Three, &[x, y, z]) if positive => Ok([x, y, z]),
+        (Dimensionality::Two, &[x, y]) if positive => Ok([x, y, 1]),
+        (Dimensionality::Three, _) => Err(format!(
+            "expected three positive replication counts as nx,ny,nz, got '{}'",
+            replicate
+        )
+        .into()),
+        (Dimensionality::Two, _) => Err(format!(
+            "expected two positive replication counts as nx,ny, got '{}'",
+            replicate
+        )
+        .into()),
+    }
+}
+
+/// Parse `rdf` of the form `bins:rmax` into a positive number of bins and a positive distance.
+fn parse_rdf(rdf: &str) -> Result<RdfBins, Box<dyn Error>> {
+    let (bins, r_max) = rdf
+        .split_once(':')
+        .ok_or_else(|| format!("expected bins:rmax, got '{}'", rdf))?;
+    let (bins, r_max) = (bins.parse()?, r_max.parse()?);
+    if bins > 0 && r_max > 0.0 && f64::is_finite(r_max) {
+        Ok(RdfBins { bins, r_max })
+    } else {
+        Err(format!("expected a positive number of bins and rmax, got '{}'", rdf).into())
+    }
+}
+
+/// Read the half-spaces bounding the container `container`, given as `mesh:<path>`, from a file in
+/// the format implied by its extension, falling back to JSON.
+fn parse_container(container: &str) -> Result<Vec<parsing::HalfSpace>, Box<dyn Error>> {
+    let path = container
+        .strip_prefix("mesh:")
+        .ok_or_else(|| format!("expected mesh:<path>, got '{}'", container))?;
+    Ok(parsing::read_half_spaces(
+        File::open(path)?,
+        Format::from_extension(path).unwrap_or(Format::Json),
+    )?)
+}
+
+/// Pair each of the comma-separated `formats` with the output path it is written to, which is
+/// `outputs` if there is one format and otherwise one of the comma-separated paths in `outputs`.
+fn parse_outputs<'a>(
+    formats: &str,
+    outputs: &'a str,
+) -> Result<Vec<(OutputFormat, &'a str)>, Box<dyn Error>> {
+    let formats = formats
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<OutputFormat>, _>>()?;
+    if formats.len() == 1 {
+        return Ok(vec![(formats[0], outputs)]);
+    }
+    let outputs: Vec<&str> = outputs.split(',').collect();
+    if formats.len() != outputs.len() {
+        return Err(format!(
+            "expected an output path for each of {} output formats, got {}",
+            formats.len(),
+            outputs.len()
+        )
+        .into());
+    }
+    Ok(formats.into_iter().zip(outputs).collect())
+}
+
+/// How JSON and JSONL summaries are written.
+#[derive(Debug, Clone)]
+struct SummaryStyle {
+    /// Renaming of the summaries' properties
+    fields: FieldMap,
+    /// Number of significant digits floating point properties are rounded to, if they're rounded
+    digits: Option<SignificantDigits>,
+    /// Whether summaries are written as canonical JSON
+    canonical: bool,
+}
+
+impl SummaryStyle {
+    /// Provides `summary` as JSON text on one line, with its properties renamed according to
+    /// `fields` and, if `digits` is given, its floating point properties rounded to that many
+    /// significant digits, as canonical JSON if `canonical` is set.
+    fn summarize<T: Serialize>(&self, summary: &T) -> serde_json::Result<String> {
+        Ok(self.render(&self.value(summary)?))
+    }
+
+    /// Provides `summary` as a JSON value, with its properties renamed and rounded as `summarize`
+    /// does.
+    fn value<T: Serialize>(&self, summary: &T) -> serde_json::Result<serde_json::Value> {
+        let mut value = self.fields.apply(summary)?;
+        if let Some(digits) = self.digits {
+            digits.apply(&mut value);
+        }
+        Ok(value)
+    }
+
+    /// Provides `value` as JSON text on one line, as canonical JSON if `canonical` is set.
+    fn render(&self, value: &serde_json::Value) -> String {
+        if self.canonical {
+            output::to_canonical_string(value)
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+/// How the outputs listing spheres write them.
+#[derive(Debug, Clone, Copy)]
+struct Positions {
+    /// Number of cells along each axis of the grid each sphere is tagged with its cell in, if
+    /// spheres are tagged
+    region_grid: Option<NonZeroUsize>,
+    /// Number of copies of the packing tiled along each axis, if it's replicated
+    replicate: Option<[usize; 3]>,
+    /// Whether JSON and JSONL summaries include the (untiled) sphere positions
+    in_summary: bool,
+}
+
+/// Write `result`, a packing of `spheres`, to `path` in `format`, writing JSON summaries in `style`
+/// and sphere positions as `positions` describes.
+///
+/// Replicated spheres are tagged with their cell in a grid over the whole tiled domain.
+fn write_result(
+    format: OutputFormat,
+    path: &str,
+    result: &packing::SimOutput,
+    spheres: &Spheres,
+    style: &SummaryStyle,
+    positions: Positions,
+) -> Result<(), Box<dyn Error>> {
+    let summary = || -> serde_json::Result<String> {
+        let mut value = style.value(result)?;
+        if positions.in_summary {
+            output::insert_positions(&mut value, result.spheres());
+        }
+        Ok(style.render(&value))
+    };
+    let replicate = positions.replicate.filter(|_| !format.is_summary());
+    let tiled =
+        replicate.map(|counts| output::replicate(result.spheres(), result.half_extents(), counts));
+    let packed = tiled.as_deref().unwrap_or(result.spheres());
+    let [nx, ny, nz] = replicate.unwrap_or([1, 1, 1]);
+    let [x, y, z] = result.half_extents();
+    let half_extents = [x * nx as f32, y * ny as f32, z * nz as f32];
+    let regions = positions
+        .region_grid
+        .map(|cells| analysis::regions(packed, half_extents, cells.get()));
+    match format {
+        OutputFormat::Json => fs::write(path, summary()?)?,
+        OutputFormat::Jsonl => output::write_jsonl_line(File::create(path)?, &summary()?)?,
+        OutputFormat::CsvSpheres => output::write_csv_spheres(
+            BufWriter::new(File::create(path)?),
+            packed,
+            regions.as_deref(),
+        )?,
+        OutputFormat::Xyz => output::write_xyz(
+            BufWriter::new(File::create(path)?),
+            packed,
+            spheres,
+            regions.as_deref(),
+        )?,
+        OutputFormat::Bin => output::write_bin(BufWriter::new(File::create(path)?), packed)?,
+        OutputFormat::Vtk => output::write_vtk(
+            BufWriter::new(File::create(path)?),
+            packed,
+            regions.as_deref(),
+        )?,
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => {
+            output::write_parquet(File::create(path)?, std::slice::from_ref(result))?
+        }
+    }
+    Ok(())
+}
+
+/// Spheres packed by `selftest`.
+const SELFTEST_SPHERES: &str = r#"[
+    {"name": "small", "radius": 1.0, "proportion": 60},
+    {"name": "large", "radius": 2.0, "proportion": 40}
+]"#;
+
+/// Check that packing is deterministic: that packing `SELFTEST_SPHERES` twice with the same seed
+/// writes byte-identical summaries and sphere positions, and that trials give identical results
+/// on one thread and on several.
+fn selftest() -> Result<(), Box<dyn Error>> {
+    let spheres: Spheres = SELFTEST_SPHERES.parse()?;
+    let options = PackOptions {
+        dims: Some([8., 8., 8.]),
+        seed: 1,
+        ..Default::default()
+    };
+    let written = || -> Result<(String, Vec<u8>), Box<dyn Error>> {
+        let result = packing::pack(&spheres, &options)?;
+        let mut positions = Vec::new();
+        output::write_csv_spheres(&mut positions, result.spheres(), None)?;
+        Ok((serde_json::to_string(&result)?, positions))
+    };
+    let (first, second) = (written()?, written()?);
+    if first.0 != second.0 {
+        return Err("packing twice with the same seed gave different summaries".into());
+    }
+    if first.1 != second.1 {
+        return Err("packing twice with the same seed gave different sphere positions".into());
+    }
+    let trials = |threads| -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string(&packing::pack_trials(
+            &spheres, &options, 4, None, threads,
+        )?)?)
+    };
+    if trials(1)? != trials(4)? {
+        return Err("trials gave different results on one thread and on several".into());
+    }
+    println!("selftest passed: packing is reproducible");
+    Ok(())
+}
+
+/// Print a table of the volume fractions of bimodal mixes of spheres with radius 1 and larger
+/// spheres, with a row for each of `--ratios` radius ratios from 1 to `--max-ratio` and a column
+/// for each of `--splits` percentages of large spheres evenly spaced between 0 and 100. Each
+/// fraction is the median over `--trials` packings, so that the odd packing which stops early
+/// doesn't skew it.
+fn calibrate(matches: &clap::ArgMatches) -> Result<(), Box<dyn Error>> {
+    let ratios: usize = matches.value_of("ratios").unwrap().parse()?;
+    let max_ratio: f64 = matches.value_of("max_ratio").unwrap().parse()?;
+    let splits: usize = matches.value_of("splits").unwrap().parse()?;
+    let trials: usize = matches.value_of("trials").unwrap().parse()?;
+    let threads = matches.value_of("threads").unwrap().parse()?;
+    if ratios == 0 || !(1..100).contains(&splits) || trials == 0 {
+        return Err("--ratios and --trials must be positive and --splits between 1 and 99".into());
+    }
+    if !(max_ratio >= 1. && max_ratio.is_finite()) {
+        return Err("--max-ratio must be a finite number of at least 1".into());
+    }
+    let options = PackOptions {
+        seed: matches.value_of("seed").unwrap().parse()?,
+        ..Default::default()
+    };
+    let percentages: Vec<usize> = (1..=splits).map(|k| 100 * k / (splits + 1)).collect();
+    print!("{:>8}", "ratio");
+    for percentage in &percentages {
+        print!("{:>8}", format!("{}%", percentage));
+    }
+    println!();
+    for i in 0..ratios {
+        let ratio = match ratios {
+            1 => max_ratio,
+            _ => 1. + (max_ratio - 1.) * i as f64 / (ratios - 1) as f64,
+        };
+        print!("{:>8.3}", ratio);
+        for percentage in &percentages {
+            let spheres: Spheres = serde_json::json!([
+                {"name": "small", "radius": 1.0, "proportion": 100 - percentage},
+                {"name": "large", "radius": ratio, "proportion": percentage},
+            ])
+            .to_string()
+            .parse()?;
+            let result = packing::pack_trials(&spheres, &options, trials, None, threads)?;
+            let mut fractions: Vec<f64> = result
+                .trials()
+                .iter()
+                .map(|t| t.volume_fraction())
+                .collect();
+            fractions.sort_by(f64::total_cmp);
+            print!("{:>8.4}", fractions[fractions.len() / 2]);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Check the JSON summary `summary` against the sphere positions written into it by
+/// `--include-positions`, as `output::verify_summary` does, printing each property which doesn't
+/// match.
+fn verify(matches: &clap::ArgMatches) -> Result<(), Box<dyn Error>> {
+    let summary = matches.value_of("summary").unwrap();
+    let recorded: serde_json::Value = serde_json::from_str(&fs::read_to_string(summary)?)?;
+    let diffs = output::verify_summary(&recorded)?;
+    if diffs.is_empty() {
+        println!("'{}' matches the spheres it records", summary);
+        return Ok(());
+    }
+    for diff in &diffs {
+        println!("{}", diff);
+    }
+    Err(format!(
+        "'{}' doesn't match the spheres it records (recorded -> recomputed above)",
+        summary
+    )
+    .into())
+}
+
+/// Print how the two dominant sphere types of the input could be adjusted to pack more densely as
+/// a bimodal mix, as `analysis::suggest_bimodal` suggests.
+fn suggest(matches: &clap::ArgMatches) -> Result<(), Box<dyn Error>> {
+    let format = matches.value_of("format").map(str::parse).transpose()?;
+    let spheres = read_spheres(
+        matches.value_of("input").unwrap(),
+        format,
+        Proportions::Percentages,
+        false,
+    )?
+    .into_spheres()
+    .map_err(|kind| format!("suggestions need sphere types, not {}", kind))?;
+    let suggestion = analysis::suggest_bimodal(&spheres)
+        .ok_or("suggestions need two sphere types of different radii")?;
+    let ((coarse, coarse_radius), (fine, fine_radius)) = (&suggestion.coarse, &suggestion.fine);
+    println!(
+        "coarse: {} (radius {}), fine: {} (radius {})",
+        coarse, coarse_radius, fine, fine_radius
+    );
+    match suggestion.suggested_fine_radius {
+        Some(radius) => println!(
+            "size ratio: {:.3}; a ratio of at least {} packs more densely, with a fine radius of \
+             {:.4} or less",
+            suggestion.size_ratio,
+            analysis::OPTIMAL_SIZE_RATIO,
+            radius
+        ),
+        None => println!(
+            "size ratio: {:.3}, already at least {}",
+            suggestion.size_ratio,
+            analysis::OPTIMAL_SIZE_RATIO
+        ),
+    }
+    println!(
+        "coarse share of volume: {:.3}; a share of {} packs most densely, which is {:.2}% coarse \
+         spheres by number{}, or proportions of {:.0} and {:.0} with --proportion-basis volume",
+        suggestion.coarse_volume_fraction,
+        analysis::DENSEST_COARSE_VOLUME_FRACTION,
+        suggestion.suggested_coarse_percentage,
+        if suggestion.suggested_fine_radius.is_some() {
+            " with the suggested fine radius"
+        } else {
+            ""
+        },
+        100. * analysis::DENSEST_COARSE_VOLUME_FRACTION,
+        100. * (1. - analysis::DENSEST_COARSE_VOLUME_FRACTION),
+    );
+    Ok(())
+}
+
+/// Provides the path the snapshot taken after relaxation pass `pass` is written to, beside `output`
+/// and named after it: `out.relax-000010.csv` for pass 10 with an output of `out.json`.
+fn snapshot_path(output: &str, pass: usize) -> PathBuf {
+    let path = Path::new(output);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.relax-{:06}.csv", stem, pass))
+}
+
+/// Ensure the directory `output` will be written to exists, creating it if `mkdir` is set.
+///
+/// This is checked before packing so that a mistyped path doesn't lose the results.
+fn prepare_output_dir(output: &str, mkdir: bool) -> Result<(), Box<dyn Error>> {
+    match Path::new(output).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.exists() => {
+            if mkdir {
+                fs::create_dir_all(dir)?;
+                Ok(())
+            } else {
+                Err(format!(
+                    "output directory '{}' does not exist; pass --mkdir to create it",
+                    dir.display()
+                )
+                .into())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Apply the options of `matches` which adjust the sphere types of the input to `spheres`: merging
+/// in other inputs, read in `format` with proportions read as `proportions`, and scaling and
+/// filtering their radii, after checking the number and radii of the types.
+fn adjust_spheres(
+    mut spheres: Spheres,
+    matches: &clap::ArgMatches,
+    format: Option<Format>,
+    proportions: Proportions,
+) -> Result<Spheres, Box<dyn Error>> {
+    if let Some(blends) = matches.values_of("merge") {
+        let mut parsed = vec![(spheres, 1.0)];
+        for blend in blends {
+            let (file, weight) = blend
+                .rsplit_once(':')
+                .ok_or_else(|| format!("expected file:weight, got '{}'", blend))?;
+            let blend = read_spheres(file, format, proportions, false)?
+                .into_spheres()
+                .map_err(|kind| format!("'{}' is {}, which can't be merged", file, kind))?;
+            parsed.push((blend, weight.parse()?));
+        }
+        spheres = parsing::merge(parsed)?;
+    }
+    spheres.check_max_types(matches.value_of("max_types").unwrap().parse()?)?;
+    if let Some(factor) = matches.value_of("scale") {
+        spheres = spheres.scale(factor.parse()?)?;
+    }
+    if let Some(filter) = matches.value_of("radius_filter") {
+        let (min, max) = filter
+            .split_once(':')
+            .ok_or_else(|| format!("expected min:max, got '{}'", filter))?;
+        spheres = spheres.filter_radius(min.parse()?, max.parse()?)?;
+    }
+    if let Some(allowed) = matches.value_of("allowed_radii") {
+        let allowed = allowed
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<f64>, _>>()?;
+        let tolerance = matches.value_of("radius_tolerance").unwrap().parse()?;
+        spheres.check_allowed_radii(&allowed, tolerance)?;
+    }
+    Ok(spheres)
+}
+
+/// Pack `spheres` once with `options`, benchmarking, retrying or packing in stages as `matches`
+/// asks to.
+fn pack_single(
+    spheres: &Spheres,
+    options: &PackOptions,
+    matches: &clap::ArgMatches,
+) -> Result<packing::SimOutput, Box<dyn Error>> {
+    Ok(if matches.is_present("benchmark") {
+        packing::benchmark(spheres, options, matches.is_present("warmup"))?
+    } else if let Some(tolerance) = matches.value_of("count_tolerance") {
+        packing::pack_to_count(spheres, options, tolerance.parse()?)?
+    } else if let Some(threshold) = matches.value_of("two_stage") {
+        packing::pack_two_stage(spheres, options, threshold.parse()?)?
+    } else if let Some(secs) = matches.value_of("persist") {
+        let timeout = Duration::try_from_secs_f64(secs.parse()?)?;
+        packing::pack_persistently(spheres, options, timeout)?
+    } else {
+        packing::pack(spheres, options)?
+    })
+}
+
+/// Pack each of `scenarios` as `pack_single` does, writing a summary of each packing, tagged with
+/// the index of its scenario, to each of `outputs` in `style`: as an array of them to JSON outputs
+/// and as a line each to JSONL outputs.
+///
+/// Warnings about each packing are given once every scenario has been packed, prefixed with the
+/// scenario they're about.
+fn pack_scenarios(
+    scenarios: &[Spheres],
+    options: &PackOptions,
+    matches: &clap::ArgMatches,
+    outputs: &[(OutputFormat, &str)],
+    style: &SummaryStyle,
+) -> Result<(), Box<dyn Error>> {
+    if !outputs
+        .iter()
+        .all(|(format, _)| matches!(format, OutputFormat::Json | OutputFormat::Jsonl))
+    {
+        return Err(
+            "only JSON or JSONL output is supported for several proportions scenarios".into(),
+        );
+    }
+    let min_expected = matches.value_of("min_expected_fraction").unwrap().parse()?;
+    let mut summaries = Vec::with_capacity(scenarios.len());
+    let mut warnings = Vec::new();
+    for (i, spheres) in scenarios.iter().enumerate() {
+        let result = pack_single(spheres, options, matches)?;
+        if matches.is_present("summary") {
+            println!("scenario {}:\n{}", i, result);
+        }
+        warnings.extend(
+            result_warnings(&result, min_expected)
+                .into_iter()
+                .map(|message| format!("scenario {}: {}", i, message)),
+        );
+        let mut summary = serde_json::Map::new();
+        summary.insert(String::from("scenario"), i.into());
+        if let serde_json::Value::Object(fields) = style.value(&result)? {
+            summary.extend(fields);
+        }
+        summaries.push(serde_json::Value::Object(summary));
+    }
+    for (format, output) in outputs {
+        if *format == OutputFormat::Jsonl {
+            let mut file = File::create(output)?;
+            for summary in &summaries {
+                output::write_jsonl_line(&mut file, &style.render(summary))?;
+            }
+        } else {
+            fs::write(
+                output,
+                style.render(&serde_json::Value::Array(summaries.clone())),
+            )?;
+        }
+    }
+    let strict = matches.is_present("strict");
+    for message in warnings {
+        warn(message, strict)?;
+    }
+    Ok(())
+}
+
+/// Print `message` on stderr as a warning, or if `strict` is set, provide it as an error instead.
+///
+/// Every warning goes through this, so that `--strict` makes any of them fail the run.
+fn warn(message: String, strict: bool) -> Result<(), Box<dyn Error>> {
+    if strict {
+        Err(message.into())
+    } else {
+        eprintln!("warning: {}", message);
+        Ok(())
+    }
+}
+
+/// Provides the warnings about `result`: that its fraction is below `min_expected`, which usually
+/// indicates a misconfigured input, and that a minimum count of spheres wasn't placed.
+fn result_warnings(result: &packing::SimOutput, min_expected: f64) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let fraction = result.volume_fraction();
+    if fraction < min_expected {
+        warnings.push(format!(
+            "volume fraction {} is below the expected minimum of {}; this is usually caused by \
+             an extreme radius ratio or by spheres too large for the container",
+            fraction, min_expected
+        ));
+    }
+    for unmet in result.min_counts().iter().filter(|m| !m.satisfied) {
+        warnings.push(format!(
+            "only {} spheres of '{}' were placed, fewer than its minimum count of {}",
+            unmet.placed, unmet.name, unmet.min_count
+        ));
+    }
+    warnings
+}
+
+/// Largest ratio of radii for which single precision resolves the volume of the smallest sphere
+/// against that of the largest: its 24-bit significand resolves one part in 2^24 = (2^8)³.
+const MAX_F32_RADIUS_RATIO: f64 = 256.;
+
+/// Provides a warning if the radii of `spheres` span more than computing metrics at `precision`
+/// can resolve, or are so small that their volumes underflow single precision.
+fn radii_warning(spheres: &Spheres, precision: Precision) -> Option<String> {
+    let (min, max) = spheres.radius_range();
+    let ratio = max / min;
+    if precision == Precision::F32 && ratio > MAX_F32_RADIUS_RATIO {
+        Some(format!(
+            "the largest radius is {} times the smallest, beyond the ratio of {} that single \
+             precision resolves the volumes of; pass --precision f64 for accurate metrics",
+            ratio, MAX_F32_RADIUS_RATIO
+        ))
+    } else if !(4. / 3. * std::f32::consts::PI * (min as f32).powi(3)).is_normal() {
+        Some(format!(
+            "the volume of a sphere of radius {} underflows the single precision the packer \
+             works in; pass --scale to pack larger radii",
+            min
+        ))
+    } else {
+        None
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
     let matches = clap_app!(pack =>
         (name: "pack")
         (version: "0.1")
         (about: "Attempts to pack spheres into a cube and reports result")
-        (@arg input: +required "Sets the input JSON file to use")
-        (@arg output: +required "Sets the filename of the output JSON file")
+        (@setting SubcommandsNegateReqs)
+        (@subcommand selftest =>
+            (about: "Checks that packing is reproducible, exiting with an error if it isn't"))
+        (@subcommand suggest =>
+            (about: "Suggests a size ratio and split of the input's two dominant sphere types which \
+                     would pack more densely, without packing")
+            (@arg input: +required "Sets the input file, or http(s) URL to download it from, to use")
+            (@arg format: --format +takes_value
+                "Sets the input format (json, yaml, toml, csv or json5) instead of using the extension"))
+        (@subcommand verify =>
+            (about: "Checks that a JSON summary's sphere count and volume fraction match the sphere \
+                     positions recorded in it, which lie in its container, exiting with an error \
+                     if they don't")
+            (@arg summary: +required
+                "Sets the JSON summary, written with --include-positions, to check"))
+        (@subcommand calibrate =>
+            (about: "Prints the volume fractions of bimodal mixes over a grid of radius ratios and \
+                     proportions of large spheres")
+            (@arg ratios: --ratios +takes_value default_value("4")
+                "Sets the number of radius ratios, evenly spaced from 1 to --max-ratio")
+            (@arg max_ratio: --("max-ratio") +takes_value default_value("4")
+                "Sets the largest ratio of the large spheres' radius to the small spheres'")
+            (@arg splits: --splits +takes_value default_value("3")
+                "Sets the number of percentages of large spheres, evenly spaced between 0 and 100")
+            (@arg trials: --trials +takes_value default_value("3")
+                "Sets the number of packings each fraction is the median of")
+            (@arg seed: --seed +takes_value default_value("0")
+                "Sets the seed of the random number generator")
+            (@arg threads: --threads +takes_value default_value("1")
+                "Sets the number of threads to run trials on"))
+        (@arg input: required_unless[print_schema list_formats compare radii]
+            "Sets the input file, or http(s) URL to download it from, to use")
+        (@arg radii: --radii +takes_value requires[proportions]
+            "Reads radii by name from this file instead of the input, which is then omitted")
+        (@arg proportions: --proportions +takes_value requires[radii]
+            "Reads proportions by name from this file, joining them with --radii")
+        (@arg format: --format +takes_value
+            "Sets the input format (json, yaml, toml, csv or json5) instead of using the extension")
+        (@arg proportions_are_counts: --("proportions-are-counts")
+            "Reads proportions as counts of spheres, which are normalized to sum to 100")
+        (@arg integer_proportions: --("integer-proportions") requires[proportions_are_counts]
+            "Rejects counts read with --proportions-are-counts which aren't whole numbers")
+        (@arg proportions_are_fractions: --("proportions-are-fractions")
+            conflicts_with[proportions_are_counts]
+            "Reads proportions as fractions of the spheres, which must sum to 1")
+        (@arg sum_epsilon: --("sum-epsilon") +takes_value requires[proportions_are_fractions]
+            "Sets how far from 1 the fractions may sum (1e-9 if not given)")
+        (@arg no_validate: --("no-validate")
+            conflicts_with[proportions_are_counts proportions_are_fractions merge scale radius_filter]
+            "Packs the input without checking its proportions sum to 100 or its radii are positive")
+        (@arg output: required_unless[print_schema list_formats compare radii]
+            "Sets the filename of the output file, or comma-separated filenames for several formats")
+        (@arg output_format: --("output-format") +takes_value default_value("json")
+            "Sets the output format: json, jsonl or parquet (if built with it), or csv-spheres or \
+             xyz or bin or vtk for sphere positions, or a comma-separated list of formats to write")
+        (@arg precision_digits: --("precision-digits") +takes_value
+            "Rounds the numbers in JSON and JSONL summaries to this many significant digits")
+        (@arg include_positions: --("include-positions") conflicts_with[trials]
+            "Also writes the sphere positions into JSON and JSONL summaries, for pack verify to check")
+        (@arg canonical: --canonical
+            "Writes JSON and JSONL summaries as canonical JSON, with sorted keys and fixed number formatting")
+        (@arg field_map: --("field-map") +takes_value
+            "Renames properties of JSON and JSONL summaries, given as key=newkey,...")
+        (@arg merge: --merge +takes_value +multiple
+            "Blends a file:weight pair into the input, which has a weight of 1")
+        (@arg scale: --scale +takes_value "Multiplies every input radius by this factor")
+        (@arg radius_filter: --("radius-filter") +takes_value
+            "Packs only the spheres with radii in min:max, re-normalizing their proportions")
+        (@arg max_types: --("max-types") +takes_value default_value("1000")
+            "Rejects inputs with more than this many sphere types")
+        (@arg allowed_radii: --("allowed-radii") +takes_value
+            "Rejects inputs with any radius other than those in r1,r2,...")
+        (@arg quantize: --quantize +takes_value
+            "Snaps each radius drawn from a radius distribution input to the nearest of r1,r2,...")
+        (@arg radius_tolerance: --("radius-tolerance") +takes_value default_value("1e-9")
+            "Sets how far a radius may be from an allowed radius, as a fraction of the latter")
+        (@arg proportion_basis: --("proportion-basis") +takes_value default_value("number")
+            "Sets what proportions are fractions of: number, volume, surface-area or mass")
+        (@arg precision: --precision +takes_value default_value("f64")
+            "Sets the precision metrics are computed and reported in: f32 or f64")
+        (@arg dims: --dims +takes_value
+            "Sets the container's half-side lengths as x,y,z (or x,y in 2d) instead of sizing a cube")
+        (@arg fill_container: --("fill-container") requires[dims]
+            conflicts_with[count_tolerance total_volume trials]
+            "Prints how many spheres filled the --dims container and the fraction they fill \
+             (packing with --dims always fills the container)")
+        (@arg dimensionality: --dimensionality +takes_value conflicts_with[estimate]
+            "Packs spheres into a cuboid (3d, the default) or disks into a rectangle (2d)")
+        (@arg container: --container +takes_value conflicts_with[estimate dims dimensionality]
+            "Packs into the convex polyhedron bounded by the half-spaces in mesh:<path>")
+        (@arg init_from: --("init-from") +takes_value
+            "Starts packing from the spheres placed in this file instead of from scratch")
+        (@arg obstacles: --obstacles +takes_value requires[dims] conflicts_with[count_tolerance two_stage]
+            "Packs around the fixed spheres placed in this file, which are counted in the volume fraction")
+        (@arg relax: --relax +takes_value
+            "Compacts the packing this many times and packs more spheres into the freed space")
+        (@arg snapshot_every: --("snapshot-every") +takes_value requires[relax]
+            conflicts_with[trials]
+            "Writes the sphere positions after every this many relaxation passes to numbered files")
+        (@arg track_growth: --("track-growth") +takes_value
+            "Also reports the fraction filled after every this many spheres placed")
+        (@arg sort_output: --("sort-output")
+            "Sorts per-type properties and sphere positions by radius so outputs can be diffed")
+        (@arg tag_spheres_by_region: --("tag-spheres-by-region") conflicts_with[trials]
+            "Labels each sphere in csv-spheres, xyz and vtk outputs with the grid cell its center is in")
+        (@arg region_grid: --("region-grid") +takes_value requires[tag_spheres_by_region]
+            "Sets the number of cells along each axis of the region grid (2, for octants)")
+        (@arg replicate: --replicate +takes_value conflicts_with[trials container]
+            "Tiles the written sphere positions nx,ny,nz times along each axis (nx,ny in 2d)")
+        (@arg explain: --explain
+            "Also reports why packing stopped and how the positions tried for new spheres fared")
+        (@arg summary: --summary conflicts_with[trials]
+            "Also prints a readable summary of the packing")
+        (@arg seed: --seed +takes_value "Sets the seed of the random number generator")
+        (@arg sample_seed: --("sample-seed") +takes_value
+            "Sets the seed radii are drawn with, separately from placements (defaults to --seed)")
+        (@arg placement_seed: --("placement-seed") +takes_value
+            "Sets the seed placements are chosen with, separately from radii (defaults to --seed)")
+        (@arg quasi_random: --("quasi-random")
+            "Draws radii and placements from a Halton sequence instead of pseudo-random numbers")
+        (@arg trials: --trials +takes_value "Sets the number of packing trials to run")
+        (@arg threads: --threads +takes_value default_value("1")
+            "Sets the number of threads to run trials on")
+        (@arg confidence: --confidence +takes_value requires[trials]
+            "Sets the confidence level of the interval reported for the mean volume fraction")
+        (@arg drop_outliers: --("drop-outliers") +takes_value requires[trials]
+            "Drops trials whose volume fraction is more than this many standard deviations below \
+             the mean from the statistics")
+        (@arg max_runtime_total: --("max-runtime-total") +takes_value requires[trials]
+            "Stops starting new trials after this many seconds")
+        (@arg min_expected_fraction: --("min-expected-fraction") +takes_value default_value("0.3")
+            "Sets the volume fraction below which a warning is printed")
+        (@arg min_fraction_to_succeed: --("min-fraction-to-succeed") +takes_value
+            "Exits with an error if the volume fraction is below this, instead of writing the result")
+        (@arg periodic: --periodic
+            "Also estimates the volume fraction with periodic instead of wall boundaries")
+        (@arg gravity: --gravity
+            "Settles spheres toward the bottom of the container, reporting the density profile")
+        (@arg density_profile: --("density-profile") +takes_value
+            "Also reports the volume fraction of each of this many slabs along the z axis")
+        (@arg wall_correction: --("wall-correction")
+            "Also estimates the bulk volume fraction by correcting for the walls' surface area")
+        (@arg rdf: --rdf +takes_value
+            "Also computes the radial distribution function in bins:rmax equal bins up to rmax")
+        (@group contacts =>
+            (@attributes +multiple)
+            (@arg coordination: --coordination
+                "Also reports how many others each sphere touches, as the mean and distribution")
+            (@arg contact_graph: --("contact-graph")
+                "Also reports the pairs of spheres which touch, by their index in the sphere positions"))
+        (@arg pore_stats: --("pore-stats") +takes_value
+            "Also reports the distribution of pore sizes, sampled at this many random points")
+        (@arg contact_epsilon: --("contact-epsilon") +takes_value requires[contacts]
+            "Sets the gap, relative to the sum of their radii, within which spheres touch (1e-4)")
+        (@arg total_volume: --("total-volume") +takes_value
+            conflicts_with[count_tolerance dimensionality]
+            "Stops packing once the spheres' total volume reaches this, sizing the cube to hold it")
+        (@arg two_stage: --("two-stage") +takes_value
+            conflicts_with[trials benchmark count_tolerance persist init_from dimensionality total_volume]
+            "Packs the types with at least this radius first, then the smaller types into the voids")
+        (@arg persist: --persist +takes_value
+            conflicts_with[trials benchmark count_tolerance sample_seed placement_seed]
+            "Packs again with the next seed whenever packing fails, for up to this many seconds")
+        (@arg count_tolerance: --("count-tolerance") +takes_value
+            conflicts_with[trials benchmark dims container]
+            "Resizes the container and packs again until the count is within this fraction of the \
+             target")
+        (@arg benchmark: --benchmark conflicts_with[trials]
+            "Also reports how long packing took and how many spheres were packed per second")
+        (@arg warmup: --warmup requires[benchmark]
+            "Packs once without timing before benchmarking")
+        (@arg print_schema: --("print-schema")
+            "Prints a JSON Schema describing the input format and exits")
+        (@arg list_formats: --("list-formats")
+            "Prints the supported input and output formats and exits")
+        (@arg compare: --compare +takes_value number_of_values(2) value_names(&["a", "b"])
+            "Prints how the numeric properties of two JSON results differ and exits")
+        (@arg dump_config: --("dump-config")
+            "Prints the resolved packing options as JSON and exits without packing")
+        (@arg estimate: --estimate
+            "Writes an estimate of the volume fraction instead of packing")
+        (@arg manifest: --manifest +takes_value conflicts_with[estimate dump_config]
+            "Also writes the command line, resolved options, version, input hash and time of the run \
+             to this JSON file")
+        (@arg no_clobber: --("no-clobber") "Exits with an error if the output file already exists")
+        (@arg mkdir: --mkdir "Creates the output file's directory if it doesn't exist")
+        (@arg strict: --strict visible_alias("warn-as-error")
+            "Exits with an error instead of printing any warning")
     )
     .get_matches();
-    let input = matches.value_of("input").unwrap();
-    let json =
-        fs::read_to_string(input)?;
-    let spheres = json.parse()?;
-    let sim_result = packing::pack(&spheres)?;
-    let output = matches.value_of("output").unwrap();
-    fs::write(output, serde_json::to_string(&sim_result)?)?;
+    if matches.subcommand_matches("selftest").is_some() {
+        return selftest();
+    }
+    if let Some(matches) = matches.subcommand_matches("calibrate") {
+        return calibrate(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("suggest") {
+        return suggest(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("verify") {
+        return verify(matches);
+    }
+    if matches.is_present("print_schema") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&parsing::input_schema())?
+        );
+        return Ok(());
+    }
+    if matches.is_present("list_formats") {
+        println!("input formats:");
+        for format in Format::ALL {
+            match format.aliases() {
+                [] => println!("  {}", format.name()),
+                aliases => println!("  {} (or {})", format.name(), aliases.join(", ")),
+            }
+        }
+        println!("output formats:");
+        for format in OutputFormat::ALL {
+            println!("  {}", format.name());
+        }
+        return Ok(());
+    }
+    if let Some(mut files) = matches.values_of("compare") {
+        let mut read = || -> Result<serde_json::Value, Box<dyn Error>> {
+            Ok(serde_json::from_str(&fs::read_to_string(
+                files.next().unwrap(),
+            )?)?)
+        };
+        let (a, b) = (read()?, read()?);
+        for diff in output::diff_summaries(&a, &b) {
+            println!("{}", diff);
+        }
+        return Ok(());
+    }
+    let format = matches.value_of("format").map(str::parse).transpose()?;
+    let proportions = if matches.is_present("proportions_are_fractions") {
+        let epsilon: f64 = matches.value_of("sum_epsilon").unwrap_or("1e-9").parse()?;
+        if !(epsilon >= 0.0 && epsilon.is_finite()) {
+            return Err(format!(
+                "expected a non-negative, finite --sum-epsilon, got {}",
+                epsilon
+            )
+            .into());
+        }
+        Proportions::Fractions { epsilon }
+    } else if matches.is_present("proportions_are_counts") {
+        Proportions::Counts {
+            integer: matches.is_present("integer_proportions"),
+        }
+    } else {
+        Proportions::Percentages
+    };
+    let unchecked = matches.is_present("no_validate");
+    // Without an input file, the only path given, which is read as the input, is the output.
+    let (input, output) = match (matches.value_of("radii"), matches.value_of("output")) {
+        (Some(radii), None) => (
+            Input::Spheres(read_joined(
+                radii,
+                matches.value_of("proportions").unwrap(),
+                format,
+                proportions,
+                unchecked,
+            )?),
+            matches
+                .value_of("input")
+                .ok_or("expected the filename of the output file")?,
+        ),
+        (Some(_), Some(_)) => {
+            return Err(
+                "--radii and --proportions replace the input file, which can't be given \
+                        as well"
+                    .into(),
+            )
+        }
+        (None, output) => (
+            read_spheres(
+                matches.value_of("input").unwrap(),
+                format,
+                proportions,
+                unchecked,
+            )?,
+            output.unwrap(),
+        ),
+    };
+    let tagged = matches!(input, Input::Scenarios(_));
+    if let Some(option) = SCENARIO_OPTIONS
+        .iter()
+        .find(|o| tagged && matches.is_present(o))
+    {
+        return Err(format!(
+            "--{} can't be used with several proportions scenarios",
+            option.replace('_', "-")
+        )
+        .into());
+    }
+    let (scenarios, distribution) = match input {
+        Input::Spheres(spheres) => (vec![spheres], None),
+        Input::Distribution(distribution, buckets) => (vec![buckets], Some(distribution)),
+        Input::Scenarios(scenarios) => (scenarios, None),
+    };
+    if distribution.is_some() {
+        if let Some(option) = TYPE_OPTIONS.iter().find(|o| matches.is_present(o)) {
+            return Err(format!(
+                "--{} can't be used with a radius distribution input",
+                option.replace('_', "-")
+            )
+            .into());
+        }
+    }
+    let mut scenarios = scenarios
+        .into_iter()
+        .map(|spheres| adjust_spheres(spheres, &matches, format, proportions))
+        .collect::<Result<Vec<_>, _>>()?;
+    let dimensionality = match matches.value_of("dimensionality") {
+        Some(dimensionality) => dimensionality.parse()?,
+        None => Dimensionality::Three,
+    };
+    let contact_epsilon = match matches.value_of("contact_epsilon") {
+        Some(epsilon) => epsilon.parse()?,
+        None => packing::CONTACT_EPSILON,
+    };
+    let options = PackOptions {
+        dims: matches
+            .value_of("dims")
+            .map(|dims| parse_dims(dims, dimensionality))
+            .transpose()?,
+        total_volume: matches
+            .value_of("total_volume")
+            .map(str::parse)
+            .transpose()?,
+        dimensionality,
+        sort_output: matches.is_present("sort_output"),
+        explain: matches.is_present("explain"),
+        sampler: if matches.is_present("quasi_random") {
+            Sampler::Halton
+        } else {
+            Sampler::PseudoRandom
+        },
+        seed: match matches.value_of("seed") {
+            Some(seed) => seed.parse()?,
+            None => rand::random(),
+        },
+        sample_seed: matches
+            .value_of("sample_seed")
+            .map(str::parse)
+            .transpose()?,
+        placement_seed: matches
+            .value_of("placement_seed")
+            .map(str::parse)
+            .transpose()?,
+        basis: matches.value_of("proportion_basis").unwrap().parse()?,
+        periodic: matches.is_present("periodic"),
+        wall_correction: matches.is_present("wall_correction"),
+        gravity: matches.is_present("gravity"),
+        density_profile: matches
+            .value_of("density_profile")
+            .map(str::parse)
+            .transpose()?,
+        rdf: matches.value_of("rdf").map(parse_rdf).transpose()?,
+        coordination: matches
+            .is_present("coordination")
+            .then_some(contact_epsilon),
+        contact_graph: matches
+            .is_present("contact_graph")
+            .then_some(contact_epsilon),
+        pore_samples: matches.value_of("pore_stats").map(str::parse).transpose()?,
+        precision: matches.value_of("precision").unwrap().parse()?,
+        relax: matches
+            .value_of("relax")
+            .map(str::parse)
+            .transpose()?
+            .unwrap_or(0),
+        snapshot_every: matches
+            .value_of("snapshot_every")
+            .map(str::parse)
+            .transpose()?,
+        track_growth: matches
+            .value_of("track_growth")
+            .map(str::parse)
+            .transpose()?,
+        mesh: matches
+            .value_of("container")
+            .map(parse_container)
+            .transpose()?
+            .unwrap_or_default(),
+        initial: match matches.value_of("init_from") {
+            Some(path) => parsing::read_positions(
+                File::open(path)?,
+                Format::from_extension(path).unwrap_or(Format::Json),
+            )?,
+            None => Vec::new(),
+        },
+        obstacles: match matches.value_of("obstacles") {
+            Some(path) => parsing::read_positions(
+                File::open(path)?,
+                Format::from_extension(path).unwrap_or(Format::Json),
+            )?,
+            None => Vec::new(),
+        },
+        distribution,
+        quantize: matches
+            .value_of("quantize")
+            .map(|radii| radii.split(',').map(str::parse).collect())
+            .transpose()?,
+        min_fraction: matches
+            .value_of("min_fraction_to_succeed")
+            .map(str::parse)
+            .transpose()?,
+        ..Default::default()
+    };
+    if matches.is_present("dump_config") {
+        println!("{}", serde_json::to_string_pretty(&options)?);
+        return Ok(());
+    }
+    let strict = matches.is_present("strict");
+    // Scenarios share their sphere types' radii, so the first's are those of every one.
+    if let Some(message) = radii_warning(&scenarios[0], options.precision) {
+        warn(message, strict)?;
+    }
+    let outputs = parse_outputs(matches.value_of("output_format").unwrap(), output)?;
+    let manifest = matches.value_of("manifest");
+    for output in outputs.iter().map(|(_, output)| *output).chain(manifest) {
+        prepare_output_dir(output, matches.is_present("mkdir"))?;
+        if matches.is_present("no_clobber") && Path::new(output).exists() {
+            return Err(format!("output file '{}' already exists", output).into());
+        }
+    }
+    let style = SummaryStyle {
+        fields: match matches.value_of("field_map") {
+            Some(mappings) => mappings.parse()?,
+            None => FieldMap::default(),
+        },
+        digits: matches
+            .value_of("precision_digits")
+            .map(str::parse)
+            .transpose()?,
+        canonical: matches.is_present("canonical"),
+    };
+    let positions = Positions {
+        region_grid: match matches.value_of("region_grid") {
+            Some(cells) => Some(cells.parse()?),
+            None => matches
+                .is_present("tag_spheres_by_region")
+                .then(|| NonZeroUsize::new(2).unwrap()),
+        },
+        replicate: matches
+            .value_of("replicate")
+            .map(|counts| parse_replicate(counts, options.dimensionality))
+            .transpose()?,
+        in_summary: matches.is_present("include_positions"),
+    };
+    if tagged {
+        return pack_scenarios(&scenarios, &options, &matches, &outputs, &style);
+    }
+    // Any other input gives one set of spheres.
+    let spheres = scenarios.remove(0);
+    if matches.is_present("estimate") {
+        let output = match outputs[..] {
+            [(_, output)] => output,
+            _ => return Err("--estimate writes only one output".into()),
+        };
+        options.basis.check(&spheres)?;
+        let [x, y, z] = packing::container_half_extents(&spheres, &options);
+        let container_volume = 8. * x as f64 * y as f64 * z as f64;
+        let estimate = serde_json::json!({
+            "estimated_volume_fraction":
+                packing::estimate_volume_fraction(&spheres, options.basis, container_volume),
+            "container_volume": container_volume,
+        });
+        fs::write(output, estimate.to_string())?;
+        return Ok(());
+    }
+    let min_expected = matches.value_of("min_expected_fraction").unwrap().parse()?;
+    let warnings: Vec<String> = match matches.value_of("trials") {
+        Some(_) if !outputs.iter().all(|(format, _)| format.is_summary()) => {
+            return Err(
+                "only JSON, JSONL or Parquet output is supported for multiple trials".into(),
+            );
+        }
+        Some(trials) => {
+            let budget = match matches.value_of("max_runtime_total") {
+                Some(secs) => Some(Duration::try_from_secs_f64(secs.parse()?)?),
+                None => None,
+            };
+            let threads = matches.value_of("threads").unwrap().parse()?;
+            let trials = trials.parse()?;
+            let mut streams = outputs
+                .iter()
+                .filter(|(format, _)| *format == OutputFormat::Jsonl)
+                .map(|(_, output)| File::create(output))
+                .collect::<io::Result<Vec<File>>>()?;
+            let mut sim_result =
+                packing::pack_trials_with(&spheres, &options, trials, budget, threads, |result| {
+                    let line = style.summarize(result)?;
+                    streams
+                        .iter_mut()
+                        .try_for_each(|file| output::write_jsonl_line(file, &line))
+                })?;
+            if let Some(level) = matches.value_of("confidence") {
+                sim_result.set_confidence_level(level.parse()?)?;
+            }
+            if let Some(k) = matches.value_of("drop_outliers") {
+                sim_result.drop_outliers(k.parse()?)?;
+            }
+            for (format, output) in &outputs {
+                match format {
+                    OutputFormat::Jsonl => {}
+                    #[cfg(feature = "parquet")]
+                    OutputFormat::Parquet => {
+                        output::write_parquet(File::create(output)?, sim_result.trials())?
+                    }
+                    _ => fs::write(output, style.summarize(&sim_result)?)?,
+                }
+            }
+            sim_result
+                .trials()
+                .iter()
+                .flat_map(|result| result_warnings(result, min_expected))
+                .collect()
+        }
+        None => {
+            let sim_result = pack_single(&spheres, &options, &matches)?;
+            for (format, output) in &outputs {
+                write_result(*format, output, &sim_result, &spheres, &style, positions)?;
+            }
+            for (pass, snapshot) in sim_result.snapshots() {
+                let path = snapshot_path(outputs[0].1, *pass);
+                output::write_csv_spheres(BufWriter::new(File::create(path)?), snapshot, None)?;
+            }
+            if matches.is_present("summary") {
+                println!("{}", sim_result);
+            }
+            if matches.is_present("fill_container") {
+                println!("{}", sim_result.fill_report());
+            }
+            result_warnings(&sim_result, min_expected)
+        }
+    };
+    if let Some(path) = manifest {
+        let command_line = std::env::args().collect();
+        let manifest = output::Manifest::new(command_line, &options, &spheres, SystemTime::now())?;
+        fs::write(path, serde_json::to_string_pretty(&manifest)?)?;
+    }
+    for message in warnings {
+        warn(message, strict)?;
+    }
     Ok(())
 }
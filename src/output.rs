@@ -0,0 +1,1092 @@
+//! Module used for writing packed sphere positions in formats other than the JSON summary, for
+//! renaming the properties of summaries, for comparing summaries, and for recording how a packing
+//! was run.
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nalgebra::Vector3;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use spherical_cow::shapes::Sphere;
+use thiserror::Error;
+
+#[cfg(feature = "parquet")]
+use crate::packing::SimOutput;
+use crate::packing::{PackOptions, FIELDS};
+use crate::parsing::{PlacedSphere, Spheres};
+
+#[derive(Debug, Error)]
+/// An enumeration of the errors that can occur while choosing how to write results.
+pub enum OutputError {
+    #[error("unknown output format '{0}'")]
+    UnknownFormat(String),
+    #[error("expected a field mapping of the form key=newkey, got '{0}'")]
+    InvalidFieldMapping(String),
+    #[error("unknown output field '{0}'")]
+    UnknownField(String),
+    #[error("output field '{0}' is mapped more than once")]
+    DuplicateFieldMapping(String),
+    #[error("expected a number of significant digits from 1 to 17, got '{0}'")]
+    InvalidDigits(String),
+    #[error("summary has no numeric '{0}' to verify")]
+    MissingField(&'static str),
+    #[error("summary has no sphere positions to verify; write it with --include-positions")]
+    MissingPositions,
+    #[cfg(feature = "parquet")]
+    #[error("failed to build Arrow columns")]
+    FailedToBuildColumns(#[from] arrow_schema::ArrowError),
+    #[cfg(feature = "parquet")]
+    #[error("failed to write Parquet")]
+    FailedToWriteParquet(#[from] parquet::errors::ParquetError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An enumeration of the formats results can be written in.
+pub enum OutputFormat {
+    /// The JSON summary of the packing
+    Json,
+    /// One JSON summary per line, each written as soon as its packing completes
+    Jsonl,
+    /// One `x,y,z,radius` row per packed sphere, after a header row
+    CsvSpheres,
+    /// The XYZ format: a line with the sphere count, a comment line, then one
+    /// `type x y z radius` line per packed sphere, where `type` is the index of the input sphere
+    /// type with the nearest radius
+    Xyz,
+    /// A binary blob for graphics consumers: the sphere count as a `u32`, then the `f32` values
+    /// `x`, `y`, `z` and `radius` of each packed sphere, all little-endian
+    Bin,
+    /// The legacy VTK format, as ASCII `POLYDATA` with a vertex at each packed sphere's center and
+    /// a `radius` point data array, for glyph rendering in ParaView
+    Vtk,
+    /// A Parquet file with one row per packing, holding the JSON summary's scalar properties
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl OutputFormat {
+    /// Every format results can be written in, as built.
+    pub const ALL: &'static [OutputFormat] = &[
+        OutputFormat::Json,
+        OutputFormat::Jsonl,
+        OutputFormat::CsvSpheres,
+        OutputFormat::Xyz,
+        OutputFormat::Bin,
+        OutputFormat::Vtk,
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet,
+    ];
+
+    /// Provides the name this format is given by with `--output-format`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::CsvSpheres => "csv-spheres",
+            OutputFormat::Xyz => "xyz",
+            OutputFormat::Bin => "bin",
+            OutputFormat::Vtk => "vtk",
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Whether this format summarizes each packing rather than listing its spheres, and so can
+    /// hold the results of several trials.
+    pub fn is_summary(self) -> bool {
+        match self {
+            OutputFormat::Json | OutputFormat::Jsonl => true,
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => true,
+            OutputFormat::CsvSpheres
+            | OutputFormat::Xyz
+            | OutputFormat::Bin
+            | OutputFormat::Vtk => false,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = OutputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| OutputError::UnknownFormat(s.to_string()))
+    }
+}
+
+/// A renaming of some of the properties of JSON summaries, parsed from a comma-separated list of
+/// `key=newkey` pairs, whose keys are each one of `packing::FIELDS`. Properties which aren't
+/// mapped keep their names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldMap(Vec<(String, String)>);
+
+impl FieldMap {
+    /// Provides `summary` as JSON with its mapped properties renamed, in place so that properties
+    /// stay in the same order. For the results of several trials, which have a `trials` list, the
+    /// properties of each trial and of the `aggregate` statistics are renamed instead.
+    pub fn apply<T: Serialize>(&self, summary: &T) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(summary)?;
+        if let Some(object) = value.as_object_mut() {
+            match object.get_mut("trials") {
+                Some(serde_json::Value::Array(trials)) => {
+                    trials
+                        .iter_mut()
+                        .filter_map(serde_json::Value::as_object_mut)
+                        .for_each(|trial| self.rename(trial));
+                    if let Some(aggregate) = object
+                        .get_mut("aggregate")
+                        .and_then(serde_json::Value::as_object_mut)
+                    {
+                        self.rename(aggregate);
+                    }
+                }
+                _ => self.rename(object),
+            }
+        }
+        Ok(value)
+    }
+
+    fn rename(&self, object: &mut serde_json::Map<String, serde_json::Value>) {
+        *object = std::mem::take(object)
+            .into_iter()
+            .map(
+                |(key, value)| match self.0.iter().find(|(from, _)| *from == key) {
+                    Some((_, to)) => (to.clone(), value),
+                    None => (key, value),
+                },
+            )
+            .collect();
+    }
+}
+
+impl FromStr for FieldMap {
+    type Err = OutputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mappings: Vec<(String, String)> = Vec::new();
+        for pair in s.split(',') {
+            let (from, to) = match pair.split_once('=') {
+                Some((from, to)) if !from.is_empty() && !to.is_empty() => (from, to),
+                _ => return Err(OutputError::InvalidFieldMapping(pair.to_string())),
+            };
+            if !FIELDS.contains(&from) {
+                return Err(OutputError::UnknownField(from.to_string()));
+            }
+            if mappings.iter().any(|(f, t)| f == from || t == to) {
+                return Err(OutputError::DuplicateFieldMapping(from.to_string()));
+            }
+            mappings.push((from.to_string(), to.to_string()));
+        }
+        Ok(FieldMap(mappings))
+    }
+}
+
+/// A number of significant digits, from 1 to 17, to round the floating point properties of JSON
+/// summaries to, for tidier outputs and smaller diffs. 17 digits are always enough to represent an
+/// `f64` exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignificantDigits(usize);
+
+impl SignificantDigits {
+    /// Round every floating point number in `summary`, however deeply nested, to this many
+    /// significant digits, leaving integers (such as counts and seeds) as they are.
+    pub fn apply(self, summary: &mut serde_json::Value) {
+        match summary {
+            serde_json::Value::Number(number) if number.is_f64() => {
+                let rounded = number
+                    .as_f64()
+                    .and_then(|n| format!("{:.*e}", self.0 - 1, n).parse().ok())
+                    .and_then(serde_json::Number::from_f64);
+                if let Some(rounded) = rounded {
+                    *number = rounded;
+                }
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|v| self.apply(v)),
+            serde_json::Value::Object(object) => object.values_mut().for_each(|v| self.apply(v)),
+            _ => {}
+        }
+    }
+}
+
+impl FromStr for SignificantDigits {
+    type Err = OutputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(digits) if (1..=17).contains(&digits) => Ok(SignificantDigits(digits)),
+            _ => Err(OutputError::InvalidDigits(s.to_string())),
+        }
+    }
+}
+
+/// A struct describing how one numeric property differs between two summaries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub a: f64,
+    pub b: f64,
+}
+
+impl FieldDiff {
+    /// Provides the change from `a` to `b`.
+    pub fn absolute(&self) -> f64 {
+        self.b - self.a
+    }
+
+    /// Provides the change from `a` to `b` as a percentage of `a`, or None if `a` is 0.
+    pub fn percent(&self) -> Option<f64> {
+        (self.a != 0.).then(|| 100. * self.absolute() / self.a.abs())
+    }
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {} ({:+}",
+            self.field,
+            self.a,
+            self.b,
+            self.absolute()
+        )?;
+        match self.percent() {
+            Some(percent) => write!(f, ", {:+.3}%)", percent),
+            None => write!(f, ")"),
+        }
+    }
+}
+
+/// Compare the numeric top-level properties of the JSON summaries `a` and `b`, providing a
+/// difference for each property which is a number in both, in alphabetical order.
+pub fn diff_summaries(a: &serde_json::Value, b: &serde_json::Value) -> Vec<FieldDiff> {
+    let (a, b) = match (a.as_object(), b.as_object()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Vec::new(),
+    };
+    let mut diffs: Vec<FieldDiff> = a
+        .iter()
+        .filter_map(|(field, value)| {
+            Some(FieldDiff {
+                field: field.clone(),
+                a: value.as_f64()?,
+                b: b.get(field)?.as_f64()?,
+            })
+        })
+        .collect();
+    diffs.sort_by(|x, y| x.field.cmp(&y.field));
+    diffs
+}
+
+/// Relative difference within which a recomputed property matches the one recorded in a summary,
+/// which allows for the property having been computed in single precision.
+pub const VERIFY_TOLERANCE: f64 = 1e-5;
+
+/// Add `packed`, the spheres of a packing, to its JSON `summary` as a `positions` array of objects
+/// with an `x`, `y`, `z` and `radius`, which `verify_summary` checks the summary against.
+pub fn insert_positions(summary: &mut serde_json::Value, packed: &[Sphere]) {
+    let positions: Vec<PlacedSphere> = packed
+        .iter()
+        .map(|s| PlacedSphere {
+            x: s.center.x,
+            y: s.center.y,
+            z: s.center.z,
+            radius: s.radius,
+        })
+        .collect();
+    summary["positions"] =
+        serde_json::to_value(positions).expect("sphere positions are always serializable");
+}
+
+/// Check the JSON `summary` of a packing against the sphere positions `insert_positions` added to
+/// it, providing how each property recomputed from them differs from the one recorded, for every
+/// property which differs by more than `VERIFY_TOLERANCE`, with `a` recorded and `b` recomputed.
+///
+/// The properties checked are `sphere_count`, the `volume_fraction` (or for a two dimensional
+/// packing, `area_fraction`) as the total volume (or area) of the spheres relative to the
+/// `container_volume` (plus the `obstacle_fraction`, if there were obstacles, as they aren't in
+/// the positions), and `packed_volume` if it was recorded. Every sphere must also lie within the
+/// recorded `half_extents` (in x and y only, for a two dimensional packing), which is checked as
+/// `spheres_outside_container`, recorded as 0. The summary's properties must have their original
+/// names.
+pub fn verify_summary(summary: &serde_json::Value) -> Result<Vec<FieldDiff>, OutputError> {
+    let number = |field: &'static str| summary.get(field).and_then(serde_json::Value::as_f64);
+    let required = |field| number(field).ok_or(OutputError::MissingField(field));
+    let container_volume = required("container_volume")?;
+    let half_extents: [f64; 3] = summary
+        .get("half_extents")
+        .and_then(|extents| serde_json::from_value(extents.clone()).ok())
+        .ok_or(OutputError::MissingField("half_extents"))?;
+    let placed: Vec<PlacedSphere> = summary
+        .get("positions")
+        .and_then(|positions| serde_json::from_value(positions.clone()).ok())
+        .ok_or(OutputError::MissingPositions)?;
+    let two_dimensional = number("area_fraction").is_some();
+    let (fraction_field, measure): (&'static str, fn(f64) -> f64) = if two_dimensional {
+        ("area_fraction", |r| std::f64::consts::PI * r.powi(2))
+    } else {
+        ("volume_fraction", |r| {
+            4. / 3. * std::f64::consts::PI * r.powi(3)
+        })
+    };
+    let filled: f64 = placed.iter().map(|s| measure(s.radius as f64)).sum();
+    let axes = if two_dimensional { 2 } else { 3 };
+    let outside = placed
+        .iter()
+        .filter(|s| {
+            let center = [s.x, s.y, s.z];
+            (0..axes).any(|axis| {
+                let reach = center[axis].abs() as f64 + s.radius as f64;
+                reach > half_extents[axis] * (1. + VERIFY_TOLERANCE)
+            })
+        })
+        .count();
+    let mut recomputed = vec![
+        (
+            "sphere_count",
+            required("sphere_count")?,
+            placed.len() as f64,
+        ),
+        (
+            fraction_field,
+            required(fraction_field)?,
+            filled / container_volume + number("obstacle_fraction").unwrap_or(0.),
+        ),
+        ("spheres_outside_container", 0., outside as f64),
+    ];
+    if let Some(volume) = number("packed_volume") {
+        recomputed.push(("packed_volume", volume, filled));
+    }
+    Ok(recomputed
+        .into_iter()
+        .filter(|(_, a, b)| (a - b).abs() > VERIFY_TOLERANCE * a.abs().max(b.abs()))
+        .map(|(field, a, b)| FieldDiff {
+            field: field.to_string(),
+            a,
+            b,
+        })
+        .collect())
+}
+
+/// Write `line`, a result as JSON text without line breaks, to `writer` as one line and flush it,
+/// so that the line is saved even if the process is later interrupted.
+pub fn write_jsonl_line<W: Write>(mut writer: W, line: &str) -> io::Result<()> {
+    writeln!(writer, "{}", line)?;
+    writer.flush()
+}
+
+/// Provides `summary` as canonical JSON, which is the same text for equal summaries on every run
+/// and platform so that it can be hashed. It is compact JSON, with no whitespace outside strings,
+/// and:
+///
+/// - the properties of every object are sorted by name, comparing names byte by byte as UTF-8;
+/// - strings escape only `"`, `\` and control characters, as `\b`, `\f`, `\n`, `\r` and `\t` or
+///   otherwise as `\u00xx` with lowercase hexadecimal digits;
+/// - integers are written in decimal, with a `-` if negative and no leading zeros;
+/// - other numbers are written in scientific notation with the fewest significant digits which
+///   read back as the same `f64`: one non-zero digit, then if there are more digits a `.` and
+///   those digits without trailing zeros, then `e` and the exponent in decimal with a `-` if
+///   negative and no `+` or leading zeros. So 0.5 is written `5e-1`, 1234.5 as `1.2345e3` and 8.0
+///   as `8e0`; zero is `0e0`, whatever its sign.
+pub fn to_canonical_string(summary: &serde_json::Value) -> String {
+    let mut canonical = String::new();
+    write_canonical(&mut canonical, summary);
+    canonical
+}
+
+/// Append `value` to `canonical` as `to_canonical_string` describes.
+fn write_canonical(canonical: &mut String, value: &serde_json::Value) {
+    use serde_json::Value;
+
+    match value {
+        Value::Number(number) => match (number.as_u64(), number.as_i64(), number.as_f64()) {
+            (Some(n), _, _) => canonical.push_str(&n.to_string()),
+            (_, Some(n), _) => canonical.push_str(&n.to_string()),
+            (_, _, Some(0.)) => canonical.push_str("0e0"),
+            (_, _, Some(n)) => canonical.push_str(&format!("{:e}", n)),
+            _ => canonical.push_str(&number.to_string()),
+        },
+        Value::Array(values) => {
+            canonical.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    canonical.push(',');
+                }
+                write_canonical(canonical, value);
+            }
+            canonical.push(']');
+        }
+        Value::Object(properties) => {
+            let mut sorted: Vec<_> = properties.iter().collect();
+            sorted.sort_by(|a, b| a.0.cmp(b.0));
+            canonical.push('{');
+            for (i, (name, value)) in sorted.into_iter().enumerate() {
+                if i > 0 {
+                    canonical.push(',');
+                }
+                canonical.push_str(&Value::from(name.as_str()).to_string());
+                canonical.push(':');
+                write_canonical(canonical, value);
+            }
+            canonical.push('}');
+        }
+        // Nulls, booleans and strings only have one compact representation.
+        _ => canonical.push_str(&value.to_string()),
+    }
+}
+
+/// A record of how a packing was run, with everything needed to reproduce or audit it.
+#[derive(Debug, Serialize)]
+pub struct Manifest<'a> {
+    /// Arguments the tool was run with, starting with the program itself
+    pub command_line: Vec<String>,
+    /// Version of this crate which ran the packing
+    pub version: &'static str,
+    /// Packing options after resolving defaults, including the seed if it was chosen at random
+    pub options: &'a PackOptions,
+    /// Hex-encoded SHA-256 hash of the canonical JSON form of the spheres packed, after any
+    /// merging, scaling or filtering
+    pub input_hash: String,
+    /// When the packing was run, in UTC as RFC 3339 to the second
+    pub timestamp: String,
+}
+
+impl<'a> Manifest<'a> {
+    /// Create the manifest of packing `spheres` with `options`, as run by `command_line` at `time`.
+    pub fn new(
+        command_line: Vec<String>,
+        options: &'a PackOptions,
+        spheres: &Spheres,
+        time: SystemTime,
+    ) -> serde_json::Result<Self> {
+        let canonical = to_canonical_string(&serde_json::to_value(spheres)?);
+        // Times before the epoch can only come from a misset clock, and are written as the epoch.
+        let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Ok(Manifest {
+            command_line,
+            version: env!("CARGO_PKG_VERSION"),
+            options,
+            input_hash: format!("{:x}", Sha256::digest(canonical.as_bytes())),
+            timestamp: rfc3339(seconds),
+        })
+    }
+}
+
+/// Provides the time `seconds` after the Unix epoch in UTC as RFC 3339, such as
+/// `2021-03-04T05:06:07Z`.
+fn rfc3339(seconds: u64) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // The civil date of a day count, from Howard Hinnant's `civil_from_days`, with eras of 400
+    // years starting on the 1st of March so that leap days come last.
+    let days = days + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Provides `packed`, in the origin-centered cuboid container with `half_extents`, tiled `counts`
+/// times along each axis: each copy is offset from its neighbours by the container's side length,
+/// and the copies are arranged so that the tiled domain is centered on the origin too, with half
+/// extents `counts` times the container's. The copies are listed one after another, with x varying
+/// fastest.
+pub fn replicate(packed: &[Sphere], half_extents: [f32; 3], counts: [usize; 3]) -> Vec<Sphere> {
+    let [nx, ny, nz] = counts;
+    // The offset of copy `i` of `n` along an axis with half extent `h`.
+    let offset = |i: usize, n: usize, h: f32| (2. * i as f32 + 1. - n as f32) * h;
+    let mut tiled = Vec::with_capacity(packed.len() * nx * ny * nz);
+    for k in 0..nz {
+        for j in 0..ny {
+            for i in 0..nx {
+                let shift = Vector3::new(
+                    offset(i, nx, half_extents[0]),
+                    offset(j, ny, half_extents[1]),
+                    offset(k, nz, half_extents[2]),
+                );
+                tiled.extend(packed.iter().map(|sphere| {
+                    let mut copy = sphere.clone();
+                    copy.center += shift;
+                    copy
+                }));
+            }
+        }
+    }
+    tiled
+}
+
+/// Write `packed` to `writer` as CSV, one sphere at a time, with a `region` column if `regions`
+/// gives the region of each sphere.
+pub fn write_csv_spheres<W: Write>(
+    mut writer: W,
+    packed: &[Sphere],
+    regions: Option<&[usize]>,
+) -> io::Result<()> {
+    let region = |i: usize| regions.map(|r| format!(",{}", r[i])).unwrap_or_default();
+    writeln!(writer, "x,y,z,radius{}", regions.map_or("", |_| ",region"))?;
+    for (i, sphere) in packed.iter().enumerate() {
+        let c = sphere.center;
+        writeln!(
+            writer,
+            "{},{},{},{}{}",
+            c.x,
+            c.y,
+            c.z,
+            sphere.radius,
+            region(i)
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write `packed` to `writer` in the XYZ format, one sphere at a time, labelling each sphere with
+/// the index of the type in `types` it was drawn from, and followed by its region if `regions`
+/// gives the region of each sphere.
+pub fn write_xyz<W: Write>(
+    mut writer: W,
+    packed: &[Sphere],
+    types: &Spheres,
+    regions: Option<&[usize]>,
+) -> io::Result<()> {
+    let region = |i: usize| regions.map(|r| format!(" {}", r[i])).unwrap_or_default();
+    writeln!(writer, "{}", packed.len())?;
+    writeln!(
+        writer,
+        "type x y z radius{}",
+        regions.map_or("", |_| " region")
+    )?;
+    for (i, sphere) in packed.iter().enumerate() {
+        let c = sphere.center;
+        let kind = types.nearest_type(sphere.radius as f64);
+        writeln!(
+            writer,
+            "{} {} {} {} {}{}",
+            kind,
+            c.x,
+            c.y,
+            c.z,
+            sphere.radius,
+            region(i)
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write `packed` to `writer` as a binary blob, one sphere at a time: the number of spheres as a
+/// little-endian `u32`, followed for each sphere by its center's `x`, `y` and `z` and its radius as
+/// little-endian `f32`s, so that sphere `i` starts at byte `4 + 16 i`.
+///
+/// Fails without writing anything if there are more spheres than a `u32` can count.
+pub fn write_bin<W: Write>(mut writer: W, packed: &[Sphere]) -> io::Result<()> {
+    let count = u32::try_from(packed.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many spheres to count in the binary header",
+        )
+    })?;
+    writer.write_all(&count.to_le_bytes())?;
+    for sphere in packed {
+        let c = sphere.center;
+        for value in [c.x, c.y, c.z, sphere.radius] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    writer.flush()
+}
+
+/// Write `packed` to `writer` in the legacy VTK format (version 3.0, ASCII), as `POLYDATA` with one
+/// point and vertex cell per sphere at its center, and point data arrays holding each sphere's
+/// `radius` and, if `regions` gives the region of each sphere, its `region`.
+///
+/// Each section lists every sphere, so `packed` is passed over once per section rather than the
+/// spheres being buffered.
+pub fn write_vtk<W: Write>(
+    mut writer: W,
+    packed: &[Sphere],
+    regions: Option<&[usize]>,
+) -> io::Result<()> {
+    let n = packed.len();
+    writeln!(writer, "# vtk DataFile Version 3.0")?;
+    writeln!(writer, "sphere_pack packing of {} spheres", n)?;
+    writeln!(writer, "ASCII")?;
+    writeln!(writer, "DATASET POLYDATA")?;
+    writeln!(writer, "POINTS {} float", n)?;
+    for sphere in packed {
+        let c = sphere.center;
+        writeln!(writer, "{} {} {}", c.x, c.y, c.z)?;
+    }
+    writeln!(writer, "VERTICES {} {}", n, 2 * n)?;
+    for i in 0..n {
+        writeln!(writer, "1 {}", i)?;
+    }
+    writeln!(writer, "POINT_DATA {}", n)?;
+    writeln!(writer, "SCALARS radius float 1")?;
+    writeln!(writer, "LOOKUP_TABLE default")?;
+    for sphere in packed {
+        writeln!(writer, "{}", sphere.radius)?;
+    }
+    if let Some(regions) = regions {
+        writeln!(writer, "SCALARS region int 1")?;
+        writeln!(writer, "LOOKUP_TABLE default")?;
+        for region in regions {
+            writeln!(writer, "{}", region)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Write `results` to `writer` as Parquet, one row per packing, with a column for each scalar
+/// property of the JSON summary and `half_extent_x`, `half_extent_y` and `half_extent_z` columns.
+/// Properties which are absent from a summary are null.
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: Write + Send>(writer: W, results: &[SimOutput]) -> Result<(), OutputError> {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        ArrayRef, Float32Array, Float64Array, RecordBatch, StringArray, UInt64Array,
+    };
+    use parquet::arrow::ArrowWriter;
+
+    let f64s = |f: fn(&SimOutput) -> Option<f64>| -> ArrayRef {
+        Arc::new(results.iter().map(f).collect::<Float64Array>())
+    };
+    let u64s = |f: fn(&SimOutput) -> Option<u64>| -> ArrayRef {
+        Arc::new(results.iter().map(f).collect::<UInt64Array>())
+    };
+    let half_extent = |axis: usize| -> ArrayRef {
+        Arc::new(Float32Array::from_iter_values(
+            results.iter().map(|r| r.half_extents[axis]),
+        ))
+    };
+    let batch = RecordBatch::try_from_iter([
+        ("volume_fraction", f64s(|r| Some(r.volume_fraction()))),
+        ("sa_to_vol", f64s(|r| Some(r.sa_to_vol))),
+        ("proportion_entropy", f64s(|r| Some(r.proportion_entropy))),
+        ("sphere_count", u64s(|r| Some(r.sphere_count as u64))),
+        ("target_count", u64s(|r| r.target_count.map(|c| c as u64))),
+        ("count_ratio", f64s(|r| r.count_ratio)),
+        ("half_extent_x", half_extent(0)),
+        ("half_extent_y", half_extent(1)),
+        ("half_extent_z", half_extent(2)),
+        (
+            "periodic_volume_fraction",
+            f64s(|r| r.periodic_volume_fraction),
+        ),
+        ("seed", u64s(|r| Some(r.seed))),
+        (
+            "config_hash",
+            Arc::new(StringArray::from_iter_values(
+                results.iter().map(|r| &r.config_hash),
+            )),
+        ),
+    ])?;
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use nalgebra::Point3;
+    use spherical_cow::shapes::Sphere;
+
+    use crate::output::{
+        diff_summaries, insert_positions, replicate, rfc3339, to_canonical_string, verify_summary,
+        write_bin, write_csv_spheres, write_jsonl_line, write_vtk, write_xyz, FieldDiff, FieldMap,
+        Manifest, OutputError, OutputFormat, SignificantDigits,
+    };
+    use crate::packing::{self, PackOptions};
+    use crate::parsing;
+
+    fn packed() -> Vec<Sphere> {
+        vec![
+            Sphere::new(Point3::new(0., 1., 2.), 5.).unwrap(),
+            Sphere::new(Point3::new(-1., 0.5, 0.), 400.).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn replicates_tiles() {
+        let packed = vec![Sphere::new(Point3::new(0.5, -1., 0.), 0.5).unwrap()];
+        let tiled = replicate(&packed, [1., 2., 3.], [2, 1, 3]);
+        let centers: Vec<[f32; 3]> = tiled
+            .iter()
+            .map(|s| [s.center.x, s.center.y, s.center.z])
+            .collect();
+        assert_eq!(
+            vec![
+                [-0.5, -1., -6.],
+                [1.5, -1., -6.],
+                [-0.5, -1., 0.],
+                [1.5, -1., 0.],
+                [-0.5, -1., 6.],
+                [1.5, -1., 6.],
+            ],
+            centers
+        );
+        assert!(tiled.iter().all(|s| s.radius == 0.5));
+        assert_eq!(packed, replicate(&packed, [1., 2., 3.], [1, 1, 1]));
+    }
+
+    #[test]
+    fn output_format_names() {
+        for &format in OutputFormat::ALL {
+            assert_eq!(format, format.name().parse().unwrap());
+        }
+        assert!("csv".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn csv_spheres() {
+        let mut written = Vec::new();
+        write_csv_spheres(&mut written, &packed(), None).unwrap();
+        assert_eq!(
+            "x,y,z,radius\n0,1,2,5\n-1,0.5,0,400\n",
+            String::from_utf8(written).unwrap()
+        );
+        let mut tagged = Vec::new();
+        write_csv_spheres(&mut tagged, &packed(), Some(&[7, 0])).unwrap();
+        assert_eq!(
+            "x,y,z,radius,region\n0,1,2,5,7\n-1,0.5,0,400,0\n",
+            String::from_utf8(tagged).unwrap()
+        );
+    }
+
+    #[test]
+    fn bin_spheres() {
+        let mut written = Vec::new();
+        write_bin(&mut written, &packed()).unwrap();
+        assert_eq!(4 + 2 * 16, written.len());
+        assert_eq!([2, 0, 0, 0], written[..4]);
+        let values: Vec<f32> = written[4..]
+            .chunks(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert_eq!(vec![0., 1., 2., 5., -1., 0.5, 0., 400.], values);
+    }
+
+    #[test]
+    fn vtk_spheres() {
+        let mut written = Vec::new();
+        write_vtk(&mut written, &packed(), None).unwrap();
+        let header = "# vtk DataFile Version 3.0\nsphere_pack packing of 2 spheres\nASCII\n\
+                      DATASET POLYDATA\n";
+        let geometry = "POINTS 2 float\n0 1 2\n-1 0.5 0\nVERTICES 2 4\n1 0\n1 1\n";
+        let radii = "POINT_DATA 2\nSCALARS radius float 1\nLOOKUP_TABLE default\n5\n400\n";
+        assert_eq!(
+            format!("{}{}{}", header, geometry, radii),
+            String::from_utf8(written).unwrap()
+        );
+        let mut tagged = Vec::new();
+        write_vtk(&mut tagged, &packed(), Some(&[7, 0])).unwrap();
+        assert!(String::from_utf8(tagged)
+            .unwrap()
+            .ends_with("400\nSCALARS region int 1\nLOOKUP_TABLE default\n7\n0\n"));
+    }
+
+    #[test]
+    fn verifies_summaries() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 60},
+            {"name": "large", "radius": 2.0, "proportion": 40}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([6., 6., 6.]),
+            seed: 1,
+            ..Default::default()
+        };
+        let result = packing::pack(&spheres, &options).unwrap();
+        let mut summary = serde_json::to_value(&result).unwrap();
+        insert_positions(&mut summary, result.spheres());
+        assert_eq!(
+            result.spheres().len(),
+            summary["positions"].as_array().unwrap().len()
+        );
+        assert_eq!(Vec::<FieldDiff>::new(), verify_summary(&summary).unwrap());
+        let obstacle = parsing::PlacedSphere {
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            radius: 2.,
+        };
+        let obstructed = packing::pack(
+            &spheres,
+            &PackOptions {
+                obstacles: vec![obstacle],
+                ..options
+            },
+        )
+        .unwrap();
+        let mut obstructed_summary = serde_json::to_value(&obstructed).unwrap();
+        insert_positions(&mut obstructed_summary, obstructed.spheres());
+        assert_eq!(
+            Vec::<FieldDiff>::new(),
+            verify_summary(&obstructed_summary).unwrap()
+        );
+
+        let mut tampered = summary.clone();
+        tampered["volume_fraction"] = serde_json::json!(0.9);
+        let diffs = verify_summary(&tampered).unwrap();
+        assert_eq!(1, diffs.len());
+        assert_eq!(
+            ("volume_fraction", 0.9),
+            (diffs[0].field.as_str(), diffs[0].a)
+        );
+        assert!((diffs[0].b - result.volume_fraction()).abs() < 1e-9);
+        let mut missing = summary.clone();
+        missing["positions"].as_array_mut().unwrap().remove(0);
+        assert_eq!(
+            vec!["sphere_count", "volume_fraction"],
+            verify_summary(&missing)
+                .unwrap()
+                .iter()
+                .map(|d| d.field.as_str())
+                .collect::<Vec<_>>()
+        );
+        let mut moved = summary.clone();
+        moved["positions"][0]["x"] = serde_json::json!(5.5);
+        assert_eq!(
+            vec![FieldDiff {
+                field: String::from("spheres_outside_container"),
+                a: 0.,
+                b: 1.,
+            }],
+            verify_summary(&moved).unwrap()
+        );
+        tampered.as_object_mut().unwrap().remove("container_volume");
+        assert!(matches!(
+            verify_summary(&tampered),
+            Err(OutputError::MissingField("container_volume"))
+        ));
+        assert!(matches!(
+            verify_summary(&serde_json::to_value(&result).unwrap()),
+            Err(OutputError::MissingPositions)
+        ));
+    }
+
+    #[test]
+    fn summary_diffs() {
+        let a = serde_json::json!({"volume_fraction": 0.5, "seed": 0, "config_hash": "ab"});
+        let b = serde_json::json!({"volume_fraction": 0.55, "seed": 3, "sphere_count": 10});
+        let diffs = diff_summaries(&a, &b);
+        assert_eq!(
+            vec!["seed", "volume_fraction"],
+            diffs.iter().map(|d| d.field.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(None, diffs[0].percent());
+        assert_eq!("seed: 0 -> 3 (+3)", diffs[0].to_string());
+        assert!((diffs[1].percent().unwrap() - 10.).abs() < 1e-9);
+        let fraction = FieldDiff {
+            field: String::from("volume_fraction"),
+            a: 0.5,
+            b: 0.25,
+        };
+        assert_eq!(
+            "volume_fraction: 0.5 -> 0.25 (-0.25, -50.000%)",
+            fraction.to_string()
+        );
+    }
+
+    #[test]
+    fn jsonl_lines() {
+        let mut written = Vec::new();
+        write_jsonl_line(&mut written, "{\"seed\":1}").unwrap();
+        write_jsonl_line(&mut written, "{\"seed\":2}").unwrap();
+        assert_eq!(
+            "{\"seed\":1}\n{\"seed\":2}\n",
+            String::from_utf8(written).unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_json() {
+        let summary = serde_json::json!({
+            "volume_fraction": 0.5,
+            "sphere_count": 12,
+            "seed": -3,
+            "container_volume": 8.0,
+            "stop_reason": "front exhausted\n\u{7f}é",
+            "aggregate": {"z": [1234.5, -0.0, 1e-7, 1e300], "a": null, "B": true},
+        });
+        let canonical = "{\"aggregate\":{\"B\":true,\"a\":null,\"z\":[1.2345e3,0e0,1e-7,1e300]},\
+                         \"container_volume\":8e0,\"seed\":-3,\"sphere_count\":12,\
+                         \"stop_reason\":\"front exhausted\\n\u{7f}é\",\"volume_fraction\":5e-1}";
+        assert_eq!(canonical, to_canonical_string(&summary));
+        // Every number reads back as the same value.
+        assert_eq!(
+            summary,
+            serde_json::from_str::<serde_json::Value>(canonical).unwrap()
+        );
+        let mut reordered = serde_json::Map::new();
+        for (name, value) in summary.as_object().unwrap().iter().rev() {
+            reordered.insert(name.clone(), value.clone());
+        }
+        assert_eq!(canonical, to_canonical_string(&reordered.into()));
+    }
+
+    #[test]
+    fn manifests() {
+        assert_eq!("1970-01-01T00:00:00Z", rfc3339(0));
+        assert_eq!("2000-02-29T23:59:59Z", rfc3339(951_868_799));
+        assert_eq!("2021-03-04T05:06:07Z", rfc3339(1_614_834_367));
+        let spheres: parsing::Spheres = r#"[{"name": "a", "radius": 1, "proportion": 100}]"#
+            .parse()
+            .unwrap();
+        let options = PackOptions::default();
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_614_834_367);
+        let args = vec![String::from("pack"), String::from("in.json")];
+        let manifest = Manifest::new(args.clone(), &options, &spheres, time).unwrap();
+        assert_eq!(env!("CARGO_PKG_VERSION"), manifest.version);
+        assert_eq!("2021-03-04T05:06:07Z", manifest.timestamp);
+        assert_eq!(64, manifest.input_hash.len());
+        let other: parsing::Spheres = r#"[{"name": "a", "radius": 2, "proportion": 100}]"#
+            .parse()
+            .unwrap();
+        let differs = Manifest::new(args, &options, &other, time).unwrap();
+        assert_ne!(manifest.input_hash, differs.input_hash);
+        let written = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(
+            serde_json::json!(["pack", "in.json"]),
+            written["command_line"]
+        );
+        assert_eq!(serde_json::to_value(&options).unwrap(), written["options"]);
+    }
+
+    #[test]
+    fn significant_digits() {
+        let mut summary = serde_json::json!({
+            "volume_fraction": 0.5834729103847,
+            "sphere_count": 1234567,
+            "half_extents": [12.345, 0.000123456],
+            "stop_reason": {"reason": "container_filled", "attempts": 10},
+            "container_volume": 1234567.0,
+        });
+        "3".parse::<SignificantDigits>()
+            .unwrap()
+            .apply(&mut summary);
+        assert_eq!(
+            serde_json::json!({
+                "volume_fraction": 0.583,
+                "sphere_count": 1234567,
+                "half_extents": [12.3, 0.000123],
+                "stop_reason": {"reason": "container_filled", "attempts": 10},
+                "container_volume": 1230000.0,
+            }),
+            summary
+        );
+        let exact = serde_json::json!({"volume_fraction": 0.5834729103847});
+        let mut rounded = exact.clone();
+        "17".parse::<SignificantDigits>()
+            .unwrap()
+            .apply(&mut rounded);
+        assert_eq!(exact, rounded);
+        for invalid in ["0", "18", "-1", "three"] {
+            assert!(invalid.parse::<SignificantDigits>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn field_maps() {
+        let fields: FieldMap = "volume_fraction=fill,seed=s".parse().unwrap();
+        let summary = serde_json::json!({"volume_fraction": 0.5, "sphere_count": 3, "seed": 1});
+        let renamed = fields.apply(&summary).unwrap();
+        let keys: Vec<&String> = renamed.as_object().unwrap().keys().collect();
+        assert_eq!(vec!["fill", "sphere_count", "s"], keys);
+        let trials = serde_json::json!({
+            "trials_run": 1,
+            "aggregate": {"count": 1, "volume_fraction": {"mean": 0.5}},
+            "trials": [summary],
+        });
+        assert_eq!(
+            serde_json::json!({
+                "trials_run": 1,
+                "aggregate": {"count": 1, "fill": {"mean": 0.5}},
+                "trials": [{"fill": 0.5, "sphere_count": 3, "s": 1}],
+            }),
+            fields.apply(&trials).unwrap()
+        );
+        for invalid in [
+            "fill",
+            "=fill",
+            "seed=",
+            "volume=fill",
+            "seed=a,seed=b",
+            "seed=a,rdf=a",
+        ] {
+            assert!(invalid.parse::<FieldMap>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn xyz() {
+        let types = r#"[
+            {"name": "5_micron_Al", "radius": 5.0, "proportion": 66},
+            {"name": "400_AP", "radius": 400, "proportion": 34}
+        ]"#
+        .parse()
+        .unwrap();
+        let mut written = Vec::new();
+        write_xyz(&mut written, &packed(), &types, None).unwrap();
+        assert_eq!(
+            "2\ntype x y z radius\n0 0 1 2 5\n1 -1 0.5 0 400\n",
+            String::from_utf8(written).unwrap()
+        );
+        let mut tagged = Vec::new();
+        write_xyz(&mut tagged, &packed(), &types, Some(&[7, 0])).unwrap();
+        assert_eq!(
+            "2\ntype x y z radius region\n0 0 1 2 5 7\n1 -1 0.5 0 400 0\n",
+            String::from_utf8(tagged).unwrap()
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_rows() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        use crate::output::write_parquet;
+        use crate::packing::{pack_trials, PackOptions};
+
+        let spheres = r#"[{"name": "5_micron_Al", "radius": 5.0, "proportion": 100}]"#
+            .parse()
+            .unwrap();
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            ..Default::default()
+        };
+        let results = pack_trials(&spheres, &options, 3, None, 1).unwrap();
+        let path = std::env::temp_dir().join("sphere_pack_parquet_rows.parquet");
+        write_parquet(std::fs::File::create(&path).unwrap(), results.trials()).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(3, metadata.num_rows());
+        assert_eq!(12, metadata.schema_descr().num_columns());
+    }
+}
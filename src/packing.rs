@@ -1,11 +1,31 @@
 //! Module used for computing sphere packing results given parameters.
-use rand::distributions::{Distribution, WeightedIndex};
-use rand::Rng;
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nalgebra::Point3;
+use rand::distributions::{Distribution, Open01, WeightedError, WeightedIndex};
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::Serialize;
-use spherical_cow::PackedVolume;
+use sha2::{Digest, Sha256};
+use spherical_cow::shapes::Sphere;
+use spherical_cow::Container;
 use thiserror::Error;
 
-use crate::parsing;
+use crate::{analysis, parsing};
+
+mod disk;
+mod front;
+mod polyhedron;
+mod quasi;
+mod relax;
 
 /// A weighted distribution for selecting sphere radius.
 #[derive(Debug)]
@@ -13,62 +33,4449 @@ struct WeightedRadiusDistribution {
     /// Available radii
     choices: Vec<f64>,
     /// Distribution which can be drawn from to provide indices into choices
-    dist: WeightedIndex<u8>,
+    dist: WeightedIndex<f64>,
+    /// Source indices are drawn with in place of the generator passed to `sample`, if any
+    stream: Option<RefCell<Stream>>,
+    /// Radii, each with a number of spheres, which are drawn before any radius is drawn by weight,
+    /// from the end
+    required: RefCell<Vec<(f64, u64)>>,
+    /// Continuous distribution radii are drawn from instead of from choices, if any
+    continuous: Option<parsing::RadiusDistribution>,
+    /// Radii each radius drawn from the continuous distribution is snapped to the nearest of, if
+    /// not empty
+    quantize: Vec<f64>,
 }
 
 impl Distribution<f64> for WeightedRadiusDistribution {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        self.choices[self.dist.sample(rng)]
+        let mut required = self.required.borrow_mut();
+        if let Some((radius, count)) = required.last_mut() {
+            let radius = *radius;
+            *count -= 1;
+            if *count == 0 {
+                required.pop();
+            }
+            return radius;
+        }
+        match &self.continuous {
+            Some(continuous) => {
+                let radius = continuous.quantile(self.draw(&Open01, rng));
+                nearest(&self.quantize, radius).unwrap_or(radius)
+            }
+            None => self.choices[self.draw(&self.dist, rng)],
+        }
     }
 }
 
+/// An enumeration of the sources a WeightedRadiusDistribution can draw from instead of the
+/// generator placements are chosen with.
+#[derive(Debug)]
+enum Stream {
+    Halton(quasi::Halton),
+    Seeded(Box<ChaCha8Rng>),
+}
+
 impl WeightedRadiusDistribution {
+    /// Draw from `dist` with this distribution's stream, or with `rng` if it has none.
+    fn draw<T, D: Distribution<T>, R: Rng + ?Sized>(&self, dist: &D, rng: &mut R) -> T {
+        match &self.stream {
+            Some(stream) => match &mut *stream.borrow_mut() {
+                Stream::Halton(halton) => dist.sample(halton),
+                Stream::Seeded(seeded) => dist.sample(&mut **seeded),
+            },
+            None => dist.sample(rng),
+        }
+    }
+
     /// Construct a new WeightedRadiusDistribution from an iterator `items`, where the first element
-    /// of each tuple is a radius and the second element is the percentage (before division by 100)
-    /// chance of that radius being drawn.
-    fn new<I>(items: I) -> Self
+    /// of each tuple is a radius and the second element is the relative weight of that radius being
+    /// drawn. Weights need not be integers or sum to any particular value, but must be
+    /// non-negative and finite, and not all 0.
+    fn new<I>(items: I) -> Result<Self, WeightedError>
     where
-        I: IntoIterator<Item = (f64, u8)>,
+        I: IntoIterator<Item = (f64, f64)>,
     {
-        let (choices, weights): (Vec<f64>, Vec<u8>) = items.into_iter().unzip();
-        let dist = WeightedIndex::new(weights).unwrap();
-        WeightedRadiusDistribution { choices, dist }
+        let (choices, weights): (Vec<f64>, Vec<f64>) = items.into_iter().unzip();
+        let dist = WeightedIndex::new(weights)?;
+        Ok(WeightedRadiusDistribution {
+            choices,
+            dist,
+            stream: None,
+            required: RefCell::new(Vec::new()),
+            continuous: None,
+            quantize: Vec::new(),
+        })
+    }
+
+    /// Construct a new WeightedRadiusDistribution drawing the radii of `spheres` with number
+    /// weights converted from their proportions according to `basis`, after first drawing the
+    /// minimum count of each type which has one, largest radius first.
+    fn from_spheres(
+        spheres: &parsing::Spheres,
+        basis: ProportionBasis,
+    ) -> Result<Self, WeightedError> {
+        let sizes = Self::new(number_fractions(spheres, basis))?;
+        let mut required: Vec<(f64, u64)> = spheres
+            .iter()
+            .filter_map(|s| Some((s.radius(), s.min_count()?)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        required.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        sizes.required.replace(required);
+        Ok(sizes)
+    }
+}
+
+/// Provides the radius of each type in `spheres` and the fraction of drawn spheres expected to be
+/// of that type when proportions are fractions of `basis`.
+fn number_fractions(spheres: &parsing::Spheres, basis: ProportionBasis) -> Vec<(f64, f64)> {
+    let weights: Vec<(f64, f64)> = spheres
+        .iter()
+        .map(|s| (s.radius(), basis.number_weight(s)))
+        .collect();
+    let total: f64 = weights.iter().map(|(_, w)| w).sum();
+    weights.into_iter().map(|(r, w)| (r, w / total)).collect()
+}
+
+/// Provides the mean of `f` applied to the radius of each sphere drawn from `spheres` when
+/// proportions are fractions of `basis`.
+fn number_mean<F: Fn(f64) -> f64>(spheres: &parsing::Spheres, basis: ProportionBasis, f: F) -> f64 {
+    number_fractions(spheres, basis)
+        .into_iter()
+        .map(|(r, fraction)| fraction * f(r))
+        .sum()
+}
+
+/// Provides the total volume of the spheres `packed`.
+fn packed_volume(packed: &[Sphere]) -> f64 {
+    // Summing from 0 rather than with `sum`, whose empty sum is -0.0, keeps a type with no packed
+    // spheres at a fraction of exactly 0.
+    packed
+        .iter()
+        .fold(0., |volume, s| volume + sphere_volume(s.radius as f64))
+}
+
+fn sphere_volume(radius: f64) -> f64 {
+    4. / 3. * PI * radius.powi(3)
+}
+
+fn sphere_surface_area(radius: f64) -> f64 {
+    4. * PI * radius.powi(2)
+}
+
+/// An enumeration of the quantities sphere proportions can be a fraction of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProportionBasis {
+    /// Proportions are fractions of the number of spheres
+    Number,
+    /// Proportions are fractions of the total volume of the spheres
+    Volume,
+    /// Proportions are fractions of the total surface area of the spheres
+    SurfaceArea,
+    /// Proportions are fractions of the total mass of the spheres, which requires every sphere
+    /// type to have a density
+    Mass,
+}
+
+impl ProportionBasis {
+    /// Convert the proportion of `sphere` into a relative number weight.
+    ///
+    /// A proportion `p` of the total volume is made up of spheres of volume `4/3 π r³`, so is
+    /// proportional to `p / (4/3 π r³)` spheres; likewise a proportion of the total surface area
+    /// gives a number weight of `p / (4 π r²)`. A proportion `p` of the total mass of a material
+    /// with density `ρ` is proportional to the volume `p / ρ`, and so to `p / (ρ 4/3 π r³)`
+    /// spheres. The weights are normalized afterwards, so the total mass or volume cancels out.
+    fn number_weight(self, sphere: &parsing::ParsedSphere) -> f64 {
+        let (proportion, radius) = (sphere.proportion(), sphere.radius());
+        match self {
+            ProportionBasis::Number => proportion,
+            ProportionBasis::Volume => proportion / sphere_volume(radius),
+            ProportionBasis::SurfaceArea => proportion / sphere_surface_area(radius),
+            ProportionBasis::Mass => sphere.density().map_or(f64::NAN, |density| {
+                proportion / (density * sphere_volume(radius))
+            }),
+        }
+    }
+
+    /// Check that proportions of `spheres` can be converted to number weights with this basis,
+    /// which for proportions by mass requires every sphere type to have a density.
+    pub fn check(self, spheres: &parsing::Spheres) -> Result<(), SimError> {
+        match spheres.iter().find(|s| s.density().is_none()) {
+            Some(sphere) if self == ProportionBasis::Mass => {
+                Err(SimError::MissingDensity(sphere.name().to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        *self == ProportionBasis::Number
+    }
+}
+
+impl FromStr for ProportionBasis {
+    type Err = SimError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "number" => Ok(ProportionBasis::Number),
+            "volume" => Ok(ProportionBasis::Volume),
+            "surface-area" => Ok(ProportionBasis::SurfaceArea),
+            "mass" => Ok(ProportionBasis::Mass),
+            _ => Err(SimError::UnknownBasis(s.to_string())),
+        }
+    }
+}
+
+/// An enumeration of the sources of the numbers which drive the packer's choices of radii and
+/// placements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sampler {
+    /// Both are drawn from a pseudo-random number generator
+    PseudoRandom,
+    /// Each is drawn from its own dimension of a Halton sequence, a low-discrepancy sequence
+    Halton,
+}
+
+impl Sampler {
+    fn is_pseudo_random(&self) -> bool {
+        *self == Sampler::PseudoRandom
+    }
+
+    /// Provides the distribution radii are drawn from, which draws the radii of `spheres` in their
+    /// proportions as fractions of `basis`, or if `continuous` is given, radii from it, each
+    /// snapped to the nearest of `quantize` if it isn't empty. The generator placements are chosen
+    /// with is provided alongside it, and both are seeded with `seed`, or if `split` is provided,
+    /// with its separate sample and placement seeds.
+    ///
+    /// The Halton sequences are shifted by amounts drawn from generators seeded with these seeds,
+    /// so that different seeds still give different packings.
+    fn sources(
+        self,
+        spheres: &parsing::Spheres,
+        basis: ProportionBasis,
+        continuous: Option<parsing::RadiusDistribution>,
+        quantize: &[f64],
+        seed: u64,
+        split: Option<(u64, u64)>,
+    ) -> Result<(WeightedRadiusDistribution, Box<dyn RngCore>), WeightedError> {
+        let mut sizes = WeightedRadiusDistribution::from_spheres(spheres, basis)?;
+        sizes.continuous = continuous;
+        sizes.quantize = quantize.to_vec();
+        let (stream, rng): (Stream, Box<dyn RngCore>) = match (self, split) {
+            (Sampler::PseudoRandom, None) => {
+                return Ok((sizes, Box::new(ChaCha8Rng::seed_from_u64(seed))));
+            }
+            (Sampler::PseudoRandom, Some((sample_seed, placement_seed))) => (
+                Stream::Seeded(Box::new(ChaCha8Rng::seed_from_u64(sample_seed))),
+                Box::new(ChaCha8Rng::seed_from_u64(placement_seed)),
+            ),
+            (Sampler::Halton, None) => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                (
+                    Stream::Halton(quasi::Halton::new(2, rng.gen())),
+                    Box::new(quasi::Halton::new(3, rng.gen())),
+                )
+            }
+            (Sampler::Halton, Some((sample_seed, placement_seed))) => (
+                Stream::Halton(quasi::Halton::new(
+                    2,
+                    ChaCha8Rng::seed_from_u64(sample_seed).gen(),
+                )),
+                Box::new(quasi::Halton::new(
+                    3,
+                    ChaCha8Rng::seed_from_u64(placement_seed).gen(),
+                )),
+            ),
+        };
+        sizes.stream = Some(RefCell::new(stream));
+        Ok((sizes, rng))
+    }
+}
+
+/// An enumeration of the floating point precisions metrics can be computed and reported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    F32,
+    F64,
+}
+
+impl Precision {
+    /// Round `value` to this precision.
+    fn round(self, value: f64) -> f64 {
+        match self {
+            Precision::F32 => value as f32 as f64,
+            Precision::F64 => value,
+        }
+    }
+
+    fn is_f64(&self) -> bool {
+        *self == Precision::F64
+    }
+}
+
+impl FromStr for Precision {
+    type Err = SimError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f32" => Ok(Precision::F32),
+            "f64" => Ok(Precision::F64),
+            _ => Err(SimError::UnknownPrecision(s.to_string())),
+        }
+    }
+}
+
+/// An enumeration of the number of dimensions spheres can be packed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Dimensionality {
+    /// Disks are packed into a rectangle
+    #[serde(rename = "2d")]
+    Two,
+    /// Spheres are packed into a cuboid
+    #[serde(rename = "3d")]
+    Three,
+}
+
+impl Dimensionality {
+    fn is_three(&self) -> bool {
+        *self == Dimensionality::Three
+    }
+}
+
+impl FromStr for Dimensionality {
+    type Err = SimError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2d" => Ok(Dimensionality::Two),
+            "3d" => Ok(Dimensionality::Three),
+            _ => Err(SimError::UnknownDimensionality(s.to_string())),
+        }
+    }
+}
+
+/// Serialize `value` as an `f32` if it is exactly representable as one, so that metrics computed
+/// at single precision are written with only as many digits as that precision has.
+fn serialize_metric<S: serde::Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    let single = *value as f32;
+    if single as f64 == *value {
+        serializer.serialize_f32(single)
+    } else {
+        serializer.serialize_f64(*value)
+    }
+}
+
+/// Serialize `value` as `serialize_metric` does, if it is present.
+fn serialize_optional_metric<S: serde::Serializer>(
+    value: &Option<f64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serialize_metric(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serialize `fractions` as a list of name and fraction pairs, with each fraction serialized as
+/// `serialize_metric` does.
+fn serialize_metrics_by_type<S: serde::Serializer>(
+    fractions: &[(String, f64)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::{SerializeSeq, SerializeTuple};
+
+    struct Pair<'a>(&'a str, f64);
+
+    impl Serialize for Pair<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            struct Metric(f64);
+
+            impl Serialize for Metric {
+                fn serialize<S: serde::Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    serialize_metric(&self.0, serializer)
+                }
+            }
+
+            let mut tuple = serializer.serialize_tuple(2)?;
+            tuple.serialize_element(self.0)?;
+            tuple.serialize_element(&Metric(self.1))?;
+            tuple.end()
+        }
+    }
+
+    let mut seq = serializer.serialize_seq(Some(fractions.len()))?;
+    for (name, fraction) in fractions {
+        seq.serialize_element(&Pair(name, *fraction))?;
+    }
+    seq.end()
+}
+
+/// The fraction of the container filled by a packing, which is serialized as `volume_fraction` or
+/// `area_fraction` depending on how many dimensions the packing is in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Fraction {
+    #[serde(rename = "volume_fraction", serialize_with = "serialize_metric")]
+    Volume(f64),
+    #[serde(rename = "area_fraction", serialize_with = "serialize_metric")]
+    Area(f64),
+}
+
+impl Fraction {
+    fn value(self) -> f64 {
+        match self {
+            Fraction::Volume(fraction) | Fraction::Area(fraction) => fraction,
+        }
     }
 }
 
 /// A struct containing the output of one sphere packing simulation.
 #[derive(Serialize)]
-pub(crate) struct SimOutput {
+pub struct SimOutput {
     /// Packing efficiency fraction
-    volume_fraction: f64,
+    #[serde(flatten)]
+    pub(crate) fraction: Fraction,
+    /// Packing efficiency fraction before relaxation, if the packing was relaxed
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) unrelaxed_volume_fraction: Option<f64>,
+    /// Fraction of the container filled by each sphere type, labelled by its name
+    #[serde(serialize_with = "serialize_metrics_by_type")]
+    pub(crate) volume_fraction_by_type: Vec<(String, f64)>,
+    /// Fraction of the container the centers of each sphere type can be in, labelled by its name
+    #[serde(serialize_with = "serialize_metrics_by_type")]
+    pub(crate) accessible_fraction_by_type: Vec<(String, f64)>,
+    /// Whether each sphere type with a minimum count had at least that many spheres placed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) min_counts: Vec<MinCount>,
+    /// Number of spheres packed of each quantized radius, as it and the count, if radii were
+    /// quantized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) quantized_counts: Option<Vec<(f64, usize)>>,
     /// Surface area to volume ratio
-    sa_to_vol: f64,
-    sphere_count: usize,
+    #[serde(serialize_with = "serialize_metric")]
+    pub(crate) sa_to_vol: f64,
+    /// Shannon entropy of the input proportions, in nats
+    #[serde(serialize_with = "serialize_metric")]
+    pub(crate) proportion_entropy: f64,
+    pub(crate) sphere_count: usize,
+    /// Skewness of the radii of the packed spheres, absent if they all have the same radius
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) radius_skewness: Option<f64>,
+    /// Kurtosis (not excess kurtosis) of the radii of the packed spheres, absent if they all have
+    /// the same radius
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) radius_kurtosis: Option<f64>,
+    /// Smallest radius of the packed spheres, absent if none were packed
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) min_radius: Option<f64>,
+    /// Largest radius of the packed spheres, absent if none were packed
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) max_radius: Option<f64>,
+    /// Number of spheres the container was sized to hold, absent if its dimensions were given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) target_count: Option<usize>,
+    /// Ratio of the realized sphere count to the target count
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) count_ratio: Option<f64>,
+    /// Number of times the container was resized to bring the count within a tolerance of the
+    /// target count, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) resize_iterations: Option<usize>,
+    /// Number of seeds tried until one packed, if packing persisted past failures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) persist_attempts: Option<usize>,
+    /// Half-side lengths of the cuboid container along each axis
+    pub(crate) half_extents: [f32; 3],
+    /// Volume of the container, or area for two dimensional packings, which the fraction filled
+    /// is relative to
+    #[serde(serialize_with = "serialize_metric")]
+    pub(crate) container_volume: f64,
+    /// Total volume of the packed spheres, if packing stopped at a total volume
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) packed_volume: Option<f64>,
+    /// Fraction of the container filled by obstacles, which the volume fraction includes, if there
+    /// are any
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) obstacle_fraction: Option<f64>,
+    /// Estimate of the volume fraction with periodic instead of wall boundaries, if requested
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) periodic_volume_fraction: Option<f64>,
+    /// Estimate of the bulk volume fraction corrected for the effect of the walls, if requested
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_metric"
+    )]
+    pub(crate) wall_corrected_volume_fraction: Option<f64>,
+    /// Volume fraction of each slab of the container along the z axis, from the bottom up, if
+    /// requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) density_profile: Option<Vec<f64>>,
+    /// Number of spheres placed and the fraction of the container they filled, at intervals as
+    /// the packing grew, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) growth_curve: Option<Vec<(usize, f64)>>,
+    /// Radial distribution function of the sphere centers, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rdf: Option<analysis::Rdf>,
+    /// Coordination numbers of the spheres, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) coordination: Option<analysis::Coordination>,
+    /// Pairs of indices into `spheres` of the spheres which touch, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) contact_graph: Option<Vec<[usize; 2]>>,
+    /// Distribution of the distances from empty points to the nearest sphere surface, if
+    /// requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) pore_stats: Option<analysis::PoreStats>,
+    /// Seed of the random number generator used for this packing
+    pub(crate) seed: u64,
+    /// Seed radii were drawn with, if it was separate from the placement seed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sample_seed: Option<u64>,
+    /// Seed placements were chosen with, if it was separate from the sample seed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) placement_seed: Option<u64>,
+    /// Source of the numbers which drove the packer's choices
+    pub(crate) sampler: Sampler,
+    /// Hex-encoded SHA-256 hash of the configuration which produced this output
+    pub(crate) config_hash: String,
+    /// How quickly the packing was computed, if it was benchmarked
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub(crate) throughput: Option<Throughput>,
+    /// Why packing stopped, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) stop_reason: Option<StopReason>,
+    /// Fractions of the container filled in each stage, if packed in two stages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) stages: Option<Stages>,
+    #[serde(skip)]
+    spheres: Vec<Sphere>,
+    /// Number of each relaxation pass a snapshot was taken after, and the spheres at the time
+    #[serde(skip)]
+    snapshots: Vec<(usize, Vec<Sphere>)>,
+}
+
+/// Names of the properties of the JSON summary of a packing, each of which is only present in the
+/// summaries of the packings it applies to.
+pub const FIELDS: &[&str] = &[
+    "volume_fraction",
+    "area_fraction",
+    "unrelaxed_volume_fraction",
+    "volume_fraction_by_type",
+    "accessible_fraction_by_type",
+    "min_counts",
+    "quantized_counts",
+    "sa_to_vol",
+    "proportion_entropy",
+    "sphere_count",
+    "radius_skewness",
+    "radius_kurtosis",
+    "min_radius",
+    "max_radius",
+    "target_count",
+    "count_ratio",
+    "resize_iterations",
+    "persist_attempts",
+    "half_extents",
+    "container_volume",
+    "packed_volume",
+    "obstacle_fraction",
+    "periodic_volume_fraction",
+    "wall_corrected_volume_fraction",
+    "density_profile",
+    "growth_curve",
+    "rdf",
+    "coordination",
+    "contact_graph",
+    "pore_stats",
+    "seed",
+    "sample_seed",
+    "placement_seed",
+    "sampler",
+    "config_hash",
+    "elapsed_ms",
+    "spheres_per_second",
+    "stop_reason",
+    "stages",
+];
+
+impl SimOutput {
+    /// Provides the output of packing `spheres` into `packed` with `options`, which `config`
+    /// describes, where `placements` counts how the positions tried fared.
+    ///
+    /// Only the properties which don't depend on the container are computed. The rest are left as
+    /// those of an empty container, for the packing into each kind of container to fill in.
+    fn new(
+        spheres: &parsing::Spheres,
+        options: &PackOptions,
+        packed: Vec<Sphere>,
+        config: &RunConfig,
+        placements: Placements,
+    ) -> SimOutput {
+        let precision = options.precision;
+        let moments = analysis::radius_moments(&packed);
+        let range = analysis::radius_range(&packed);
+        let volume = packed_volume(&packed);
+        let split = options.split_seeds();
+        SimOutput {
+            fraction: Fraction::Volume(0.),
+            unrelaxed_volume_fraction: None,
+            volume_fraction_by_type: Vec::new(),
+            accessible_fraction_by_type: Vec::new(),
+            min_counts: min_counts(spheres, &packed),
+            quantized_counts: quantized_counts(options.quantize.as_deref(), &packed),
+            sa_to_vol: precision.round(
+                number_mean(spheres, options.basis, sphere_volume)
+                    / number_mean(spheres, options.basis, sphere_surface_area),
+            ),
+            proportion_entropy: precision.round(spheres.proportion_entropy()),
+            sphere_count: packed.len(),
+            radius_skewness: moments.map(|(skewness, _)| precision.round(skewness)),
+            radius_kurtosis: moments.map(|(_, kurtosis)| precision.round(kurtosis)),
+            min_radius: range.map(|(min, _)| min),
+            max_radius: range.map(|(_, max)| max),
+            target_count: None,
+            count_ratio: None,
+            resize_iterations: None,
+            persist_attempts: None,
+            half_extents: [0.; 3],
+            container_volume: 0.,
+            packed_volume: options.total_volume.map(|_| precision.round(volume)),
+            obstacle_fraction: None,
+            periodic_volume_fraction: None,
+            wall_corrected_volume_fraction: None,
+            density_profile: None,
+            growth_curve: None,
+            rdf: None,
+            coordination: None,
+            contact_graph: None,
+            pore_stats: None,
+            seed: options.seed,
+            sample_seed: split.map(|(sample_seed, _)| sample_seed),
+            placement_seed: split.map(|(_, placement_seed)| placement_seed),
+            sampler: options.sampler,
+            config_hash: config.hash(),
+            throughput: None,
+            stop_reason: options
+                .explain
+                .then(|| StopReason::new(placements, volume, options.total_volume)),
+            stages: None,
+            spheres: packed,
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Provides the fraction of the container filled, which is an area fraction for a two
+    /// dimensional packing.
+    pub fn volume_fraction(&self) -> f64 {
+        self.fraction.value()
+    }
+
+    /// Provides a line reporting how many spheres (or disks, for a two dimensional packing) were
+    /// packed and the fraction of the container they fill.
+    pub fn fill_report(&self) -> String {
+        match self.fraction {
+            Fraction::Volume(fraction) => format!(
+                "filled the container with {} spheres, a volume fraction of {}",
+                self.sphere_count, fraction
+            ),
+            Fraction::Area(fraction) => format!(
+                "filled the container with {} disks, an area fraction of {}",
+                self.sphere_count, fraction
+            ),
+        }
+    }
+
+    /// Provides the packed spheres.
+    pub fn spheres(&self) -> &[Sphere] {
+        &self.spheres
+    }
+
+    /// Provides the half-side lengths of the container along each axis, or of the bounding cuboid
+    /// of a mesh or polyhedron container. The z half-side length of a two dimensional packing's
+    /// rectangle is 0.
+    pub fn half_extents(&self) -> [f32; 3] {
+        let [x, y, z] = self.half_extents;
+        if matches!(self.fraction, Fraction::Area(_)) {
+            [x, y, 0.]
+        } else {
+            [x, y, z]
+        }
+    }
+
+    /// Provides the snapshots of the spheres taken during relaxation, each with the number of the
+    /// pass it was taken after.
+    pub fn snapshots(&self) -> &[(usize, Vec<Sphere>)] {
+        &self.snapshots
+    }
+
+    /// Provides whether each sphere type with a minimum count had that many spheres placed.
+    pub fn min_counts(&self) -> &[MinCount] {
+        &self.min_counts
+    }
+
+    /// Sort the lists in this output into an order which doesn't depend on the order spheres
+    /// were listed in the input or placed in, so that outputs can be compared line by line: each
+    /// sphere type's properties by its radius in `spheres` (the spheres this output is of) and
+    /// then by name, and the packed spheres by radius and then by position.
+    pub fn sort(&mut self, spheres: &parsing::Spheres) {
+        let radius = |name: &str| {
+            spheres
+                .iter()
+                .find(|s| s.name() == name)
+                .map_or(f64::NAN, parsing::ParsedSphere::radius)
+        };
+        let by_radius = |(a, _): &(String, f64), (b, _): &(String, f64)| {
+            radius(a).total_cmp(&radius(b)).then_with(|| a.cmp(b))
+        };
+        self.volume_fraction_by_type.sort_by(by_radius);
+        self.accessible_fraction_by_type.sort_by(by_radius);
+        self.min_counts.sort_by(|a, b| {
+            radius(&a.name)
+                .total_cmp(&radius(&b.name))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        let key = |s: &Sphere| [s.radius, s.center.x, s.center.y, s.center.z];
+        let mut order: Vec<usize> = (0..self.spheres.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (key(&self.spheres[a]), key(&self.spheres[b]));
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.total_cmp(b))
+                .find(|order| order.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        // The contact graph's indices follow their spheres to where they're sorted to.
+        if let Some(edges) = &mut self.contact_graph {
+            let mut sorted_index = vec![0; order.len()];
+            for (new, &old) in order.iter().enumerate() {
+                sorted_index[old] = new;
+            }
+            for edge in edges.iter_mut() {
+                let [a, b] = edge.map(|i| sorted_index[i]);
+                *edge = [a.min(b), a.max(b)];
+            }
+            edges.sort_unstable();
+        }
+        self.spheres = order.iter().map(|&i| self.spheres[i].clone()).collect();
+    }
+
+    /// Provides statistics of each scalar property of `results`, such as those of several trials.
+    ///
+    /// Optional properties are summarized over the results which have them, and are absent if
+    /// none do.
+    pub fn aggregate(results: &[SimOutput]) -> AggregateOutput {
+        let stats = |property: fn(&SimOutput) -> Option<f64>| {
+            Stats::of(&results.iter().filter_map(property).collect::<Vec<_>>())
+        };
+        AggregateOutput {
+            count: results.len(),
+            volume_fraction: stats(|r| Some(r.volume_fraction())),
+            unrelaxed_volume_fraction: stats(|r| r.unrelaxed_volume_fraction),
+            sa_to_vol: stats(|r| Some(r.sa_to_vol)),
+            proportion_entropy: stats(|r| Some(r.proportion_entropy)),
+            sphere_count: stats(|r| Some(r.sphere_count as f64)),
+            count_ratio: stats(|r| r.count_ratio),
+            periodic_volume_fraction: stats(|r| r.periodic_volume_fraction),
+            wall_corrected_volume_fraction: stats(|r| r.wall_corrected_volume_fraction),
+        }
+    }
+}
+
+/// A multi-line summary of the packing for reading in a terminal, with a labelled line for each
+/// property and the units of those which have them. Lengths are in the units of the input radii,
+/// and the properties of the JSON summary which are lists, other than the fractions by type, are
+/// only summarized.
+impl fmt::Display for SimOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let two_dimensional = matches!(self.fraction, Fraction::Area(_));
+        let (fraction, size, size_units) = if two_dimensional {
+            ("area fraction", "container area", "units²")
+        } else {
+            ("volume fraction", "container volume", "units³")
+        };
+        let mut lines = vec![(
+            fraction.to_string(),
+            format!("{:.4}", self.fraction.value()),
+        )];
+        for (name, fraction) in &self.volume_fraction_by_type {
+            lines.push((format!("  {}", name), format!("{:.4}", fraction)));
+        }
+        for (name, fraction) in &self.accessible_fraction_by_type {
+            lines.push((format!("  {} accessible", name), format!("{:.4}", fraction)));
+        }
+        let optional_fractions = [
+            ("obstacle fraction", self.obstacle_fraction),
+            ("unrelaxed volume fraction", self.unrelaxed_volume_fraction),
+            ("periodic volume fraction", self.periodic_volume_fraction),
+            (
+                "wall-corrected volume fraction",
+                self.wall_corrected_volume_fraction,
+            ),
+        ];
+        for (label, fraction) in optional_fractions {
+            if let Some(fraction) = fraction {
+                lines.push((label.to_string(), format!("{:.4}", fraction)));
+            }
+        }
+        if let Some(profile) = &self.density_profile {
+            let slabs: Vec<String> = profile.iter().map(|f| format!("{:.4}", f)).collect();
+            lines.push((String::from("density profile"), slabs.join(" ")));
+        }
+        let count = match (self.target_count, self.count_ratio) {
+            (Some(target), Some(ratio)) => format!(
+                "{} (target {}, ratio {:.3})",
+                self.sphere_count, target, ratio
+            ),
+            _ => self.sphere_count.to_string(),
+        };
+        lines.push((String::from("spheres packed"), count));
+        if let Some(resizes) = self.resize_iterations {
+            lines.push((String::from("container resizes"), resizes.to_string()));
+        }
+        if let Some(attempts) = self.persist_attempts {
+            lines.push((String::from("packing attempts"), attempts.to_string()));
+        }
+        for min_count in &self.min_counts {
+            lines.push((
+                format!("  minimum of {}", min_count.name),
+                format!(
+                    "{} of {} placed{}",
+                    min_count.placed,
+                    min_count.min_count,
+                    if min_count.satisfied {
+                        ""
+                    } else {
+                        " (unsatisfied)"
+                    }
+                ),
+            ));
+        }
+        for (radius, count) in self.quantized_counts.iter().flatten() {
+            lines.push((format!("  radius {}", radius), count.to_string()));
+        }
+        let [x, y, z] = self.half_extents;
+        let half_extents = if two_dimensional {
+            format!("{} × {} units", x, y)
+        } else {
+            format!("{} × {} × {} units", x, y, z)
+        };
+        lines.push((String::from("half extents"), half_extents));
+        lines.push((
+            size.to_string(),
+            format!("{:.4} {}", self.container_volume, size_units),
+        ));
+        if let Some(volume) = self.packed_volume {
+            lines.push((
+                String::from("packed volume"),
+                format!("{:.4} {}", volume, size_units),
+            ));
+        }
+        lines.push((
+            String::from("mean volume / surface area"),
+            format!("{:.4} units", self.sa_to_vol),
+        ));
+        lines.push((
+            String::from("proportion entropy"),
+            format!("{:.4} nats", self.proportion_entropy),
+        ));
+        if let (Some(skewness), Some(kurtosis)) = (self.radius_skewness, self.radius_kurtosis) {
+            lines.push((String::from("radius skewness"), format!("{:.4}", skewness)));
+            lines.push((String::from("radius kurtosis"), format!("{:.4}", kurtosis)));
+        }
+        if let (Some(min), Some(max)) = (self.min_radius, self.max_radius) {
+            lines.push((String::from("packed radii"), format!("{} to {}", min, max)));
+        }
+        if let Some(rdf) = &self.rdf {
+            lines.push((
+                String::from("radial distribution"),
+                format!("{} bins", rdf.r.len()),
+            ));
+        }
+        if let Some(stages) = self.stages {
+            lines.push((
+                String::from("stage fractions"),
+                format!(
+                    "{:.4} large, {:.4} small",
+                    stages.large_fraction, stages.small_fraction
+                ),
+            ));
+        }
+        if let Some(coordination) = &self.coordination {
+            lines.push((
+                String::from("coordination"),
+                format!("{:.3} contacts", coordination.mean),
+            ));
+        }
+        if let Some(edges) = &self.contact_graph {
+            lines.push((
+                String::from("contact graph"),
+                format!("{} edges", edges.len()),
+            ));
+        }
+        if let Some(pores) = &self.pore_stats {
+            lines.push((
+                String::from("pores"),
+                format!(
+                    "{:.4} empty, {:.4} to the nearest sphere on average",
+                    pores.empty_fraction, pores.mean_distance
+                ),
+            ));
+        }
+        if let Some(throughput) = self.throughput {
+            lines.push((
+                String::from("elapsed"),
+                format!("{:.3} ms", throughput.elapsed_ms),
+            ));
+            lines.push((
+                String::from("throughput"),
+                format!("{:.0} spheres/s", throughput.spheres_per_second),
+            ));
+        }
+        if let Some(stop_reason) = self.stop_reason {
+            let placements = stop_reason.placements;
+            lines.push((
+                String::from("stopped"),
+                format!(
+                    "{} after {} attempts ({} placed, {} outside the container, {} overlapping)",
+                    match stop_reason.reason {
+                        Termination::ContainerFilled => "container filled",
+                        Termination::PlacementFailures => "placement failures",
+                        Termination::VolumeReached => "total volume reached",
+                    },
+                    placements.attempts,
+                    placements.placed,
+                    placements.outside_container,
+                    placements.overlapping
+                ),
+            ));
+        }
+        lines.push((String::from("seed"), self.seed.to_string()));
+        if let (Some(sample_seed), Some(placement_seed)) = (self.sample_seed, self.placement_seed) {
+            lines.push((String::from("sample seed"), sample_seed.to_string()));
+            lines.push((String::from("placement seed"), placement_seed.to_string()));
+        }
+        let sampler = match self.sampler {
+            Sampler::PseudoRandom => "pseudo-random",
+            Sampler::Halton => "halton",
+        };
+        lines.push((String::from("sampler"), sampler.to_string()));
+        lines.push((String::from("config hash"), self.config_hash.clone()));
+        let width = lines
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for (i, (label, value)) in lines.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<width$} {}",
+                format!("{}:", label),
+                value,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The fractions of the container filled by each stage of a two-stage packing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Stages {
+    /// Radius the sphere types were split at, with types of at least this radius packed first
+    pub radius_threshold: f64,
+    /// Fraction of the container filled by the spheres packed in the first stage
+    #[serde(serialize_with = "serialize_metric")]
+    pub large_fraction: f64,
+    /// Fraction of the container filled by the spheres packed into the voids in the second stage
+    #[serde(serialize_with = "serialize_metric")]
+    pub small_fraction: f64,
+}
+
+/// A struct describing whether the minimum count of one sphere type was placed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MinCount {
+    pub name: String,
+    pub min_count: u64,
+    /// Number of packed spheres matched to this type, which have the nearest radius to it
+    pub placed: u64,
+    pub satisfied: bool,
+}
+
+/// Provides whether each type in `spheres` with a minimum count had at least that many spheres
+/// of `packed` matched to it.
+fn min_counts(spheres: &parsing::Spheres, packed: &[Sphere]) -> Vec<MinCount> {
+    let mut placed = vec![0; spheres.iter().count()];
+    for sphere in packed {
+        placed[spheres.nearest_type(sphere.radius as f64)] += 1;
+    }
+    spheres
+        .iter()
+        .zip(placed)
+        .filter_map(|(s, placed)| {
+            let min_count = s.min_count()?;
+            Some(MinCount {
+                name: s.name().to_string(),
+                min_count,
+                placed,
+                satisfied: placed >= min_count,
+            })
+        })
+        .collect()
+}
+
+impl parsing::Spheres {
+    /// Check that these spheres could be packed into a container with `container_volume`, so that
+    /// an infeasible packing fails fast with the first reason it can't be packed: a container
+    /// volume which isn't positive and finite, a sphere larger than the whole container, or
+    /// minimum counts which fill more than it.
+    ///
+    /// Only the types which can be packed, those with a positive proportion or minimum count, are
+    /// checked. This only compares volumes, so spheres which pass may still not fit in the
+    /// container's shape, such as a thin cuboid. `pack` checks this before packing.
+    pub fn feasibility(&self, container_volume: f64) -> Result<(), SimError> {
+        check_feasible(self, container_volume, sphere_volume)
+    }
+}
+
+/// Check that `spheres` could be packed into a container with `volume` as
+/// `Spheres::feasibility` does, where `content` provides the volume (or area) of a sphere with
+/// some radius.
+fn check_feasible<F: Fn(f64) -> f64>(
+    spheres: &parsing::Spheres,
+    volume: f64,
+    content: F,
+) -> Result<(), SimError> {
+    if !(volume > 0. && volume.is_finite()) {
+        return Err(SimError::InvalidContainerVolume(volume));
+    }
+    let packable = spheres
+        .iter()
+        .filter(|s| s.proportion() > 0.0 || s.min_count().is_some_and(|n| n > 0));
+    for sphere in packable {
+        let radius = sphere.radius();
+        if content(radius) > volume {
+            return Err(SimError::OversizedSphere(
+                sphere.name().to_string(),
+                radius,
+                content(radius),
+                volume,
+            ));
+        }
+    }
+    check_min_counts(spheres, volume, content)
+}
+
+/// Check that the minimum counts of `spheres` could fit in a container with `volume`, where
+/// `content` provides the volume (or area) of a sphere with some radius.
+fn check_min_counts<F: Fn(f64) -> f64>(
+    spheres: &parsing::Spheres,
+    volume: f64,
+    content: F,
+) -> Result<(), SimError> {
+    let required: f64 = spheres
+        .iter()
+        .filter_map(|s| Some(s.min_count()? as f64 * content(s.radius())))
+        .sum();
+    if required > volume {
+        Err(SimError::UnsatisfiableMinimums(required, volume))
+    } else {
+        Ok(())
+    }
+}
+
+/// Summary statistics of some values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Stats {
+    pub mean: f64,
+    /// Population standard deviation, which is 0 for a single value
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Stats {
+    /// Provides the statistics of `values`, or None if there are none.
+    fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        Some(Stats {
+            mean,
+            std: variance.sqrt(),
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+/// Confidence level of the interval for the volume fraction reported over trials, unless another is
+/// set with `TrialsOutput::set_confidence_level`.
+pub const DEFAULT_CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Provides the normal-approximation confidence interval at `level` for the mean of `values`,
+/// `mean ± z s / √n` where `s` is their sample standard deviation and `z` the standard normal
+/// quantile at `(1 + level) / 2`, or None if there are fewer than two values.
+fn normal_interval(values: &[f64], level: f64) -> Option<(f64, f64)> {
+    if values.len() < 2 {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.);
+    let half_width = normal_quantile((1. + level) / 2.) * (variance / n).sqrt();
+    Some((mean - half_width, mean + half_width))
+}
+
+/// Provides the quantile of the standard normal distribution at `p`, in `(0, 1)`, using Acklam's
+/// rational approximation, which has a relative error of at most about 1.15e-9.
+pub(crate) fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    let polynomial =
+        |coefficients: &[f64], x: f64| coefficients.iter().fold(0., |acc, c| acc * x + c);
+    // The tails are approximated in terms of sqrt(-2 ln q), with q the smaller tail probability.
+    let tail = |q: f64| {
+        let r = (-2. * q.ln()).sqrt();
+        polynomial(&C, r) / (polynomial(&D, r) * r + 1.)
+    };
+    const LOW: f64 = 0.02425;
+    if p < LOW {
+        tail(p)
+    } else if p > 1. - LOW {
+        -tail(1. - p)
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        polynomial(&A, r) * q / (polynomial(&B, r) * r + 1.)
+    }
+}
+
+/// A struct containing statistics of the scalar properties of several packings' outputs, each of
+/// which is absent if no output had it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregateOutput {
+    /// Number of outputs summarized
+    pub count: usize,
+    /// Volume fraction, or area fraction for two dimensional packings
+    pub volume_fraction: Option<Stats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unrelaxed_volume_fraction: Option<Stats>,
+    pub sa_to_vol: Option<Stats>,
+    pub proportion_entropy: Option<Stats>,
+    pub sphere_count: Option<Stats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count_ratio: Option<Stats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub periodic_volume_fraction: Option<Stats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wall_corrected_volume_fraction: Option<Stats>,
+}
+
+/// A struct describing how quickly a packing was computed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Throughput {
+    /// Wall-clock time taken to pack, in milliseconds
+    pub elapsed_ms: f64,
+    /// Number of spheres packed per second of wall-clock time
+    pub spheres_per_second: f64,
+}
+
+/// Counts of the positions tried for new spheres while packing, and of why those which weren't
+/// used were rejected.
+///
+/// Each position touches spheres already placed, and more than one may be usable at a time, so
+/// `attempts` can exceed the sum of the other counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Placements {
+    /// Number of positions a new sphere was tried at
+    pub attempts: u64,
+    /// Number of spheres placed at one of those positions
+    pub placed: u64,
+    /// Number of positions rejected because the sphere would leave the container
+    pub outside_container: u64,
+    /// Number of positions rejected because the sphere would overlap one already placed
+    pub overlapping: u64,
+}
+
+impl Placements {
+    /// Count an attempt at a position which is in the container if `contained` and overlaps a
+    /// sphere already placed if `overlapping` provides true, which is only checked if contained.
+    /// Provides whether a sphere can be placed there.
+    fn record(&mut self, contained: bool, overlapping: impl FnOnce() -> bool) -> bool {
+        self.attempts += 1;
+        if !contained {
+            self.outside_container += 1;
+            false
+        } else if overlapping() {
+            self.overlapping += 1;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+/// Why packing stopped, which is once no sphere on the advancing front has room for a new sphere of
+/// the radius drawn next to it, unless a total volume of spheres to pack was reached first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    /// The packing grew out to the container's walls, so some positions were rejected for leaving
+    /// it: the container filled up, and a larger container would hold more spheres.
+    ContainerFilled,
+    /// Every position was rejected for overlapping spheres already placed before the packing
+    /// reached the walls, which usually means the mix of radii doesn't pack from its starting
+    /// spheres, and a larger container wouldn't help.
+    PlacementFailures,
+    /// The spheres placed reached the total volume to pack, so packing stopped before the
+    /// container filled up.
+    VolumeReached,
+}
+
+/// A struct describing why packing stopped and how the positions tried for new spheres fared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StopReason {
+    pub reason: Termination,
+    #[serde(flatten)]
+    pub placements: Placements,
+}
+
+impl StopReason {
+    /// Provides why packing stopped, given how the positions tried fared in `placements` and the
+    /// total volume to pack, `total_volume`, if any, which spheres with `volume` reached or not.
+    fn new(placements: Placements, volume: f64, total_volume: Option<f64>) -> Self {
+        match total_volume {
+            Some(total_volume) if volume >= total_volume => StopReason {
+                reason: Termination::VolumeReached,
+                placements,
+            },
+            _ => placements.into(),
+        }
+    }
+}
+
+impl From<Placements> for StopReason {
+    fn from(placements: Placements) -> Self {
+        StopReason {
+            reason: if placements.outside_container > 0 {
+                Termination::ContainerFilled
+            } else {
+                Termination::PlacementFailures
+            },
+            placements,
+        }
+    }
+}
+
+/// Options controlling how spheres are packed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackOptions {
+    /// Half-side lengths of the container along each axis, or None to use a cube sized to hold
+    /// about `target_count` spheres
+    pub dims: Option<[f32; 3]>,
+    /// Number of spheres the default cube is sized to hold
+    pub target_count: usize,
+    /// Ratio of the default cube's volume to the total volume of `target_count` spheres
+    pub fill_factor: f32,
+    /// Total volume of spheres to pack, after which packing stops, and which the default cube is
+    /// sized from (as `TOTAL_VOLUME_FILL_FACTOR` times it) instead of `target_count`, if given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_volume: Option<f64>,
+    /// Seed of the random number generator
+    pub seed: u64,
+    /// Seed radii are drawn with, independently of placements, or None to use `seed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_seed: Option<u64>,
+    /// Seed placements are chosen with, independently of radii, or None to use `seed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placement_seed: Option<u64>,
+    /// What sphere proportions are fractions of
+    pub basis: ProportionBasis,
+    /// Whether to estimate the volume fraction with periodic boundaries
+    pub periodic: bool,
+    /// Whether to estimate the bulk volume fraction by correcting for the effect of the walls
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub wall_correction: bool,
+    /// Whether spheres settle downward along the z axis as they're placed, as under gravity,
+    /// rather than filling the container evenly
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub gravity: bool,
+    /// Number of slabs along the z axis to report the volume fraction of, if any, which defaults
+    /// to `PROFILE_SLICES` with `gravity`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub density_profile: Option<usize>,
+    /// Binning of the radial distribution function to compute, if any, which must have at least
+    /// one bin and a positive, finite rmax
+    pub rdf: Option<analysis::RdfBins>,
+    /// Relative gap within which spheres are counted as touching to compute the coordination
+    /// numbers with, if they are computed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coordination: Option<f64>,
+    /// Relative gap within which spheres are counted as touching to report the contact graph
+    /// with, if it is reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_graph: Option<f64>,
+    /// Number of random points to sample the pore size distribution at, if it's computed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pore_samples: Option<usize>,
+    /// Number of relaxation passes to compact the packing with before packing more spheres into
+    /// the space freed up, or 0 to not relax it
+    pub relax: usize,
+    /// Number of relaxation passes between each snapshot of the spheres kept in the output, or
+    /// None to keep no snapshots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_every: Option<usize>,
+    /// Number of spheres placed between each point of the growth curve reported, or None to not
+    /// report it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_growth: Option<usize>,
+    /// Precision metrics are computed and reported in
+    #[serde(skip_serializing_if = "Precision::is_f64")]
+    pub precision: Precision,
+    /// Whether spheres or disks are packed
+    #[serde(skip_serializing_if = "Dimensionality::is_three")]
+    pub dimensionality: Dimensionality,
+    /// Source of the numbers which drive the packer's choices
+    #[serde(skip_serializing_if = "Sampler::is_pseudo_random")]
+    pub sampler: Sampler,
+    /// Whether to sort the output's lists as `SimOutput::sort` does
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub sort_output: bool,
+    /// Whether to report why packing stopped as `stop_reason`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub explain: bool,
+    /// Spheres the container starts with, which packing continues from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub initial: Vec<parsing::PlacedSphere>,
+    /// Fixed spheres the container starts with, which spheres are packed around but which aren't
+    /// part of the packing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub obstacles: Vec<parsing::PlacedSphere>,
+    /// Half-spaces bounding a convex polyhedron to pack into instead of a cuboid, if any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mesh: Vec<parsing::HalfSpace>,
+    /// Continuous distribution to draw radii from instead of the sphere types, which then only
+    /// bucket the drawn radii for reporting, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distribution: Option<parsing::RadiusDistribution>,
+    /// Radii each radius drawn from `distribution` is snapped to the nearest of, if any, which
+    /// must be positive and finite
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantize: Option<Vec<f64>>,
+    /// Least fraction of the container a packing must fill to be provided rather than an error, if
+    /// any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_fraction: Option<f64>,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        PackOptions {
+            dims: None,
+            target_count: 1000,
+            fill_factor: 2.,
+            total_volume: None,
+            seed: 0,
+            sample_seed: None,
+            placement_seed: None,
+            basis: ProportionBasis::Number,
+            periodic: false,
+            wall_correction: false,
+            gravity: false,
+            density_profile: None,
+            rdf: None,
+            coordination: None,
+            contact_graph: None,
+            pore_samples: None,
+            relax: 0,
+            snapshot_every: None,
+            track_growth: None,
+            precision: Precision::F64,
+            dimensionality: Dimensionality::Three,
+            sampler: Sampler::PseudoRandom,
+            sort_output: false,
+            explain: false,
+            initial: Vec::new(),
+            obstacles: Vec::new(),
+            mesh: Vec::new(),
+            distribution: None,
+            quantize: None,
+            min_fraction: None,
+        }
+    }
+}
+
+impl PackOptions {
+    /// Provides the seeds radii are drawn with and placements are chosen with, each defaulting to
+    /// `seed`, if either is given separately. Otherwise both are drawn from one generator.
+    fn split_seeds(&self) -> Option<(u64, u64)> {
+        if self.sample_seed.is_none() && self.placement_seed.is_none() {
+            return None;
+        }
+        Some((
+            self.sample_seed.unwrap_or(self.seed),
+            self.placement_seed.unwrap_or(self.seed),
+        ))
+    }
+}
+
+/// A struct describing every parameter that determines the result of a packing.
+#[derive(Serialize)]
+struct RunConfig<'a> {
+    spheres: &'a parsing::Spheres,
+    target_count: f64,
+    fill_factor: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_volume: Option<f64>,
+    shape: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dims: Option<[f32; 3]>,
+    seed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample_seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    placement_seed: Option<u64>,
+    #[serde(skip_serializing_if = "ProportionBasis::is_number")]
+    basis: ProportionBasis,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    initial: &'a [parsing::PlacedSphere],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    obstacles: &'a [parsing::PlacedSphere],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    mesh: &'a [parsing::HalfSpace],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distribution: Option<parsing::RadiusDistribution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quantize: Option<&'a [f64]>,
+    #[serde(skip_serializing_if = "is_zero")]
+    relax: usize,
+    #[serde(skip_serializing_if = "Dimensionality::is_three")]
+    dimensionality: Dimensionality,
+    #[serde(skip_serializing_if = "Sampler::is_pseudo_random")]
+    sampler: Sampler,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    gravity: bool,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl RunConfig<'_> {
+    /// Provides a hex-encoded SHA-256 hash of this configuration's canonical JSON form, which is
+    /// identical for identical configurations regardless of platform.
+    fn hash(&self) -> String {
+        let canonical = serde_json::to_vec(self).expect("RunConfig is always serializable");
+        format!("{:x}", Sha256::digest(&canonical))
+    }
 }
 
 #[derive(Debug, Error)]
 /// An enumeration of all errors that can occur while running the simulation.
-pub(crate) enum SimError {
+pub enum SimError {
     #[error("failed to pack shape")]
     FailedToPack(#[from] spherical_cow::errors::SphericalCowError),
+    #[error("sphere radii can't be drawn from the proportions")]
+    UndrawableRadii(#[from] WeightedError),
+    #[error("unknown proportion basis '{0}'")]
+    UnknownBasis(String),
+    #[error("proportions are by mass, but sphere '{0}' has no density")]
+    MissingDensity(String),
+    #[error("unknown precision '{0}'")]
+    UnknownPrecision(String),
+    #[error("unknown dimensionality '{0}'")]
+    UnknownDimensionality(String),
+    #[error("{0} can't be used with a two dimensional packing")]
+    UnsupportedIn2d(&'static str),
+    #[error("invalid mesh container: {0}")]
+    InvalidMesh(&'static str),
+    #[error("{0} can't be used with a mesh container")]
+    UnsupportedWithMesh(&'static str),
+    #[error("container side lengths {0:?} must all be positive and finite")]
+    InvalidContainer([f32; 3]),
+    #[error("failed to report a trial's result")]
+    FailedToReport(#[source] io::Error),
+    #[error("initial sphere {0} does not fit in the container")]
+    InitialUncontained(usize),
+    #[error("initial spheres {0} and {1} overlap")]
+    InitialOverlap(usize, usize),
+    #[error("obstacle {0} does not fit in the container")]
+    ObstacleUncontained(usize),
+    #[error("obstacles {0} and {1} overlap")]
+    ObstacleOverlap(usize, usize),
+    #[error("initial sphere {0} overlaps obstacle {1}")]
+    InitialOverlapsObstacle(usize, usize),
+    #[error("the obstacles leave no room for the spheres packing starts from")]
+    NoRoomAmongObstacles,
+    #[error("{0} can't be used with obstacles")]
+    UnsupportedWithObstacles(&'static str),
+    #[error("the minimum counts of spheres fill {0}, more than the container's {1}")]
+    UnsatisfiableMinimums(f64, f64),
+    #[error("container volume {0} must be positive and finite")]
+    InvalidContainerVolume(f64),
+    #[error("sphere '{0}' with radius {1} fills {2}, more than the container's {3}")]
+    OversizedSphere(String, f64, f64, f64),
+    #[error("volume fraction {got} is below the required minimum of {threshold}")]
+    FractionTooLow { got: f64, threshold: f64 },
+    #[error("minimum fraction {0} must be between 0 and 1")]
+    InvalidMinFraction(f64),
+    #[error("invalid radius distribution")]
+    InvalidDistribution(#[source] parsing::ParsingError),
+    #[error("{0} can't be used with a radius distribution")]
+    UnsupportedWithDistribution(&'static str),
+    #[error("quantization needs a radius distribution to draw radii from")]
+    QuantizeWithoutDistribution,
+    #[error("quantization radii {0:?} must be non-empty, positive and finite")]
+    InvalidQuantization(Vec<f64>),
+    #[error("count tolerance {0} must be non-negative and finite")]
+    InvalidCountTolerance(f64),
+    #[error("a count tolerance needs a container sized by the target count")]
+    ToleranceWithoutTarget,
+    #[error("a density profile needs at least one slice")]
+    NoSlices,
+    #[error("confidence level {0} must be strictly between 0 and 1")]
+    InvalidConfidenceLevel(f64),
+    #[error(
+        "outliers must be a non-negative, finite number {0} of standard deviations below the mean"
+    )]
+    InvalidOutlierThreshold(f64),
+    #[error("snapshots must be taken at least every relaxation pass")]
+    NoSnapshotInterval,
+    #[error("the growth curve must have a point at least every sphere")]
+    NoGrowthInterval,
+    #[error("pore statistics need at least one sample")]
+    NoPoreSamples,
+    #[error("contact epsilon {0} must be non-negative and finite")]
+    InvalidContactEpsilon(f64),
+    #[error("total volume {0} must be positive and finite")]
+    InvalidTotalVolume(f64),
+    #[error("no packing succeeded in {0} attempts within {1:?}")]
+    PersistTimedOut(usize, Duration),
+    #[error("invalid two-stage split")]
+    InvalidSplit(#[source] parsing::ParsingError),
+    #[error(
+        "the radial distribution function needs at least one bin and a positive, finite rmax, \
+         got {} bins up to {}",
+        .0.bins,
+        .0.r_max
+    )]
+    InvalidRdf(analysis::RdfBins),
 }
 
-/// Pack spheres into a cylinder, returning the result of this packing or an error to indicate
+/// Default relative gap within which spheres are counted as touching for their coordination
+/// numbers, which is well above the rounding error of spheres placed tangent to each other.
+pub const CONTACT_EPSILON: f64 = 1e-4;
+
+/// Number of slabs the density profile of a packing settled under gravity is reported in, unless
+/// another number is given.
+const PROFILE_SLICES: usize = 10;
+
+/// Pack spheres into a cuboid, returning the result of this packing or an error to indicate
 /// simulation failure.
-pub(crate) fn pack(spheres: &parsing::Spheres) -> Result<SimOutput, SimError> {
-    const TARGET_SPHERE_CT: f64 = 1000.;
-    let sphere_volume = (spheres.avg_volume() * TARGET_SPHERE_CT) as f32;
-    let cube_volume = (sphere_volume * 2.) as f32;
-    let cube_side = cube_volume.cbrt();
-    let container =
-        spherical_cow::shapes::Cuboid::new(cube_side / 2., cube_side / 2., cube_side / 2.)
-            .expect("Side lengths unexpectedly negative");
-    let mut sizes =
-        WeightedRadiusDistribution::new(spheres.iter().map(|s| (s.radius(), s.proportion())));
-    let packed = PackedVolume::new(container, &mut sizes)?;
-    Ok(SimOutput {
-        volume_fraction: packed.volume_fraction() as f64,
-        sa_to_vol: spheres.avg_volume() / spheres.avg_surface_area(),
-        sphere_count: packed.spheres.len(),
+///
+/// If `options.dims` is provided, the container has those half-side lengths along each axis;
+/// otherwise the container is a cube sized to hold about `options.target_count` spheres.
+///
+/// In either case spheres are placed until no more fit, so the realized count is not capped by
+/// the target count. Packing with the same options always gives the same result.
+///
+/// If `options.mesh` is non-empty, spheres are instead packed into the convex polyhedron bounded
+/// by its half-spaces, which must contain the origin, and the volume fraction is relative to the
+/// polyhedron's volume.
+///
+/// With `options.gravity` set, spheres settle toward the bottom of the cuboid as they're placed,
+/// and the volume fraction of each of `PROFILE_SLICES` slabs from the bottom up is reported, as it
+/// is for `options.density_profile` slabs if that is given.
+///
+/// With `options.dimensionality` set to two dimensions, disks with the radii of `spheres` are
+/// instead packed into a rectangle (or square), and the area fraction is reported in place of
+/// the volume fraction.
+///
+/// Before packing, the spheres are checked against the container's volume (or area) as
+/// `Spheres::feasibility` checks them, so that a mix which can't be packed fails fast. If
+/// `options.min_fraction` is given, a packing whose fraction is below it is an error,
+/// `SimError::FractionTooLow`, rather than a result.
+pub fn pack(spheres: &parsing::Spheres, options: &PackOptions) -> Result<SimOutput, SimError> {
+    if let Some(threshold) = options.min_fraction {
+        if !(0. ..=1.).contains(&threshold) {
+            return Err(SimError::InvalidMinFraction(threshold));
+        }
+    }
+    check_min_fraction(pack_ungated(spheres, options)?, options.min_fraction)
+}
+
+/// Provides `output`, or if its fraction is below `threshold`, the error saying so.
+fn check_min_fraction(output: SimOutput, threshold: Option<f64>) -> Result<SimOutput, SimError> {
+    match threshold {
+        Some(threshold) if output.volume_fraction() < threshold => Err(SimError::FractionTooLow {
+            got: output.volume_fraction(),
+            threshold,
+        }),
+        _ => Ok(output),
+    }
+}
+
+/// Pack spheres as `pack` does, whatever the fraction of the packing.
+fn pack_ungated(spheres: &parsing::Spheres, options: &PackOptions) -> Result<SimOutput, SimError> {
+    let PackOptions {
+        dims,
+        target_count,
+        fill_factor,
+        total_volume,
+        seed,
+        sample_seed,
+        placement_seed,
+        basis,
+        periodic,
+        wall_correction,
+        gravity,
+        density_profile,
+        rdf,
+        coordination,
+        contact_graph,
+        pore_samples,
+        relax,
+        snapshot_every,
+        track_growth,
+        precision,
+        dimensionality,
+        sampler,
+        sort_output: _,
+        explain: _,
+        ref initial,
+        ref obstacles,
+        ref mesh,
+        distribution,
+        ref quantize,
+        min_fraction: _,
+    } = *options;
+    if let Some(binning) = rdf {
+        if binning.bins == 0 || !(binning.r_max > 0. && binning.r_max.is_finite()) {
+            return Err(SimError::InvalidRdf(binning));
+        }
+    }
+    let config = RunConfig {
+        spheres,
+        target_count: target_count as f64,
+        fill_factor,
+        total_volume,
+        shape: if !mesh.is_empty() {
+            "mesh"
+        } else if dims.is_some() {
+            "cuboid"
+        } else {
+            "cube"
+        },
+        dims,
+        seed,
+        sample_seed,
+        placement_seed,
+        basis,
+        initial,
+        obstacles,
+        mesh,
+        distribution,
+        quantize: quantize.as_deref(),
+        relax,
+        dimensionality,
+        sampler,
+        gravity,
+    };
+    if let Some(volume) = total_volume {
+        if !(volume > 0. && volume.is_finite()) {
+            return Err(SimError::InvalidTotalVolume(volume));
+        }
+    }
+    for epsilon in coordination.into_iter().chain(contact_graph) {
+        if !(epsilon >= 0. && epsilon.is_finite()) {
+            return Err(SimError::InvalidContactEpsilon(epsilon));
+        }
+    }
+    if track_growth == Some(0) {
+        return Err(SimError::NoGrowthInterval);
+    }
+    if pore_samples == Some(0) {
+        return Err(SimError::NoPoreSamples);
+    }
+    basis.check(spheres)?;
+    if let Some(distribution) = distribution {
+        distribution
+            .validate()
+            .map_err(SimError::InvalidDistribution)?;
+        if basis != ProportionBasis::Number {
+            return Err(SimError::UnsupportedWithDistribution(
+                "a proportion basis other than number",
+            ));
+        }
+    }
+    if let Some(radii) = quantize {
+        if distribution.is_none() {
+            return Err(SimError::QuantizeWithoutDistribution);
+        }
+        if radii.is_empty() || !radii.iter().all(|r| *r > 0. && r.is_finite()) {
+            return Err(SimError::InvalidQuantization(radii.clone()));
+        }
+    }
+    if !mesh.is_empty() {
+        return pack_mesh(spheres, options, config);
+    }
+    if dimensionality == Dimensionality::Two {
+        return pack_2d(spheres, options, config);
+    }
+    let half_extents = container_half_extents(spheres, options);
+    let [x, y, z] = half_extents;
+    // Cuboid::new only rejects non-positive lengths, so NaN is checked for here too.
+    if !half_extents.iter().all(|h| h.is_finite() && *h > 0.0) {
+        return Err(SimError::InvalidContainer(half_extents.map(|h| 2. * h)));
+    }
+    let container = spherical_cow::shapes::Cuboid::new(x, y, z)?;
+    let slices = density_profile.or_else(|| gravity.then_some(PROFILE_SLICES));
+    if slices == Some(0) {
+        return Err(SimError::NoSlices);
+    }
+    if snapshot_every == Some(0) {
+        return Err(SimError::NoSnapshotInterval);
+    }
+    spheres.feasibility(8. * x as f64 * y as f64 * z as f64)?;
+    if !obstacles.is_empty() {
+        let unsupported = [
+            (relax > 0, "relaxation"),
+            (pore_samples.is_some(), "pore statistics"),
+            (total_volume.is_some(), "a total volume"),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+            return Err(SimError::UnsupportedWithObstacles(option));
+        }
+    }
+    let obstacles = obstacle_spheres(obstacles, &container)?;
+    let mut starting = initial_spheres(initial, &container)?;
+    if let Some((i, j)) = starting.iter().enumerate().find_map(|(i, sphere)| {
+        obstacles
+            .iter()
+            .position(|obstacle| overlapping(sphere, obstacle))
+            .map(|j| (i, j))
+    }) {
+        return Err(SimError::InitialOverlapsObstacle(i, j));
+    }
+    let split = options.split_seeds();
+    let quantized = quantize.as_deref().unwrap_or_default();
+    let (mut sizes, mut rng) =
+        sampler.sources(spheres, basis, distribution, quantized, seed, split)?;
+    if !obstacles.is_empty() && starting.is_empty() {
+        starting = front::seed_spheres(&container, half_extents, &obstacles, &mut sizes, &mut rng)
+            .ok_or(SimError::NoRoomAmongObstacles)?;
+    }
+    let mut placements = Placements::default();
+    // Obstacles are listed first, so that they can be split off the packing again.
+    let fixed = obstacles.len();
+    let mut packed = front::pack_spheres(
+        &container,
+        obstacles.into_iter().chain(starting).collect(),
+        &mut sizes,
+        &mut rng,
+        &mut placements,
+        gravity,
+        total_volume,
+    )?;
+    let obstacles: Vec<Sphere> = packed.drain(..fixed).collect();
+    let mut unrelaxed_volume_fraction = None;
+    let mut snapshots = Vec::new();
+    if relax > 0 {
+        unrelaxed_volume_fraction = Some(filled_fraction(&packed, half_extents, precision));
+        relax::compact(&mut packed, half_extents, relax, |pass, spheres| {
+            if snapshot_every.is_some_and(|every| pass % every == 0) {
+                snapshots.push((pass, spheres.to_vec()));
+            }
+        });
+        packed = front::pack_spheres(
+            &container,
+            packed,
+            &mut sizes,
+            &mut rng,
+            &mut placements,
+            gravity,
+            total_volume,
+        )?;
+    }
+    let fraction = filled_fraction(&packed, half_extents, precision);
+    let obstacle_fraction =
+        (fixed > 0).then(|| filled_fraction(&obstacles, half_extents, precision));
+    let sized_by_count = dims.is_none() && total_volume.is_none();
+    let mut output = SimOutput {
+        fraction: Fraction::Volume(
+            obstacle_fraction.map_or(fraction, |obstacles| precision.round(fraction + obstacles)),
+        ),
+        unrelaxed_volume_fraction,
+        volume_fraction_by_type: fractions_by_type(spheres, &packed, |packed| {
+            filled_fraction(packed, half_extents, precision)
+        }),
+        accessible_fraction_by_type: accessible_fractions(spheres, precision, |radius| {
+            cuboid_accessible_fraction(&half_extents, radius)
+        }),
+        target_count: sized_by_count.then_some(target_count),
+        count_ratio: sized_by_count
+            .then(|| precision.round(packed.len() as f64 / target_count as f64)),
+        half_extents,
+        container_volume: precision.round(8. * x as f64 * y as f64 * z as f64),
+        obstacle_fraction,
+        periodic_volume_fraction: periodic
+            .then(|| analysis::periodic_volume_fraction(&packed, half_extents))
+            .flatten()
+            .map(|fraction| precision.round(fraction)),
+        wall_corrected_volume_fraction: wall_correction
+            .then(|| analysis::wall_corrected_volume_fraction(&packed, half_extents, fraction))
+            .flatten()
+            .map(|fraction| precision.round(fraction)),
+        density_profile: slices.map(|slices| {
+            analysis::density_profile(&packed, half_extents, slices)
+                .into_iter()
+                .map(|fraction| precision.round(fraction))
+                .collect()
+        }),
+        growth_curve: track_growth.map(|every| {
+            let container_volume = 8. * x as f64 * y as f64 * z as f64;
+            growth_curve(&packed, every, precision, |s| {
+                sphere_volume(s.radius as f64) / container_volume
+            })
+        }),
+        rdf: rdf.and_then(|binning| analysis::radial_distribution(&packed, half_extents, binning)),
+        coordination: coordination_numbers(&packed, half_extents, coordination, precision),
+        contact_graph: contact_graph
+            .map(|epsilon| analysis::contact_graph(&packed, half_extents, epsilon)),
+        pore_stats: pore_samples.and_then(|samples| {
+            // A stream of its own keeps the packing the same whether or not pores are sampled.
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            analysis::pore_stats(&packed, half_extents, samples, &mut rng)
+        }),
+        snapshots,
+        ..SimOutput::new(spheres, options, packed, &config, placements)
+    };
+    if options.sort_output {
+        output.sort(spheres);
+    }
+    Ok(output)
+}
+
+/// Provides the element of `radii` nearest `radius`, if there are any.
+fn nearest(radii: &[f64], radius: f64) -> Option<f64> {
+    radii
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - radius).abs().total_cmp(&(b - radius).abs()))
+}
+
+/// Provides the number of spheres of `packed` of each of the quantized `radii`, if radii were
+/// quantized, with each sphere counted as the radius nearest its own.
+fn quantized_counts(radii: Option<&[f64]>, packed: &[Sphere]) -> Option<Vec<(f64, usize)>> {
+    let mut counts: Vec<(f64, usize)> = radii?.iter().map(|r| (*r, 0)).collect();
+    for sphere in packed {
+        let radius = sphere.radius as f64;
+        if let Some((_, count)) = counts
+            .iter_mut()
+            .min_by(|(a, _), (b, _)| (*a - radius).abs().total_cmp(&(*b - radius).abs()))
+        {
+            *count += 1;
+        }
+    }
+    Some(counts)
+}
+
+/// Provides the coordination numbers of `packed` in the cuboid container with `half_extents` as
+/// `analysis::coordination` does, with the mean rounded to `precision`, if `epsilon` is given.
+fn coordination_numbers(
+    packed: &[Sphere],
+    half_extents: [f32; 3],
+    epsilon: Option<f64>,
+    precision: Precision,
+) -> Option<analysis::Coordination> {
+    let mut coordination = analysis::coordination(packed, half_extents, epsilon?)?;
+    coordination.mean = precision.round(coordination.mean);
+    Some(coordination)
+}
+
+/// Provides the fraction of the cuboid container with `half_extents` filled by `packed`, computed
+/// at `precision`.
+fn filled_fraction(packed: &[Sphere], half_extents: [f32; 3], precision: Precision) -> f64 {
+    let [x, y, z] = half_extents;
+    match precision {
+        Precision::F32 => {
+            let filled: f32 = packed
+                .iter()
+                .map(|s| 4. * std::f32::consts::FRAC_PI_3 * s.radius.powi(3))
+                .fold(0., |filled, volume| filled + volume);
+            (filled / (2. * x * 2. * y * 2. * z)) as f64
+        }
+        Precision::F64 => packed_volume(packed) / (8. * x as f64 * y as f64 * z as f64),
+    }
+}
+
+/// Pack spheres into the convex polyhedron bounded by the half-spaces of `options.mesh` as `pack`
+/// does into a cuboid, where `config` describes `options`.
+fn pack_mesh(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    config: RunConfig,
+) -> Result<SimOutput, SimError> {
+    let unsupported = [
+        (options.periodic, "periodic boundaries"),
+        (options.wall_correction, "the wall correction"),
+        (options.gravity, "gravity"),
+        (options.density_profile.is_some(), "the density profile"),
+        (options.rdf.is_some(), "the radial distribution function"),
+        (options.pore_samples.is_some(), "pore statistics"),
+        (options.relax > 0, "relaxation"),
+        (!options.obstacles.is_empty(), "obstacles"),
+        (options.dims.is_some(), "dimensions"),
+        (
+            options.dimensionality == Dimensionality::Two,
+            "two dimensional packing",
+        ),
+    ];
+    if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+        return Err(SimError::UnsupportedWithMesh(option));
+    }
+    let container = polyhedron::Polyhedron::new(&options.mesh).map_err(SimError::InvalidMesh)?;
+    spheres.feasibility(container.volume_f64())?;
+    let precision = options.precision;
+    let (mut sizes, mut rng) = options.sampler.sources(
+        spheres,
+        options.basis,
+        options.distribution,
+        options.quantize.as_deref().unwrap_or_default(),
+        options.seed,
+        options.split_seeds(),
+    )?;
+    let mut placements = Placements::default();
+    let packed = front::pack_spheres(
+        &container,
+        initial_spheres(&options.initial, &container)?,
+        &mut sizes,
+        &mut rng,
+        &mut placements,
+        false,
+        options.total_volume,
+    )?;
+    let volume = container.volume_f64();
+    let fraction = |packed: &[Sphere]| match precision {
+        Precision::F32 => {
+            let filled: f32 = packed
+                .iter()
+                .map(|s| 4. * std::f32::consts::FRAC_PI_3 * s.radius.powi(3))
+                .fold(0., |filled, volume| filled + volume);
+            (filled / volume as f32) as f64
+        }
+        Precision::F64 => packed_volume(packed) / volume,
+    };
+    let mut output = SimOutput {
+        fraction: Fraction::Volume(fraction(&packed)),
+        volume_fraction_by_type: fractions_by_type(spheres, &packed, fraction),
+        accessible_fraction_by_type: accessible_fractions(spheres, precision, |radius| {
+            container.accessible_volume(radius) / volume
+        }),
+        half_extents: container.half_extents(),
+        container_volume: precision.round(volume),
+        growth_curve: options.track_growth.map(|every| {
+            growth_curve(&packed, every, precision, |s| {
+                sphere_volume(s.radius as f64) / volume
+            })
+        }),
+        coordination: coordination_numbers(
+            &packed,
+            container.half_extents(),
+            options.coordination,
+            precision,
+        ),
+        contact_graph: options
+            .contact_graph
+            .map(|epsilon| analysis::contact_graph(&packed, container.half_extents(), epsilon)),
+        ..SimOutput::new(spheres, options, packed, &config, placements)
+    };
+    if options.sort_output {
+        output.sort(spheres);
+    }
+    Ok(output)
+}
+
+/// Pack disks with the radii of `spheres` into a rectangle as `pack` does for spheres, where
+/// `config` describes `options`.
+fn pack_2d(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    config: RunConfig,
+) -> Result<SimOutput, SimError> {
+    let unsupported = [
+        (options.periodic, "periodic boundaries"),
+        (options.wall_correction, "the wall correction"),
+        (options.gravity, "gravity"),
+        (options.density_profile.is_some(), "the density profile"),
+        (options.rdf.is_some(), "the radial distribution function"),
+        (options.pore_samples.is_some(), "pore statistics"),
+        (options.relax > 0, "relaxation"),
+        (!options.initial.is_empty(), "warm starting"),
+        (!options.obstacles.is_empty(), "obstacles"),
+        (options.total_volume.is_some(), "a total volume"),
+    ];
+    if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+        return Err(SimError::UnsupportedIn2d(option));
+    }
+    let half_extents = container_half_extents(spheres, options);
+    let [x, y, _] = half_extents;
+    if ![x, y].iter().all(|h| h.is_finite() && *h > 0.0) {
+        return Err(SimError::InvalidContainer(half_extents.map(|h| 2. * h)));
+    }
+    check_feasible(spheres, 4. * x as f64 * y as f64, |r| PI * r.powi(2))?;
+    let precision = options.precision;
+    let (mut sizes, mut rng) = options.sampler.sources(
+        spheres,
+        options.basis,
+        options.distribution,
+        options.quantize.as_deref().unwrap_or_default(),
+        options.seed,
+        options.split_seeds(),
+    )?;
+    let mut placements = Placements::default();
+    let packed = disk::pack_disks([x, y], &mut sizes, &mut rng, &mut placements)?;
+    let target_count = options.target_count;
+    let area = |packed: &[Sphere]| filled_area_fraction(packed, [x, y], precision);
+    let mut output = SimOutput {
+        fraction: Fraction::Area(area(&packed)),
+        volume_fraction_by_type: fractions_by_type(spheres, &packed, area),
+        accessible_fraction_by_type: accessible_fractions(spheres, precision, |radius| {
+            cuboid_accessible_fraction(&[x, y], radius)
+        }),
+        target_count: options.dims.is_none().then_some(target_count),
+        count_ratio: options
+            .dims
+            .is_none()
+            .then(|| precision.round(packed.len() as f64 / target_count as f64)),
+        half_extents,
+        container_volume: precision.round(4. * x as f64 * y as f64),
+        growth_curve: options.track_growth.map(|every| {
+            let container_area = 4. * x as f64 * y as f64;
+            growth_curve(&packed, every, precision, |s| {
+                PI * (s.radius as f64).powi(2) / container_area
+            })
+        }),
+        coordination: coordination_numbers(&packed, half_extents, options.coordination, precision),
+        contact_graph: options
+            .contact_graph
+            .map(|epsilon| analysis::contact_graph(&packed, half_extents, epsilon)),
+        ..SimOutput::new(spheres, options, packed, &config, placements)
+    };
+    if options.sort_output {
+        output.sort(spheres);
+    }
+    Ok(output)
+}
+
+/// Provides the number of spheres and the fraction of the container they filled after every
+/// `every` spheres of `packed` and after the last, in the order they were placed (starting with
+/// any initial spheres), where `fraction` gives the fraction of the container a sphere fills.
+///
+/// Relaxation moves (and may remove) spheres without reordering them, so the spheres packed into
+/// the space it frees up are at the end of the curve.
+fn growth_curve<F: Fn(&Sphere) -> f64>(
+    packed: &[Sphere],
+    every: usize,
+    precision: Precision,
+    fraction: F,
+) -> Vec<(usize, f64)> {
+    let mut filled = 0.;
+    let mut curve = Vec::with_capacity(packed.len() / every + 1);
+    for (i, sphere) in packed.iter().enumerate() {
+        filled += fraction(sphere);
+        let count = i + 1;
+        if count % every == 0 || count == packed.len() {
+            curve.push((count, precision.round(filled)));
+        }
+    }
+    curve
+}
+
+/// Provides the fraction of the rectangle with `half_extents` covered by the disks `packed`, which
+/// are centered in it, computed at `precision`.
+fn filled_area_fraction(packed: &[Sphere], half_extents: [f32; 2], precision: Precision) -> f64 {
+    let [x, y] = half_extents;
+    match precision {
+        Precision::F32 => {
+            let filled: f32 = packed
+                .iter()
+                .map(|s| std::f32::consts::PI * s.radius.powi(2))
+                .fold(0., |filled, area| filled + area);
+            (filled / (2. * x * 2. * y)) as f64
+        }
+        Precision::F64 => {
+            let filled = packed
+                .iter()
+                .fold(0., |filled, s| filled + PI * (s.radius as f64).powi(2));
+            filled / (4. * x as f64 * y as f64)
+        }
+    }
+}
+
+/// Provides the name of each type in `spheres` and the `fraction` of the container filled by the
+/// spheres of `packed` with the nearest radius to it.
+fn fractions_by_type<F: Fn(&[Sphere]) -> f64>(
+    spheres: &parsing::Spheres,
+    packed: &[Sphere],
+    fraction: F,
+) -> Vec<(String, f64)> {
+    let mut by_type = vec![Vec::new(); spheres.iter().count()];
+    for sphere in packed {
+        by_type[spheres.nearest_type(sphere.radius as f64)].push(sphere.clone());
+    }
+    spheres
+        .names()
+        .zip(by_type)
+        .map(|(name, packed)| (name.to_string(), fraction(&packed)))
+        .collect()
+}
+
+/// Provides the name of each type in `spheres` and the `accessible` fraction of the container for
+/// its radius, rounded to `precision`.
+fn accessible_fractions<F: Fn(f64) -> f64>(
+    spheres: &parsing::Spheres,
+    precision: Precision,
+    accessible: F,
+) -> Vec<(String, f64)> {
+    spheres
+        .iter()
+        .map(|s| {
+            (
+                s.name().to_string(),
+                precision.round(accessible(s.radius())),
+            )
+        })
+        .collect()
+}
+
+/// Provides the fraction of the origin-centered cuboid (or rectangle) with `half_extents` in which
+/// the center of a sphere with `radius` can be while the sphere stays inside it, which is the
+/// cuboid shrunk by `radius` on every side.
+fn cuboid_accessible_fraction(half_extents: &[f32], radius: f64) -> f64 {
+    half_extents
+        .iter()
+        .map(|h| (1. - radius.max(0.) / *h as f64).max(0.))
+        .product()
+}
+
+/// Pack spheres as `pack` does, additionally reporting how quickly the packing was computed.
+///
+/// If `warmup` is set, the spheres are first packed once without being timed, so that the timed
+/// packing isn't slowed by cold caches or lazily initialized memory.
+pub fn benchmark(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    warmup: bool,
+) -> Result<SimOutput, SimError> {
+    if warmup {
+        pack(spheres, options)?;
+    }
+    let start = Instant::now();
+    let mut output = pack(spheres, options)?;
+    let elapsed = start.elapsed().as_secs_f64();
+    output.throughput = Some(Throughput {
+        elapsed_ms: elapsed * 1e3,
+        spheres_per_second: output.sphere_count as f64 / elapsed,
+    });
+    Ok(output)
+}
+
+/// Pack spheres as `pack` does, invoking `callback` with the center and radius of each packed
+/// sphere, such as to draw the packing as it is built up.
+///
+/// The callback is invoked once packing has finished rather than as each sphere is placed, but in
+/// the order spheres were placed in: the spheres packing started from (`options.initial`, or the
+/// first three spheres drawn) first, and then each sphere in turn as it was added, so that
+/// replaying the calls shows the packing growing. There are two exceptions: with `options.relax`,
+/// spheres are at their final, relaxed positions, and with `options.sort_output`, they are in
+/// sorted order as `SimOutput::sort` arranges them.
+pub fn pack_with_callback<F>(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    mut callback: F,
+) -> Result<SimOutput, SimError>
+where
+    F: FnMut([f32; 3], f32),
+{
+    let output = pack(spheres, options)?;
+    for sphere in &output.spheres {
+        let c = sphere.center;
+        callback([c.x, c.y, c.z], sphere.radius);
+    }
+    Ok(output)
+}
+
+/// Most times `pack_to_count` resizes the container.
+const MAX_RESIZES: usize = 10;
+
+/// Most the container's volume is scaled up or down by in one resize, so that a packing which
+/// stopped far short of filling its container doesn't size the next one far too large.
+const MAX_RESIZE_FACTOR: f32 = 2.;
+
+/// Pack spheres as `pack` does, then resize the container and pack again until the number of
+/// spheres packed is within `tolerance`, a fraction of `options.target_count`, of the target
+/// count, reporting the number of resizes as `resize_iterations`.
+///
+/// The count grows with the container's volume, so each resize scales the fill factor (and with
+/// it the volume) by the ratio of the target count to the last count, by at most
+/// `MAX_RESIZE_FACTOR`. After `MAX_RESIZES` resizes, the packing with the count closest to the
+/// target is provided even if it isn't within `tolerance`. The container must be sized by the
+/// target count, so neither `options.dims` nor `options.mesh` can be given. Only the packing
+/// provided is held to `options.min_fraction`.
+pub fn pack_to_count(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    tolerance: f64,
+) -> Result<SimOutput, SimError> {
+    if !(tolerance >= 0. && tolerance.is_finite()) {
+        return Err(SimError::InvalidCountTolerance(tolerance));
+    }
+    if options.dims.is_some() || !options.mesh.is_empty() || options.total_volume.is_some() {
+        return Err(SimError::ToleranceWithoutTarget);
+    }
+    let target = options.target_count as f64;
+    let deviation = |count: usize| (count as f64 / target - 1.).abs();
+    let min_fraction = options.min_fraction;
+    let mut options = PackOptions {
+        min_fraction: None,
+        ..options.clone()
+    };
+    let mut best = pack(spheres, &options)?;
+    let (mut count, mut resizes) = (best.sphere_count, 0);
+    while deviation(count) > tolerance && resizes < MAX_RESIZES {
+        let factor = (target / count.max(1) as f64) as f32;
+        options.fill_factor *= factor.clamp(1. / MAX_RESIZE_FACTOR, MAX_RESIZE_FACTOR);
+        let output = pack(spheres, &options)?;
+        resizes += 1;
+        count = output.sphere_count;
+        if deviation(count) < deviation(best.sphere_count) {
+            best = output;
+        }
+    }
+    best.resize_iterations = Some(resizes);
+    check_min_fraction(best, min_fraction)
+}
+
+/// Ratio of the default cube's volume to the total volume of spheres to pack, when one is given.
+/// The advancing front fills little more than half of a container, and less next to its walls, so
+/// this leaves room for the packing to reach the total volume before the cube fills up.
+const TOTAL_VOLUME_FILL_FACTOR: f32 = 4.;
+
+/// Pack spheres as `pack` does, but if packing fails, keep packing again with the next seed after
+/// the last (wrapping around) until it succeeds or `timeout` has elapsed, reporting the number of
+/// seeds tried as `persist_attempts`. The output's `seed` is that of the packing which succeeded.
+///
+/// Packing fails if the packer can't place its starting spheres in the container, or if it stops
+/// without adding a single sphere to them, both of which can depend on the radii drawn first, or
+/// if the packing fills less than `options.min_fraction`. Other errors, which don't depend on the
+/// seed, are provided at once. The timeout is checked between attempts, so the last attempt may
+/// finish after it. Only `options.seed` is varied, so separate sample and placement seeds are kept
+/// for every attempt.
+pub fn pack_persistently(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    timeout: Duration,
+) -> Result<SimOutput, SimError> {
+    let starting = if !options.initial.is_empty() {
+        options.initial.len()
+    } else if options.dimensionality == Dimensionality::Two {
+        2
+    } else {
+        3
+    };
+    let start = Instant::now();
+    let mut options = options.clone();
+    let first_seed = options.seed;
+    let mut attempts = 0;
+    while attempts == 0 || start.elapsed() < timeout {
+        options.seed = trial_seed(first_seed, attempts);
+        attempts += 1;
+        match pack(spheres, &options) {
+            Ok(mut output) if output.sphere_count > starting => {
+                output.persist_attempts = Some(attempts);
+                return Ok(output);
+            }
+            Ok(_) | Err(SimError::FailedToPack(_) | SimError::FractionTooLow { .. }) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Err(SimError::PersistTimedOut(attempts, timeout))
+}
+
+/// Pack the sphere types of `spheres` with radii of at least `threshold` as `pack` does, and then
+/// the smaller types into the voids left between them, reporting the fraction of the container
+/// filled in each stage as `stages`.
+///
+/// Each group's proportions are re-normalized to sum to 100 on their own, and each stage packs
+/// until no more of its spheres fit, so the proportions between the groups aren't kept. Both
+/// stages pack into the same container, which unless `options.dims` or `options.mesh` is given is
+/// the cube sized to hold about `options.target_count` spheres of the whole of `spheres`. The
+/// per-type properties of the output are of every type in `spheres`. Only the packing of both
+/// stages is held to `options.min_fraction`.
+pub fn pack_two_stage(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    threshold: f64,
+) -> Result<SimOutput, SimError> {
+    if options.distribution.is_some() {
+        return Err(SimError::UnsupportedWithDistribution("two-stage packing"));
+    }
+    let (large, small) = spheres
+        .split_at_radius(threshold)
+        .map_err(SimError::InvalidSplit)?;
+    let dims = if options.mesh.is_empty() {
+        Some(container_half_extents(spheres, options))
+    } else {
+        None
+    };
+    let first = pack(
+        &large,
+        &PackOptions {
+            dims,
+            min_fraction: None,
+            ..options.clone()
+        },
+    )?;
+    let initial = first
+        .spheres
+        .iter()
+        .map(|s| parsing::PlacedSphere {
+            x: s.center.x,
+            y: s.center.y,
+            z: s.center.z,
+            radius: s.radius,
+        })
+        .collect();
+    let mut output = pack(
+        &small,
+        &PackOptions {
+            dims,
+            initial,
+            ..options.clone()
+        },
+    )?;
+    let precision = options.precision;
+    let container_volume = output.container_volume;
+    output.volume_fraction_by_type = fractions_by_type(spheres, &output.spheres, |packed| {
+        precision.round(packed_volume(packed) / container_volume)
+    });
+    output.accessible_fraction_by_type = spheres
+        .names()
+        .filter_map(|name| {
+            first
+                .accessible_fraction_by_type
+                .iter()
+                .chain(&output.accessible_fraction_by_type)
+                .find(|(n, _)| n == name)
+                .cloned()
+        })
+        .collect();
+    output.min_counts = min_counts(spheres, &output.spheres);
+    output.sa_to_vol = precision.round(
+        number_mean(spheres, options.basis, sphere_volume)
+            / number_mean(spheres, options.basis, sphere_surface_area),
+    );
+    output.proportion_entropy = precision.round(spheres.proportion_entropy());
+    output.stages = Some(Stages {
+        radius_threshold: threshold,
+        large_fraction: first.volume_fraction(),
+        small_fraction: precision.round(output.volume_fraction() - first.volume_fraction()),
+    });
+    if options.sort_output {
+        output.sort(spheres);
+    }
+    Ok(output)
+}
+
+/// Relative amount two initial spheres may overlap by without being rejected, since spheres packed
+/// tangent to each other can overlap slightly due to rounding.
+const OVERLAP_TOLERANCE: f32 = 1e-5;
+
+/// Whether `a` and `b` overlap by more than `OVERLAP_TOLERANCE`.
+fn overlapping(a: &Sphere, b: &Sphere) -> bool {
+    nalgebra::distance(&a.center, &b.center) < (a.radius + b.radius) * (1. - OVERLAP_TOLERANCE)
+}
+
+/// Convert `placed` into spheres, checking that each fits in `container` and that none overlap by
+/// more than `OVERLAP_TOLERANCE`, with `uncontained` and `overlap` providing the error for a sphere
+/// which doesn't fit and for two which overlap.
+fn placed_spheres<C: Container>(
+    placed: &[parsing::PlacedSphere],
+    container: &C,
+    uncontained: fn(usize) -> SimError,
+    overlap: fn(usize, usize) -> SimError,
+) -> Result<Vec<Sphere>, SimError> {
+    let spheres = placed
+        .iter()
+        .map(|s| Sphere::new(Point3::new(s.x, s.y, s.z), s.radius))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, sphere) in spheres.iter().enumerate() {
+        if !container.contains(sphere) {
+            return Err(uncontained(i));
+        }
+        if let Some(j) = spheres[i + 1..].iter().position(|s| overlapping(s, sphere)) {
+            return Err(overlap(i, i + 1 + j));
+        }
+    }
+    Ok(spheres)
+}
+
+/// Convert `initial` into spheres as `placed_spheres` does.
+fn initial_spheres<C: Container>(
+    initial: &[parsing::PlacedSphere],
+    container: &C,
+) -> Result<Vec<Sphere>, SimError> {
+    placed_spheres(
+        initial,
+        container,
+        SimError::InitialUncontained,
+        SimError::InitialOverlap,
+    )
+}
+
+/// Convert `obstacles` into spheres as `placed_spheres` does.
+fn obstacle_spheres<C: Container>(
+    obstacles: &[parsing::PlacedSphere],
+    container: &C,
+) -> Result<Vec<Sphere>, SimError> {
+    placed_spheres(
+        obstacles,
+        container,
+        SimError::ObstacleUncontained,
+        SimError::ObstacleOverlap,
+    )
+}
+
+/// Provides the half-side lengths of the container `spheres` are packed into under `options`.
+///
+/// A two dimensional container is a rectangle, or by default a square, whose last half-side
+/// length is 0.
+pub fn container_half_extents(spheres: &parsing::Spheres, options: &PackOptions) -> [f32; 3] {
+    if options.dimensionality == Dimensionality::Two {
+        return match options.dims {
+            Some([x, y, _]) => [x, y, 0.],
+            None => {
+                let mean_area = number_mean(spheres, options.basis, |r| PI * r.powi(2));
+                let disk_area = (mean_area * options.target_count as f64) as f32;
+                let side = (disk_area * options.fill_factor).sqrt();
+                [side / 2., side / 2., 0.]
+            }
+        };
+    }
+    options.dims.unwrap_or_else(|| {
+        let cube_volume = match options.total_volume {
+            Some(volume) => volume as f32 * TOTAL_VOLUME_FILL_FACTOR,
+            None => {
+                let mean_volume = number_mean(spheres, options.basis, sphere_volume);
+                (mean_volume * options.target_count as f64) as f32 * options.fill_factor
+            }
+        };
+        let cube_side = cube_volume.cbrt();
+        [cube_side / 2.; 3]
     })
 }
+
+/// Estimate the volume fraction of packing `spheres`, whose proportions are fractions of `basis`,
+/// into a cube of volume `container_volume`, without packing.
+///
+/// The bulk fraction follows the random close packing model of Desmond and Weeks, Phys. Rev. E 90,
+/// 022204 (2014): `0.634 + 0.0658 δ + 0.0857 S δ²`, where `δ` is the polydispersity (standard
+/// deviation over mean) and `S` the skewness of the sphere radii. Spheres can't fill the space
+/// within about half a mean radius of each wall, so the bulk fraction is scaled by the fraction
+/// of the cube outside that layer.
+///
+/// This is only an approximation: the model was fit to mildly polydisperse mixtures (`δ` up to
+/// about 0.4) and to jammed packings, which are denser than the advancing front packings `pack`
+/// produces, so it is best treated as an upper bound.
+pub fn estimate_volume_fraction(
+    spheres: &parsing::Spheres,
+    basis: ProportionBasis,
+    container_volume: f64,
+) -> f64 {
+    let mean = number_mean(spheres, basis, |r| r);
+    let variance = number_mean(spheres, basis, |r| (r - mean).powi(2));
+    let third = number_mean(spheres, basis, |r| (r - mean).powi(3));
+    let polydispersity = variance.sqrt() / mean;
+    let skewness = if variance > 0. {
+        third / variance.powf(1.5)
+    } else {
+        0.
+    };
+    let bulk = 0.634 + 0.0658 * polydispersity + 0.0857 * skewness * polydispersity.powi(2);
+    let side = container_volume.cbrt();
+    let interior = (1. - mean / side).max(0.).powi(3);
+    bulk * interior
+}
+
+/// A struct containing the outputs of several sphere packing simulations.
+#[derive(Serialize)]
+pub struct TrialsOutput {
+    /// Number of trials which completed before any time budget ran out
+    trials_run: usize,
+    /// Number of trials dropped as outliers, which `aggregate`, `volume_fraction_ci` and `trials`
+    /// leave out, if outliers were dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dropped_trials: Option<usize>,
+    /// Statistics of the trials' results
+    aggregate: AggregateOutput,
+    /// Confidence level of `volume_fraction_ci`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence_level: Option<f64>,
+    /// Normal-approximation confidence interval for the mean volume fraction (or area fraction),
+    /// absent if fewer than two trials were run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume_fraction_ci: Option<(f64, f64)>,
+    trials: Vec<SimOutput>,
+}
+
+impl TrialsOutput {
+    /// Summarize `results`, the results of every trial run, with the confidence interval at
+    /// `DEFAULT_CONFIDENCE_LEVEL`.
+    fn new(results: Vec<SimOutput>) -> Result<Self, SimError> {
+        let mut output = TrialsOutput {
+            trials_run: results.len(),
+            dropped_trials: None,
+            aggregate: SimOutput::aggregate(&results),
+            confidence_level: None,
+            volume_fraction_ci: None,
+            trials: results,
+        };
+        output.set_confidence_level(DEFAULT_CONFIDENCE_LEVEL)?;
+        Ok(output)
+    }
+
+    /// Provides the results of each trial which was run, other than any dropped as outliers.
+    pub fn trials(&self) -> &[SimOutput] {
+        &self.trials
+    }
+
+    /// Provides the confidence interval for the mean volume fraction over the trials, if at least
+    /// two were run.
+    pub fn volume_fraction_ci(&self) -> Option<(f64, f64)> {
+        self.volume_fraction_ci
+    }
+
+    /// Report the confidence interval for the mean volume fraction at `level`, which must be
+    /// strictly between 0 and 1, instead of at `DEFAULT_CONFIDENCE_LEVEL`.
+    pub fn set_confidence_level(&mut self, level: f64) -> Result<(), SimError> {
+        if !(level > 0. && level < 1.) {
+            return Err(SimError::InvalidConfidenceLevel(level));
+        }
+        let fractions: Vec<f64> = self.trials.iter().map(SimOutput::volume_fraction).collect();
+        self.volume_fraction_ci = normal_interval(&fractions, level);
+        self.confidence_level = self.volume_fraction_ci.map(|_| level);
+        Ok(())
+    }
+
+    /// Drop the trials whose volume fraction (or area fraction) is more than `k` standard
+    /// deviations below the mean, so that a degenerate packing doesn't drag down the statistics,
+    /// and provide how many were dropped. `k` must be non-negative and finite.
+    ///
+    /// This takes two passes over the trials. The first finds the mean and population standard
+    /// deviation of every trial's volume fraction, and so the threshold of `mean - k std`. The
+    /// second keeps the trials at or above the threshold, which always includes the densest, and
+    /// recomputes the aggregate statistics and the confidence interval (at the level it was last
+    /// set to) over them. The threshold isn't recomputed over the trials kept, so a trial which is
+    /// only an outlier among them stays.
+    pub fn drop_outliers(&mut self, k: f64) -> Result<usize, SimError> {
+        if !(k >= 0. && k.is_finite()) {
+            return Err(SimError::InvalidOutlierThreshold(k));
+        }
+        let fractions: Vec<f64> = self.trials.iter().map(SimOutput::volume_fraction).collect();
+        let kept = self.trials.len();
+        if let Some(stats) = Stats::of(&fractions) {
+            // Rounding can put the mean of equal fractions just above them.
+            let threshold = (stats.mean - k * stats.std).min(stats.max);
+            self.trials
+                .retain(|trial| trial.volume_fraction() >= threshold);
+        }
+        let dropped = kept - self.trials.len();
+        self.dropped_trials = Some(self.dropped_trials.unwrap_or(0) + dropped);
+        self.aggregate = SimOutput::aggregate(&self.trials);
+        self.set_confidence_level(self.confidence_level.unwrap_or(DEFAULT_CONFIDENCE_LEVEL))?;
+        Ok(dropped)
+    }
+}
+
+/// Pack spheres `trials` times across `threads` threads, returning the results of each packing in
+/// trial order or an error if any trial fails.
+///
+/// The seed of each trial is derived from `options.seed` and the trial's index, so the results
+/// depend only on `options` and not on `threads` or scheduling. If `budget` is provided, no new
+/// trial is started once `budget` has elapsed since the first trial started, and only the trials
+/// which completed are reported.
+pub fn pack_trials(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    trials: usize,
+    budget: Option<Duration>,
+    threads: usize,
+) -> Result<TrialsOutput, SimError> {
+    pack_trials_with(spheres, options, trials, budget, threads, |_| Ok(()))
+}
+
+/// Pack spheres as `pack_trials` does, passing each successful trial's result to `on_result` as
+/// soon as it completes, so that results can be saved before every trial has finished.
+///
+/// `on_result` is called from one thread at a time, in the order trials complete, which depends on
+/// scheduling if `threads` is more than 1. If it returns an error, no new trial is started and the
+/// error is returned.
+pub fn pack_trials_with<F>(
+    spheres: &parsing::Spheres,
+    options: &PackOptions,
+    trials: usize,
+    budget: Option<Duration>,
+    threads: usize,
+    on_result: F,
+) -> Result<TrialsOutput, SimError>
+where
+    F: FnMut(&SimOutput) -> io::Result<()> + Send,
+{
+    let start = Instant::now();
+    let next_trial = AtomicUsize::new(0);
+    let on_result = Mutex::new(on_result);
+    let report_error = Mutex::new(None);
+    let stopped = AtomicBool::new(false);
+    let run_trials = || {
+        let mut completed = Vec::new();
+        loop {
+            if stopped.load(Ordering::Relaxed)
+                || budget.is_some_and(|budget| start.elapsed() >= budget)
+            {
+                break;
+            }
+            let trial = next_trial.fetch_add(1, Ordering::Relaxed);
+            if trial >= trials {
+                break;
+            }
+            let options = PackOptions {
+                seed: trial_seed(options.seed, trial),
+                ..options.clone()
+            };
+            let result = pack(spheres, &options);
+            if let Ok(output) = &result {
+                if let Err(error) = (on_result.lock().unwrap())(output) {
+                    *report_error.lock().unwrap() = Some(error);
+                    stopped.store(true, Ordering::Relaxed);
+                }
+            }
+            completed.push((trial, result));
+        }
+        completed
+    };
+    let mut completed: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.max(1))
+            .map(|_| scope.spawn(run_trials))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("trial thread panicked"))
+            .collect()
+    });
+    if let Some(error) = report_error.into_inner().unwrap() {
+        return Err(SimError::FailedToReport(error));
+    }
+    completed.sort_by_key(|(trial, _)| *trial);
+    let results = completed
+        .into_iter()
+        .map(|(_, result)| result)
+        .collect::<Result<Vec<_>, _>>()?;
+    TrialsOutput::new(results)
+}
+
+/// Derive the seed of trial number `trial` from the base seed `seed`.
+fn trial_seed(seed: u64, trial: usize) -> u64 {
+    seed.wrapping_add(trial as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::time::Duration;
+
+    use rand::distributions::Distribution;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use spherical_cow::Container;
+
+    use crate::analysis::RdfBins;
+    use crate::packing::{
+        benchmark, estimate_volume_fraction, normal_interval, normal_quantile, pack,
+        pack_persistently, pack_to_count, pack_trials, pack_trials_with, pack_two_stage,
+        pack_with_callback, packed_volume, polyhedron, serialize_metric, sphere_volume,
+        Dimensionality, Fraction, PackOptions, Placements, Precision, ProportionBasis, RunConfig,
+        Sampler, SimError, SimOutput, Stats, StopReason, Termination, TrialsOutput,
+        WeightedRadiusDistribution, FIELDS,
+    };
+    use crate::parsing::{self, Format, HalfSpace, PlacedSphere, Spheres};
+
+    static VALID: &str = r#"[{"name": "5_micron_Al", "radius": 5.0, "proportion": 100}]"#;
+    static BIMODAL: &str = r#"[
+        {"name": "small", "radius": 1.0, "proportion": 60},
+        {"name": "large", "radius": 2.0, "proportion": 40}
+    ]"#;
+
+    #[test]
+    fn exhausted_budget_runs_no_trials() {
+        let output = pack_trials(
+            &VALID.parse().unwrap(),
+            &PackOptions::default(),
+            10,
+            Some(Duration::from_secs(0)),
+            1,
+        )
+        .unwrap();
+        assert_eq!(0, output.trials_run);
+        assert!(output.trials.is_empty());
+    }
+
+    #[test]
+    fn packs_unvalidated_spheres() {
+        let unvalidated =
+            |input: &str| Spheres::from_reader_unchecked(input.as_bytes(), Format::Json).unwrap();
+        let options = PackOptions {
+            dims: Some([10., 10., 10.]),
+            ..PackOptions::default()
+        };
+        let unnormalized = unvalidated(
+            r#"[{"name": "small", "radius": 1, "proportion": 30},
+                {"name": "large", "radius": 2, "proportion": 20}]"#,
+        );
+        assert!(pack(&unnormalized, &options).unwrap().spheres.len() > 3);
+        let undrawable = unvalidated(r#"[{"name": "none", "radius": 1, "proportion": 0}]"#);
+        assert!(matches!(
+            pack(&undrawable, &options),
+            Err(SimError::UndrawableRadii(_))
+        ));
+        let negative = unvalidated(
+            r#"[{"name": "negative", "radius": -1, "proportion": 50},
+                {"name": "positive", "radius": 2, "proportion": 50}]"#,
+        );
+        let volume = PackOptions {
+            basis: ProportionBasis::Volume,
+            ..options.clone()
+        };
+        assert!(pack(&negative, &options).is_err());
+        assert!(matches!(
+            pack(&negative, &volume),
+            Err(SimError::UndrawableRadii(_))
+        ));
+    }
+
+    #[test]
+    fn fractional_weights() {
+        let dist =
+            WeightedRadiusDistribution::new(vec![(1.0, 0.0), (2.0, 0.25), (3.0, 0.0)]).unwrap();
+        let mut rng = rand::thread_rng();
+        assert!(dist.sample_iter(&mut rng).take(100).all(|r| r == 2.0));
+    }
+
+    #[test]
+    fn draws_rare_counted_types() {
+        // 1 of 801 spheres is about 0.12%, which rounds to no percent at all.
+        let counts = r#"[
+            {"name": "a", "radius": 1.0, "proportion": 500},
+            {"name": "b", "radius": 2.0, "proportion": 300},
+            {"name": "c", "radius": 3.0, "proportion": 1}
+        ]"#;
+        let spheres = Spheres::from_reader_counts(counts.as_bytes(), Format::Json, false).unwrap();
+        let dist =
+            WeightedRadiusDistribution::from_spheres(&spheres, ProportionBasis::Number).unwrap();
+        let draws = dist.sample_iter(ChaCha8Rng::seed_from_u64(0)).take(80_100);
+        let rare = draws.filter(|&r| r == 3.0).count();
+        assert!((rare as f64 - 100.).abs() < 30., "{}", rare);
+    }
+
+    #[test]
+    fn reports_filled_container() {
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            ..Default::default()
+        };
+        let output = pack(&VALID.parse().unwrap(), &options).unwrap();
+        assert_eq!(
+            format!(
+                "filled the container with {} spheres, a volume fraction of {}",
+                output.sphere_count,
+                output.volume_fraction()
+            ),
+            output.fill_report()
+        );
+        let two_dimensional = PackOptions {
+            dims: Some([20., 20., 0.]),
+            dimensionality: Dimensionality::Two,
+            ..options
+        };
+        let output = pack(&VALID.parse().unwrap(), &two_dimensional).unwrap();
+        assert_eq!(
+            format!(
+                "filled the container with {} disks, an area fraction of {}",
+                output.sphere_count,
+                output.volume_fraction()
+            ),
+            output.fill_report()
+        );
+    }
+
+    #[test]
+    fn trials_independent_of_threads() {
+        let spheres = VALID.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            seed: 42,
+            ..Default::default()
+        };
+        let run = |threads| {
+            let output = pack_trials(&spheres, &options, 8, None, threads).unwrap();
+            serde_json::to_string(&output).unwrap()
+        };
+        assert_eq!(run(1), run(8));
+    }
+
+    #[test]
+    fn volume_fraction_confidence_interval() {
+        assert!((normal_quantile(0.975) - 1.959964).abs() < 1e-6);
+        assert!((normal_quantile(0.005) + 2.575829).abs() < 1e-6);
+        assert_eq!(0., normal_quantile(0.5));
+        // Mean 2 and sample standard deviation 1 over 4 values give a half width of z / 2.
+        let (lower, upper) = normal_interval(&[1., 2., 2., 3.], 0.95).unwrap();
+        let half_width = 1.959964 * (2f64 / 3.).sqrt() / 2.;
+        assert!(
+            (lower - (2. - half_width)).abs() < 1e-6 && (upper - (2. + half_width)).abs() < 1e-6
+        );
+        assert_eq!(None, normal_interval(&[1.], 0.95));
+
+        let spheres = VALID.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            ..Default::default()
+        };
+        let mut output = pack_trials(&spheres, &options, 4, None, 1).unwrap();
+        let (lower, upper) = output.volume_fraction_ci().unwrap();
+        let mean = output.aggregate.volume_fraction.unwrap().mean;
+        assert!(lower < mean && mean < upper, "{} {} {}", lower, mean, upper);
+        output.set_confidence_level(0.5).unwrap();
+        let (narrow_lower, narrow_upper) = output.volume_fraction_ci().unwrap();
+        assert!(narrow_upper - narrow_lower < upper - lower);
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            serde_json::json!([narrow_lower, narrow_upper]),
+            json["volume_fraction_ci"]
+        );
+        assert_eq!(0.5, json["confidence_level"]);
+        assert!(matches!(
+            output.set_confidence_level(1.),
+            Err(SimError::InvalidConfidenceLevel(_))
+        ));
+    }
+
+    #[test]
+    fn precisions_agree() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 70},
+            {"name": "large", "radius": 3.0, "proportion": 30}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            target_count: 100,
+            periodic: true,
+            ..Default::default()
+        };
+        let metrics = |precision| {
+            let output = pack(
+                &spheres,
+                &PackOptions {
+                    precision,
+                    ..options.clone()
+                },
+            )
+            .unwrap();
+            let json = serde_json::to_value(&output).unwrap();
+            [
+                "volume_fraction",
+                "sa_to_vol",
+                "proportion_entropy",
+                "count_ratio",
+            ]
+            .map(|field| json[field].as_f64().unwrap())
+        };
+        let (single, double) = (metrics(Precision::F32), metrics(Precision::F64));
+        for (s, d) in single.iter().zip(double.iter()) {
+            assert!(((s - d) / d).abs() < 1e-5, "{} vs {}", s, d);
+        }
+    }
+
+    fn output(volume_fraction: f64, sphere_count: usize, count_ratio: Option<f64>) -> SimOutput {
+        SimOutput {
+            fraction: Fraction::Volume(volume_fraction),
+            unrelaxed_volume_fraction: None,
+            volume_fraction_by_type: Vec::new(),
+            accessible_fraction_by_type: Vec::new(),
+            min_counts: Vec::new(),
+            quantized_counts: None,
+            sa_to_vol: 0.5,
+            proportion_entropy: 0.,
+            sphere_count,
+            radius_skewness: None,
+            radius_kurtosis: None,
+            min_radius: None,
+            max_radius: None,
+            target_count: None,
+            count_ratio,
+            resize_iterations: None,
+            persist_attempts: None,
+            half_extents: [1., 1., 1.],
+            container_volume: 8.,
+            packed_volume: None,
+            obstacle_fraction: None,
+            periodic_volume_fraction: None,
+            wall_corrected_volume_fraction: None,
+            density_profile: None,
+            growth_curve: None,
+            rdf: None,
+            coordination: None,
+            contact_graph: None,
+            pore_stats: None,
+            seed: 0,
+            sample_seed: None,
+            placement_seed: None,
+            sampler: Sampler::PseudoRandom,
+            config_hash: String::new(),
+            throughput: None,
+            stop_reason: None,
+            stages: None,
+            spheres: Vec::new(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn aggregate_hand_computed() {
+        let results = [
+            output(0.4, 10, Some(1.)),
+            output(0.5, 20, None),
+            output(0.6, 60, Some(3.)),
+        ];
+        let aggregate = SimOutput::aggregate(&results);
+        assert_eq!(3, aggregate.count);
+        let fraction = aggregate.volume_fraction.unwrap();
+        assert!((fraction.mean - 0.5).abs() < 1e-12);
+        // sqrt(((0.1)² + 0 + (0.1)²) / 3)
+        assert!((fraction.std - (0.02f64 / 3.).sqrt()).abs() < 1e-12);
+        assert_eq!((0.4, 0.6), (fraction.min, fraction.max));
+        assert_eq!(
+            Some(Stats {
+                mean: 30.,
+                std: (1400f64 / 3.).sqrt(),
+                min: 10.,
+                max: 60.
+            }),
+            aggregate.sphere_count
+        );
+        assert_eq!(
+            Some(Stats {
+                mean: 0.5,
+                std: 0.,
+                min: 0.5,
+                max: 0.5
+            }),
+            aggregate.sa_to_vol
+        );
+        // Only the results with a count ratio are summarized.
+        assert_eq!(
+            Some(Stats {
+                mean: 2.,
+                std: 1.,
+                min: 1.,
+                max: 3.
+            }),
+            aggregate.count_ratio
+        );
+        assert_eq!(None, aggregate.periodic_volume_fraction);
+        assert_eq!(None, aggregate.wall_corrected_volume_fraction);
+        assert_eq!(None, SimOutput::aggregate(&[]).volume_fraction);
+    }
+
+    #[test]
+    fn drops_outliers() {
+        // The mean fraction is 0.5 and the standard deviation 0.2.
+        let results = || {
+            let mut results: Vec<_> = (0..4).map(|_| output(0.6, 10, None)).collect();
+            results.push(output(0.1, 1, None));
+            TrialsOutput::new(results).unwrap()
+        };
+        let mut lenient = results();
+        assert_eq!(0, lenient.drop_outliers(3.).unwrap());
+        assert_eq!(5, lenient.aggregate.count);
+        let mut strict = results();
+        assert_eq!(1, strict.drop_outliers(1.).unwrap());
+        assert_eq!(4, strict.trials().len());
+        assert_eq!(4, strict.aggregate.count);
+        assert_eq!(0.6, strict.aggregate.volume_fraction.unwrap().min);
+        let json = serde_json::to_value(&strict).unwrap();
+        assert_eq!(5, json["trials_run"]);
+        assert_eq!(1, json["dropped_trials"]);
+        assert_eq!(
+            None,
+            serde_json::to_value(results())
+                .unwrap()
+                .get("dropped_trials")
+        );
+        // None of the trials left is below their mean, so a second pass drops no more.
+        assert_eq!(0, strict.drop_outliers(0.).unwrap());
+        assert!(matches!(
+            strict.drop_outliers(-1.),
+            Err(SimError::InvalidOutlierThreshold(_))
+        ));
+    }
+
+    #[test]
+    fn container_volume_recomputes_fraction() {
+        let options = PackOptions {
+            dims: Some([20., 25., 30.]),
+            ..Default::default()
+        };
+        let output = pack(&VALID.parse().unwrap(), &options).unwrap();
+        assert_eq!(120_000., output.container_volume);
+        let filled: f64 = output
+            .spheres
+            .iter()
+            .map(|s| 4. / 3. * std::f64::consts::PI * (s.radius as f64).powi(3))
+            .sum();
+        assert!((filled / output.container_volume - output.volume_fraction()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn packs_into_mesh() {
+        // An octahedron with vertices 8 from the origin along each axis.
+        let mesh: Vec<HalfSpace> = (0..8)
+            .map(|corner| HalfSpace {
+                normal: [0, 1, 2].map(|axis| if corner >> axis & 1 == 0 { 1. } else { -1. }),
+                offset: 8.,
+            })
+            .collect();
+        let spheres = "[{\"name\": \"a\", \"radius\": 1.0, \"proportion\": 100}]"
+            .parse()
+            .unwrap();
+        let options = PackOptions {
+            mesh: mesh.clone(),
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let octahedron = polyhedron::Polyhedron::new(&mesh).unwrap();
+        assert!(output.spheres.len() > 20);
+        assert!(output.spheres.iter().all(|s| octahedron.contains(s)));
+        assert!((output.container_volume - 4. / 3. * 8f64.powi(3)).abs() < 1e-9);
+        let filled = output.spheres.len() as f64 * 4. / 3. * std::f64::consts::PI;
+        assert!((filled / output.container_volume - output.volume_fraction()).abs() < 1e-12);
+        assert_eq!([8., 8., 8.], output.half_extents);
+        let cuboid = pack(&spheres, &PackOptions::default()).unwrap();
+        assert_ne!(cuboid.config_hash, output.config_hash);
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    periodic: true,
+                    ..options
+                }
+            ),
+            Err(SimError::UnsupportedWithMesh(_))
+        ));
+    }
+
+    #[test]
+    fn explains_stop_reason() {
+        let spheres = r#"[
+            {"name": "large", "radius": 2.0, "proportion": 50},
+            {"name": "small", "radius": 1.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([6., 6., 6.]),
+            seed: 1,
+            ..Default::default()
+        };
+        let unexplained = pack(&spheres, &options).unwrap();
+        assert_eq!(None, unexplained.stop_reason);
+        let explained = pack(
+            &spheres,
+            &PackOptions {
+                explain: true,
+                ..options
+            },
+        )
+        .unwrap();
+        assert_eq!(unexplained.spheres, explained.spheres);
+        let summary = serde_json::to_value(&explained).unwrap();
+        for field in summary.as_object().unwrap().keys() {
+            assert!(FIELDS.contains(&field.as_str()), "{} isn't listed", field);
+        }
+        let stop_reason = explained.stop_reason.unwrap();
+        assert_eq!(Termination::ContainerFilled, stop_reason.reason);
+        let placements = stop_reason.placements;
+        assert_eq!(explained.sphere_count as u64 - 3, placements.placed);
+        assert!(
+            placements.attempts
+                >= placements.placed + placements.outside_container + placements.overlapping
+        );
+        let failures = Placements {
+            attempts: 4,
+            overlapping: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            Termination::PlacementFailures,
+            StopReason::from(failures).reason
+        );
+    }
+
+    #[test]
+    fn min_counts_placed_first() {
+        let spheres: Spheres = r#"[
+            {"name": "small", "radius": 1, "proportion": 99},
+            {"name": "large", "radius": 3, "proportion": 1, "min_count": 40}
+        ]"#
+        .parse()
+        .unwrap();
+        let dist =
+            WeightedRadiusDistribution::from_spheres(&spheres, ProportionBasis::Number).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let drawn: Vec<f64> = dist.sample_iter(&mut rng).take(40).collect();
+        assert!(drawn.iter().all(|&r| r == 3.));
+        let options = PackOptions {
+            dims: Some([15., 15., 15.]),
+            seed: 1,
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        assert_eq!(1, output.min_counts.len());
+        let large = &output.min_counts[0];
+        assert_eq!(("large", 40), (large.name.as_str(), large.min_count));
+        assert!(large.satisfied && large.placed >= 40);
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    dims: Some([5., 5., 5.]),
+                    ..options
+                }
+            ),
+            Err(SimError::UnsatisfiableMinimums(..))
+        ));
+    }
+
+    #[test]
+    fn feasibility() {
+        let spheres: Spheres = r#"[
+            {"name": "small", "radius": 1, "proportion": 100},
+            {"name": "unused", "radius": 10, "proportion": 0}
+        ]"#
+        .parse()
+        .unwrap();
+        assert!(spheres.feasibility(8.).is_ok());
+        assert!(matches!(
+            spheres.feasibility(4.),
+            Err(SimError::OversizedSphere(name, ..)) if name == "small"
+        ));
+        for volume in [0., -1., f64::INFINITY, f64::NAN] {
+            assert!(matches!(
+                spheres.feasibility(volume),
+                Err(SimError::InvalidContainerVolume(_))
+            ));
+        }
+        let minimums: Spheres = r#"[
+            {"name": "small", "radius": 1, "proportion": 100, "min_count": 3}
+        ]"#
+        .parse()
+        .unwrap();
+        assert!(matches!(
+            minimums.feasibility(8.),
+            Err(SimError::UnsatisfiableMinimums(..))
+        ));
+    }
+
+    #[test]
+    fn separate_seeds() {
+        let spheres = r#"[
+            {"name": "large", "radius": 2.0, "proportion": 50},
+            {"name": "small", "radius": 1.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let pack_with = |placement_seed| {
+            pack(
+                &spheres,
+                &PackOptions {
+                    dims: Some([8., 8., 8.]),
+                    sample_seed: Some(3),
+                    placement_seed: Some(placement_seed),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let (a, b) = (pack_with(1), pack_with(2));
+        assert_eq!((Some(3), Some(1)), (a.sample_seed, a.placement_seed));
+        assert_ne!(a.spheres, b.spheres);
+        assert_ne!(a.config_hash, b.config_hash);
+        // Spheres are placed in the order their radii are drawn, which only the sample seed sets.
+        let radii =
+            |output: &SimOutput| -> Vec<f32> { output.spheres.iter().map(|s| s.radius).collect() };
+        let n = a.sphere_count.min(b.sphere_count);
+        assert!(n > 10);
+        assert_eq!(radii(&a)[..n], radii(&b)[..n]);
+    }
+
+    #[test]
+    fn calls_back_in_placement_order() {
+        let spheres = VALID.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            ..Default::default()
+        };
+        let mut placed = Vec::new();
+        let output = pack_with_callback(&spheres, &options, |center, radius| {
+            placed.push((center, radius))
+        })
+        .unwrap();
+        assert_eq!(pack(&spheres, &options).unwrap().spheres, output.spheres);
+        assert_eq!(output.sphere_count, placed.len());
+        for ((center, radius), sphere) in placed.iter().zip(&output.spheres) {
+            let c = sphere.center;
+            assert_eq!((*center, *radius), ([c.x, c.y, c.z], sphere.radius));
+        }
+    }
+
+    #[test]
+    fn persists_until_packed() {
+        let spheres = BIMODAL.parse().unwrap();
+        // Seeds 35 to 37 stop after the three starting spheres.
+        let options = PackOptions {
+            dims: Some([6., 6., 6.]),
+            seed: 35,
+            ..Default::default()
+        };
+        assert_eq!(3, pack(&spheres, &options).unwrap().sphere_count);
+        let output = pack_persistently(&spheres, &options, Duration::from_secs(60)).unwrap();
+        assert_eq!(Some(4), output.persist_attempts);
+        assert_eq!(38, output.seed);
+        assert!(output.sphere_count > 3);
+
+        // The large spheres fill less than this container but are wider than it.
+        let too_small = PackOptions {
+            dims: Some([1.7, 1.7, 1.7]),
+            ..options.clone()
+        };
+        assert!(matches!(
+            pack_persistently(&spheres, &too_small, Duration::from_millis(10)),
+            Err(SimError::PersistTimedOut(attempts, _)) if attempts >= 1
+        ));
+        let infeasible = PackOptions {
+            dims: Some([1., 1., 1.]),
+            ..options
+        };
+        assert!(matches!(
+            pack_persistently(&spheres, &infeasible, Duration::from_millis(10)),
+            Err(SimError::OversizedSphere(name, ..)) if name == "large"
+        ));
+    }
+
+    #[test]
+    fn packs_in_two_stages() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 60},
+            {"name": "large", "radius": 3.0, "proportion": 40}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([9., 9., 9.]),
+            seed: 3,
+            ..Default::default()
+        };
+        let output = pack_two_stage(&spheres, &options, 2.).unwrap();
+        let stages = output.stages.unwrap();
+        assert!(stages.large_fraction > 0. && stages.small_fraction > 0.);
+        let by_type = &output.volume_fraction_by_type;
+        assert_eq!(
+            ("small", "large"),
+            (by_type[0].0.as_str(), by_type[1].0.as_str())
+        );
+        assert!((by_type[1].1 - stages.large_fraction).abs() < 1e-12);
+        assert!((by_type[0].1 - stages.small_fraction).abs() < 1e-12);
+        assert_eq!(2, output.accessible_fraction_by_type.len());
+        for (i, a) in output.spheres.iter().enumerate() {
+            for b in &output.spheres[i + 1..] {
+                let distance = nalgebra::distance(&a.center, &b.center);
+                assert!(distance > (a.radius + b.radius) * (1. - 1e-5));
+            }
+        }
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(2., json["stages"]["radius_threshold"]);
+        assert!(matches!(
+            pack_two_stage(&spheres, &options, 5.),
+            Err(SimError::InvalidSplit(_))
+        ));
+    }
+
+    #[test]
+    fn sorted_output() {
+        let spheres = r#"[
+            {"name": "large", "radius": 2.0, "proportion": 50},
+            {"name": "small", "radius": 1.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([6., 6., 6.]),
+            ..Default::default()
+        };
+        let unsorted = pack(&spheres, &options).unwrap();
+        let sorted = pack(
+            &spheres,
+            &PackOptions {
+                sort_output: true,
+                ..options
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["small", "large"],
+            sorted
+                .volume_fraction_by_type
+                .iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        );
+        assert!(sorted
+            .spheres
+            .windows(2)
+            .all(|pair| pair[0].radius <= pair[1].radius));
+        assert_ne!(unsorted.spheres, sorted.spheres);
+        assert_eq!(unsorted.spheres.len(), sorted.spheres.len());
+        assert!(unsorted.spheres.iter().all(|s| sorted.spheres.contains(s)));
+    }
+
+    #[test]
+    fn halton_sampler_reproducible() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 50},
+            {"name": "large", "radius": 2.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([8., 8., 8.]),
+            sampler: Sampler::Halton,
+            ..Default::default()
+        };
+        let halton = pack(&spheres, &options).unwrap();
+        assert_eq!(halton.spheres, pack(&spheres, &options).unwrap().spheres);
+        let reseeded = PackOptions {
+            seed: 1,
+            ..options.clone()
+        };
+        assert_ne!(halton.spheres, pack(&spheres, &reseeded).unwrap().spheres);
+        let pseudo = PackOptions {
+            sampler: Sampler::PseudoRandom,
+            ..options
+        };
+        let pseudo = pack(&spheres, &pseudo).unwrap();
+        assert_ne!(halton.config_hash, pseudo.config_hash);
+        let json = serde_json::to_value(&halton).unwrap();
+        assert_eq!("halton", json["sampler"]);
+        assert_eq!(
+            "pseudo-random",
+            serde_json::to_value(&pseudo).unwrap()["sampler"]
+        );
+    }
+
+    #[test]
+    fn packs_disks_in_2d() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 50},
+            {"name": "large", "radius": 2.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            target_count: 200,
+            seed: 4,
+            dimensionality: Dimensionality::Two,
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let [x, y, z] = output.half_extents;
+        assert_eq!((x, 0.), (y, z));
+        assert!(output.spheres.iter().all(|s| s.center.z == 0.));
+        let json = serde_json::to_value(&output).unwrap();
+        assert!(json.get("volume_fraction").is_none());
+        let area = json["area_fraction"].as_f64().unwrap();
+        assert!(area > 0.5 && area < 0.91, "{}", area);
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    relax: 1,
+                    ..options
+                }
+            ),
+            Err(SimError::UnsupportedIn2d(_))
+        ));
+    }
+
+    #[test]
+    fn volume_fractions_by_type() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 90},
+            {"name": "large", "radius": 3.0, "proportion": 10}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([10., 10., 10.]),
+            seed: 2,
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let by_type = &output.volume_fraction_by_type;
+        assert_eq!(
+            vec!["small", "large"],
+            by_type.iter().map(|(name, _)| name).collect::<Vec<_>>()
+        );
+        let total: f64 = by_type.iter().map(|(_, fraction)| fraction).sum();
+        assert!((total - output.volume_fraction()).abs() < 1e-12);
+        // Few in number, but each large sphere has 27 times the volume of a small one.
+        assert!(by_type[1].1 > by_type[0].1, "{:?}", by_type);
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            serde_json::json!(["large", by_type[1].1]),
+            json["volume_fraction_by_type"][1]
+        );
+    }
+
+    #[test]
+    fn unplaced_types_fill_nothing() {
+        let spheres = r#"[
+            {"name": "placed", "radius": 1.0, "proportion": 100},
+            {"name": "unplaced", "radius": 2.0, "proportion": 0}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([4., 4., 4.]),
+            ..Default::default()
+        };
+        let two_dimensional = PackOptions {
+            dims: Some([4., 4., 0.]),
+            dimensionality: Dimensionality::Two,
+            ..options.clone()
+        };
+        let single = PackOptions {
+            precision: Precision::F32,
+            ..options.clone()
+        };
+        for options in [options, two_dimensional, single] {
+            let output = pack(&spheres, &options).unwrap();
+            let (_, unplaced) = output.volume_fraction_by_type[1];
+            assert!(unplaced == 0. && unplaced.is_sign_positive());
+            let json = serde_json::to_string(&output).unwrap();
+            assert!(json.contains(r#"["unplaced",0.0]"#), "{}", json);
+        }
+    }
+
+    #[test]
+    fn accessible_fractions_by_type() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([4., 5., 10.]),
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let expected = [
+            ("small", 3. / 4. * 4. / 5. * 9. / 10.),
+            ("large", 2. / 4. * 3. / 5. * 8. / 10.),
+        ];
+        for ((name, fraction), (expected_name, expected)) in
+            output.accessible_fraction_by_type.iter().zip(expected)
+        {
+            assert_eq!(expected_name, name);
+            assert!((fraction - expected).abs() < 1e-12, "{}", fraction);
+        }
+        let flat = PackOptions {
+            dimensionality: Dimensionality::Two,
+            dims: Some([4., 5., 0.]),
+            ..Default::default()
+        };
+        let output = pack(&spheres, &flat).unwrap();
+        assert!((output.accessible_fraction_by_type[1].1 - 2. / 4. * 3. / 5.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn metrics_serialized_at_their_precision() {
+        let write = |value: f64| {
+            let mut written = Vec::new();
+            serialize_metric(&value, &mut serde_json::Serializer::new(&mut written)).unwrap();
+            String::from_utf8(written).unwrap()
+        };
+        assert_eq!("0.1", write(Precision::F32.round(0.1)));
+        assert_eq!("0.1", write(0.1));
+        assert_eq!("0.30000000000000004", write(0.1 + 0.2));
+    }
+
+    #[test]
+    fn benchmark_matches_pack() {
+        let spheres = VALID.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            seed: 42,
+            ..Default::default()
+        };
+        let timed = benchmark(&spheres, &options, true).unwrap();
+        let throughput = timed.throughput.unwrap();
+        assert!(throughput.elapsed_ms > 0. && throughput.spheres_per_second > 0.);
+        assert_eq!(pack(&spheres, &options).unwrap().spheres, timed.spheres);
+        let json = serde_json::to_value(&timed).unwrap();
+        assert!(json["elapsed_ms"].is_number() && json["spheres_per_second"].is_number());
+    }
+
+    #[test]
+    fn reports_coordination() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            target_count: 300,
+            ..Default::default()
+        };
+        let plain = pack(&spheres, &options).unwrap();
+        assert_eq!(None, plain.coordination);
+        let counted = pack(
+            &spheres,
+            &PackOptions {
+                coordination: Some(1e-4),
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(plain.spheres, counted.spheres);
+        assert_eq!(plain.config_hash, counted.config_hash);
+        let coordination = counted.coordination.unwrap();
+        assert_eq!(
+            counted.sphere_count,
+            coordination.distribution.iter().sum::<usize>()
+        );
+        // Every sphere placed by the advancing front touches the spheres it was placed against.
+        assert!(coordination.mean >= 2. && coordination.mean < 12.);
+        for contact in [-1., f64::NAN] {
+            assert!(matches!(
+                pack(
+                    &spheres,
+                    &PackOptions {
+                        coordination: Some(contact),
+                        ..options.clone()
+                    }
+                ),
+                Err(SimError::InvalidContactEpsilon(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn min_fraction_gate() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([6., 6., 6.]),
+            seed: 2,
+            ..Default::default()
+        };
+        let fraction = pack(&spheres, &options).unwrap().volume_fraction();
+        let gated = |threshold| PackOptions {
+            min_fraction: Some(threshold),
+            ..options.clone()
+        };
+        assert_eq!(
+            fraction,
+            pack(&spheres, &gated(fraction)).unwrap().volume_fraction()
+        );
+        assert!(matches!(
+            pack(&spheres, &gated(fraction + 0.01)),
+            Err(SimError::FractionTooLow { got, threshold })
+                if got == fraction && threshold == fraction + 0.01
+        ));
+        assert!(matches!(
+            pack_to_count(
+                &spheres,
+                &PackOptions {
+                    dims: None,
+                    target_count: 100,
+                    ..gated(1.)
+                },
+                0.5
+            ),
+            Err(SimError::FractionTooLow { .. })
+        ));
+        for invalid in [-0.1, 1.5, f64::NAN] {
+            assert!(matches!(
+                pack(&spheres, &gated(invalid)),
+                Err(SimError::InvalidMinFraction(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn packs_radius_distribution() {
+        let distribution = parsing::RadiusDistribution::Uniform { min: 1., max: 2. };
+        let spheres = distribution.buckets();
+        let options = PackOptions {
+            dims: Some([8., 8., 8.]),
+            distribution: Some(distribution),
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        assert!(output.sphere_count > 10);
+        assert!(output
+            .spheres
+            .iter()
+            .all(|s| (1. ..=2.).contains(&s.radius)));
+        let mut radii: Vec<f32> = output.spheres.iter().map(|s| s.radius).collect();
+        radii.sort_by(f32::total_cmp);
+        radii.dedup();
+        // Radii are drawn continuously, not just the bucket radii.
+        assert!(radii.len() > parsing::DISTRIBUTION_BUCKETS);
+        assert_eq!(
+            parsing::DISTRIBUTION_BUCKETS,
+            output.volume_fraction_by_type.len()
+        );
+        let discrete = pack(
+            &spheres,
+            &PackOptions {
+                distribution: None,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_ne!(discrete.config_hash, output.config_hash);
+        for invalid in [
+            PackOptions {
+                basis: ProportionBasis::Volume,
+                ..options.clone()
+            },
+            PackOptions {
+                distribution: Some(parsing::RadiusDistribution::Uniform { min: 2., max: 1. }),
+                ..options.clone()
+            },
+        ] {
+            assert!(matches!(
+                pack(&spheres, &invalid),
+                Err(SimError::UnsupportedWithDistribution(_) | SimError::InvalidDistribution(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn quantizes_drawn_radii() {
+        let distribution = parsing::RadiusDistribution::Lognormal {
+            mean: 1.5,
+            sigma: 0.3,
+        };
+        let spheres = distribution.buckets();
+        let options = PackOptions {
+            dims: Some([8., 8., 8.]),
+            distribution: Some(distribution),
+            quantize: Some(vec![1., 1.5, 2.5]),
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        assert!(output
+            .spheres
+            .iter()
+            .all(|s| [1., 1.5, 2.5].contains(&s.radius)));
+        let counts = output.quantized_counts.as_ref().unwrap();
+        assert_eq!(
+            vec![1., 1.5, 2.5],
+            counts.iter().map(|(radius, _)| *radius).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            output.sphere_count,
+            counts.iter().map(|(_, count)| count).sum::<usize>()
+        );
+        assert!(counts.iter().all(|(_, count)| *count > 0));
+        let unquantized = pack(
+            &spheres,
+            &PackOptions {
+                quantize: None,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(None, unquantized.quantized_counts);
+        assert_ne!(unquantized.config_hash, output.config_hash);
+
+        for radii in [vec![], vec![1., 0.], vec![f64::NAN]] {
+            assert!(matches!(
+                pack(
+                    &spheres,
+                    &PackOptions {
+                        quantize: Some(radii),
+                        ..options.clone()
+                    }
+                ),
+                Err(SimError::InvalidQuantization(_))
+            ));
+        }
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    distribution: None,
+                    ..options.clone()
+                }
+            ),
+            Err(SimError::QuantizeWithoutDistribution)
+        ));
+    }
+
+    #[test]
+    fn reports_contact_graph() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            target_count: 300,
+            coordination: Some(1e-4),
+            contact_graph: Some(1e-4),
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let edges = output.contact_graph.clone().unwrap();
+        let mut degrees = vec![0; output.sphere_count];
+        for &[i, j] in &edges {
+            assert!(i < j);
+            degrees[i] += 1;
+            degrees[j] += 1;
+        }
+        let mut distribution = vec![0; degrees.iter().max().unwrap() + 1];
+        for degree in degrees {
+            distribution[degree] += 1;
+        }
+        assert_eq!(
+            output.coordination.as_ref().unwrap().distribution,
+            distribution
+        );
+
+        // Sorting the spheres keeps each edge between the same two spheres.
+        let sorted = pack(
+            &spheres,
+            &PackOptions {
+                sort_output: true,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        let sorted_edges = sorted.contact_graph.as_ref().unwrap();
+        assert_eq!(edges.len(), sorted_edges.len());
+        assert!(sorted_edges.windows(2).all(|pair| pair[0] < pair[1]));
+        let centers = |output: &SimOutput, edge: &[usize; 2]| {
+            let mut ends = edge.map(|i| {
+                let c = &output.spheres[i].center;
+                [c.x, c.y, c.z]
+            });
+            ends.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            ends
+        };
+        let mut touching: Vec<_> = edges.iter().map(|e| centers(&output, e)).collect();
+        let mut sorted_touching: Vec<_> =
+            sorted_edges.iter().map(|e| centers(&sorted, e)).collect();
+        touching.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        sorted_touching.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(touching, sorted_touching);
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    contact_graph: Some(-1.),
+                    ..options
+                }
+            ),
+            Err(SimError::InvalidContactEpsilon(_))
+        ));
+    }
+
+    #[test]
+    fn packs_total_volume() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            total_volume: Some(2000.),
+            explain: true,
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let packed = output.packed_volume.unwrap();
+        assert!((2000.0..2000. + sphere_volume(2.)).contains(&packed));
+        assert!((output.volume_fraction() - packed / output.container_volume).abs() < 1e-9);
+        assert_eq!(None, output.target_count);
+        assert_eq!(None, output.count_ratio);
+        assert_eq!(
+            Termination::VolumeReached,
+            output.stop_reason.unwrap().reason
+        );
+        let unlimited = pack(
+            &spheres,
+            &PackOptions {
+                dims: Some(output.half_extents),
+                total_volume: None,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert!(unlimited.sphere_count > output.sphere_count);
+        assert_eq!(None, unlimited.packed_volume);
+        for volume in [0., f64::INFINITY] {
+            assert!(matches!(
+                pack(
+                    &spheres,
+                    &PackOptions {
+                        total_volume: Some(volume),
+                        ..options.clone()
+                    }
+                ),
+                Err(SimError::InvalidTotalVolume(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn packed_radius_range() {
+        let monodisperse = r#"[{"name": "only", "radius": 1.5, "proportion": 100}]"#
+            .parse()
+            .unwrap();
+        let options = PackOptions {
+            dims: Some([8., 8., 8.]),
+            seed: 2,
+            ..Default::default()
+        };
+        let output = pack(&monodisperse, &options).unwrap();
+        assert_eq!(Some(1.5), output.min_radius);
+        assert_eq!(Some(1.5), output.max_radius);
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(1.5, json["min_radius"]);
+        assert_eq!(1.5, json["max_radius"]);
+
+        let bimodal = BIMODAL.parse().unwrap();
+        let output = pack(&bimodal, &options).unwrap();
+        assert_eq!(Some(1.), output.min_radius);
+        assert_eq!(Some(2.), output.max_radius);
+    }
+
+    #[test]
+    fn tracks_growth() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([8., 8., 8.]),
+            seed: 1,
+            track_growth: Some(10),
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let curve = output.growth_curve.as_ref().unwrap();
+        let counts: Vec<usize> = curve.iter().map(|(count, _)| *count).collect();
+        let mut expected: Vec<usize> = (10..=output.sphere_count).step_by(10).collect();
+        if !output.sphere_count.is_multiple_of(10) {
+            expected.push(output.sphere_count);
+        }
+        assert_eq!(expected, counts);
+        assert!(curve.windows(2).all(|pair| pair[0].1 < pair[1].1));
+        let (_, last) = curve.last().unwrap();
+        assert!((last - output.volume_fraction()).abs() < 1e-12);
+        let untracked = pack(
+            &spheres,
+            &PackOptions {
+                track_growth: None,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(None, untracked.growth_curve);
+        assert_eq!(output.spheres(), untracked.spheres());
+
+        let disks = pack(
+            &spheres,
+            &PackOptions {
+                dims: Some([12., 12., 0.]),
+                dimensionality: Dimensionality::Two,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        let (_, last) = disks.growth_curve.as_ref().unwrap().last().unwrap();
+        assert!((last - disks.volume_fraction()).abs() < 1e-12);
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    track_growth: Some(0),
+                    ..options
+                }
+            ),
+            Err(SimError::NoGrowthInterval)
+        ));
+    }
+
+    #[test]
+    fn settles_under_gravity() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            target_count: 300,
+            gravity: true,
+            ..Default::default()
+        };
+        let settled = pack(&spheres, &options).unwrap();
+        assert_eq!(settled.spheres, pack(&spheres, &options).unwrap().spheres);
+        let even = pack(
+            &spheres,
+            &PackOptions {
+                gravity: false,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(None, even.density_profile);
+        assert_ne!(even.config_hash, settled.config_hash);
+        let profiled = pack(
+            &spheres,
+            &PackOptions {
+                gravity: false,
+                density_profile: Some(3),
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(even.spheres, profiled.spheres);
+        assert_eq!(even.config_hash, profiled.config_hash);
+        let profile = profiled.density_profile.as_ref().unwrap();
+        assert_eq!(3, profile.len());
+        assert!((profile.iter().sum::<f64>() / 3. - even.volume_fraction()).abs() < 1e-9);
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    density_profile: Some(0),
+                    ..options.clone()
+                }
+            ),
+            Err(SimError::NoSlices)
+        ));
+        // The slabs have equal volumes, so their mean fraction is the container's.
+        let profile = settled.density_profile.as_ref().unwrap();
+        assert_eq!(10, profile.len());
+        let mean = profile.iter().sum::<f64>() / profile.len() as f64;
+        assert!((mean - settled.volume_fraction()).abs() < 1e-9);
+        assert!(matches!(
+            pack(
+                &spheres,
+                &PackOptions {
+                    dimensionality: Dimensionality::Two,
+                    ..options
+                }
+            ),
+            Err(SimError::UnsupportedIn2d("gravity"))
+        ));
+    }
+
+    #[test]
+    fn displays_summary() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            target_count: 100,
+            explain: true,
+            ..Default::default()
+        };
+        let output = pack(&spheres, &options).unwrap();
+        let summary = output.to_string();
+        let lines: Vec<&str> = summary.lines().collect();
+        // Values are aligned in one column after the longest label.
+        let column = lines[0].len() - 6;
+        assert!(lines
+            .iter()
+            .all(|l| l[..column].ends_with(' ') && l.as_bytes()[column] != b' '));
+        assert_eq!(
+            format!("{:.4}", output.volume_fraction()),
+            lines[0].strip_prefix("volume fraction:").unwrap().trim()
+        );
+        assert!(lines[1].starts_with("  small:"));
+        for label in [
+            "spheres packed:",
+            "container volume:",
+            "stopped:",
+            "config hash:",
+        ] {
+            assert!(lines.iter().any(|l| l.starts_with(label)), "{}", label);
+        }
+        assert!(summary.contains(&format!("{} (target 100", output.sphere_count)));
+        assert!(!summary.ends_with('\n'));
+    }
+
+    #[test]
+    fn resizes_to_count() {
+        let spheres = BIMODAL.parse().unwrap();
+        let options = PackOptions {
+            target_count: 300,
+            ..Default::default()
+        };
+        let unresized = pack(&spheres, &options).unwrap();
+        let loose = pack_to_count(&spheres, &options, 10.).unwrap();
+        assert_eq!(Some(0), loose.resize_iterations);
+        assert_eq!(unresized.spheres, loose.spheres);
+        let tight = pack_to_count(&spheres, &options, 0.05).unwrap();
+        let deviation = |output: &SimOutput| (output.count_ratio.unwrap() - 1.).abs();
+        assert!(deviation(&tight) <= deviation(&unresized));
+        assert!(deviation(&tight) <= 0.05 || tight.resize_iterations == Some(10));
+        assert!(matches!(
+            pack_to_count(&spheres, &options, -1.),
+            Err(SimError::InvalidCountTolerance(_))
+        ));
+        let cuboid = PackOptions {
+            dims: Some([8., 8., 8.]),
+            ..options
+        };
+        assert!(matches!(
+            pack_to_count(&spheres, &cuboid, 0.05),
+            Err(SimError::ToleranceWithoutTarget)
+        ));
+    }
+
+    #[test]
+    fn relaxation_packs_more() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 50},
+            {"name": "large", "radius": 2.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            dims: Some([8., 8., 8.]),
+            seed: 1,
+            ..Default::default()
+        };
+        let plain = pack(&spheres, &options).unwrap();
+        let relaxing = PackOptions {
+            relax: 50,
+            ..options
+        };
+        let relaxed = pack(&spheres, &relaxing).unwrap();
+        assert_eq!(
+            Some(plain.volume_fraction()),
+            relaxed.unrelaxed_volume_fraction
+        );
+        assert!(relaxed.volume_fraction() > plain.volume_fraction());
+        for (i, a) in relaxed.spheres.iter().enumerate() {
+            for b in &relaxed.spheres[i + 1..] {
+                let distance = nalgebra::distance(&a.center, &b.center);
+                assert!(distance > (a.radius + b.radius) * (1. - 1e-5));
+            }
+        }
+        assert!(relaxed.snapshots().is_empty());
+
+        let snapshotted = pack(
+            &spheres,
+            &PackOptions {
+                snapshot_every: Some(20),
+                ..relaxing.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(relaxed.spheres, snapshotted.spheres);
+        let passes: Vec<usize> = snapshotted.snapshots().iter().map(|(p, _)| *p).collect();
+        assert_eq!(vec![20, 40], passes);
+        let never = PackOptions {
+            snapshot_every: Some(0),
+            ..relaxing
+        };
+        assert!(matches!(
+            pack(&spheres, &never),
+            Err(SimError::NoSnapshotInterval)
+        ));
+    }
+
+    #[test]
+    fn warm_start_keeps_initial() {
+        let spheres = VALID.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            seed: 42,
+            ..Default::default()
+        };
+        let first = pack(&spheres, &options).unwrap();
+        let kept = first.spheres().len() / 2;
+        let initial: Vec<PlacedSphere> = first.spheres()[..kept]
+            .iter()
+            .map(|s| PlacedSphere {
+                x: s.center.x,
+                y: s.center.y,
+                z: s.center.z,
+                radius: s.radius,
+            })
+            .collect();
+        let warm = pack(&spheres, &PackOptions { initial, ..options }).unwrap();
+        assert_eq!(first.spheres()[..kept], warm.spheres()[..kept]);
+        assert!(kept > 3 && warm.spheres().len() > kept);
+    }
+
+    #[test]
+    fn degenerate_container_is_an_error() {
+        let spheres = VALID.parse().unwrap();
+        let options = PackOptions {
+            target_count: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            pack(&spheres, &options),
+            Err(SimError::InvalidContainer([side, _, _])) if side == 0.
+        ));
+        let options = PackOptions {
+            dims: Some([1., f32::NAN, 1.]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            pack(&spheres, &options),
+            Err(SimError::InvalidContainer(_))
+        ));
+    }
+
+    #[test]
+    fn warm_start_rejects_invalid() {
+        let spheres = VALID.parse().unwrap();
+        let placed = |x| PlacedSphere {
+            x,
+            y: 0.,
+            z: 0.,
+            radius: 5.,
+        };
+        let with_initial = |initial| PackOptions {
+            dims: Some([20., 20., 20.]),
+            initial,
+            ..Default::default()
+        };
+        assert!(matches!(
+            pack(&spheres, &with_initial(vec![placed(0.), placed(18.)])),
+            Err(SimError::InitialUncontained(1))
+        ));
+        assert!(matches!(
+            pack(
+                &spheres,
+                &with_initial(vec![placed(-10.), placed(0.), placed(9.)])
+            ),
+            Err(SimError::InitialOverlap(1, 2))
+        ));
+    }
+
+    #[test]
+    fn reports_rdf() {
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            rdf: Some(RdfBins {
+                bins: 8,
+                r_max: 10.,
+            }),
+            ..Default::default()
+        };
+        let output = pack(&VALID.parse().unwrap(), &options).unwrap();
+        assert_eq!(8, output.rdf.unwrap().r.len());
+        for (bins, r_max) in [
+            (0, 20.),
+            (8, 0.),
+            (8, -1.),
+            (8, f64::NAN),
+            (8, f64::INFINITY),
+        ] {
+            assert!(matches!(
+                pack(
+                    &VALID.parse().unwrap(),
+                    &PackOptions {
+                        rdf: Some(RdfBins { bins, r_max }),
+                        ..options.clone()
+                    }
+                ),
+                Err(SimError::InvalidRdf(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn packs_around_obstacles() {
+        let spheres = VALID.parse().unwrap();
+        let placed = |x, radius| PlacedSphere {
+            x,
+            y: 0.,
+            z: 0.,
+            radius,
+        };
+        let with_obstacles = |obstacles| PackOptions {
+            dims: Some([20., 20., 20.]),
+            obstacles,
+            ..Default::default()
+        };
+        // The spheres packing starts from don't fit at the origin, inside the first obstacle.
+        let obstacles = vec![placed(0., 8.), placed(14., 4.)];
+        let output = pack(&spheres, &with_obstacles(obstacles.clone())).unwrap();
+        assert!(output.spheres().len() > 10);
+        let obstacle_volume = sphere_volume(8.) + sphere_volume(4.);
+        for sphere in output.spheres() {
+            for obstacle in &obstacles {
+                let center = nalgebra::Point3::new(obstacle.x, obstacle.y, obstacle.z);
+                assert!(
+                    nalgebra::distance(&sphere.center, &center)
+                        >= (sphere.radius + obstacle.radius) * (1. - 1e-5)
+                );
+            }
+        }
+        let obstacle_fraction = obstacle_volume / 40f64.powi(3);
+        assert!((output.obstacle_fraction.unwrap() - obstacle_fraction).abs() < 1e-12);
+        let packed_fraction = packed_volume(output.spheres()) / 40f64.powi(3);
+        assert!((output.volume_fraction() - (packed_fraction + obstacle_fraction)).abs() < 1e-12);
+        let by_type: f64 = output.volume_fraction_by_type.iter().map(|(_, f)| f).sum();
+        assert!((by_type - packed_fraction).abs() < 1e-12);
+
+        assert!(matches!(
+            pack(
+                &spheres,
+                &with_obstacles(vec![placed(0., 5.), placed(18., 5.)])
+            ),
+            Err(SimError::ObstacleUncontained(1))
+        ));
+        assert!(matches!(
+            pack(
+                &spheres,
+                &with_obstacles(vec![placed(0., 5.), placed(9., 5.)])
+            ),
+            Err(SimError::ObstacleOverlap(0, 1))
+        ));
+        let warm = PackOptions {
+            initial: vec![placed(-10., 1.), placed(9., 1.)],
+            ..with_obstacles(vec![placed(0., 8.5)])
+        };
+        assert!(matches!(
+            pack(&spheres, &warm),
+            Err(SimError::InitialOverlapsObstacle(1, 0))
+        ));
+        let full = PackOptions {
+            dims: Some([6., 6., 6.]),
+            ..with_obstacles(vec![placed(0., 5.9)])
+        };
+        assert!(matches!(
+            pack(&spheres, &full),
+            Err(SimError::NoRoomAmongObstacles)
+        ));
+        let relaxed = PackOptions {
+            relax: 1,
+            ..with_obstacles(vec![placed(0., 5.)])
+        };
+        assert!(matches!(
+            pack(&spheres, &relaxed),
+            Err(SimError::UnsupportedWithObstacles("relaxation"))
+        ));
+    }
+
+    #[test]
+    fn results_reported_as_completed() {
+        let spheres = VALID.parse().unwrap();
+        let options = PackOptions {
+            dims: Some([20., 20., 20.]),
+            ..Default::default()
+        };
+        let mut seeds = Vec::new();
+        let output = pack_trials_with(&spheres, &options, 6, None, 3, |result| {
+            seeds.push(result.seed);
+            Ok(())
+        })
+        .unwrap();
+        seeds.sort_unstable();
+        assert_eq!(vec![0, 1, 2, 3, 4, 5], seeds);
+        assert_eq!(6, output.trials().len());
+
+        let failing = pack_trials_with(&spheres, &options, 6, None, 1, |_| {
+            Err(io::Error::other("disk full"))
+        });
+        assert!(matches!(failing, Err(SimError::FailedToReport(_))));
+    }
+
+    #[test]
+    fn counts_by_basis() {
+        let spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 50},
+            {"name": "large", "radius": 2.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let small_per_large = |basis| {
+            let dist = WeightedRadiusDistribution::from_spheres(&spheres, basis).unwrap();
+            let draws = dist.sample_iter(ChaCha8Rng::seed_from_u64(0)).take(90_000);
+            let small = draws.filter(|&r| r == 1.0).count();
+            small as f64 / (90_000 - small) as f64
+        };
+        assert!((small_per_large(ProportionBasis::Number) - 1.).abs() < 0.05);
+        assert!((small_per_large(ProportionBasis::SurfaceArea) - 4.).abs() < 0.2);
+        assert!((small_per_large(ProportionBasis::Volume) - 8.).abs() < 0.4);
+    }
+
+    #[test]
+    fn counts_by_mass() {
+        let spheres: Spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 50, "density": 2.0},
+            {"name": "large", "radius": 2.0, "proportion": 50, "density": 1.0}
+        ]"#
+        .parse()
+        .unwrap();
+        let dist =
+            WeightedRadiusDistribution::from_spheres(&spheres, ProportionBasis::Mass).unwrap();
+        let draws = dist.sample_iter(ChaCha8Rng::seed_from_u64(0)).take(90_000);
+        let small = draws.filter(|&r| r == 1.0).count();
+        // Half the mass at twice the density is a quarter of the volume of the large spheres, in
+        // spheres an eighth the size.
+        assert!((small as f64 / (90_000 - small) as f64 - 4.).abs() < 0.2);
+
+        let without_density = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 50, "density": 2.0},
+            {"name": "large", "radius": 2.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let options = PackOptions {
+            basis: ProportionBasis::Mass,
+            ..Default::default()
+        };
+        assert!(matches!(
+            pack(&without_density, &options),
+            Err(SimError::MissingDensity(name)) if name == "large"
+        ));
+    }
+
+    #[test]
+    fn estimate_monodisperse() {
+        let spheres = VALID.parse().unwrap();
+        let huge = estimate_volume_fraction(&spheres, ProportionBasis::Number, 1e15);
+        assert!((huge - 0.634).abs() < 1e-3);
+        let small = estimate_volume_fraction(&spheres, ProportionBasis::Number, 1e5);
+        assert!(small < huge);
+    }
+
+    #[test]
+    fn estimate_polydisperse_denser() {
+        let bidisperse = r#"[
+            {"name": "small", "radius": 4.0, "proportion": 50},
+            {"name": "large", "radius": 6.0, "proportion": 50}
+        ]"#
+        .parse()
+        .unwrap();
+        let mono = estimate_volume_fraction(&VALID.parse().unwrap(), ProportionBasis::Number, 1e15);
+        assert!(estimate_volume_fraction(&bidisperse, ProportionBasis::Number, 1e15) > mono);
+    }
+
+    fn config_hash(json: &str) -> String {
+        RunConfig {
+            spheres: &json.parse::<Spheres>().unwrap(),
+            target_count: 1000.,
+            fill_factor: 2.,
+            total_volume: None,
+            shape: "cube",
+            dims: None,
+            seed: 0,
+            sample_seed: None,
+            placement_seed: None,
+            basis: ProportionBasis::Number,
+            initial: &[],
+            obstacles: &[],
+            mesh: &[],
+            distribution: None,
+            quantize: None,
+            relax: 0,
+            dimensionality: Dimensionality::Three,
+            sampler: Sampler::PseudoRandom,
+            gravity: false,
+        }
+        .hash()
+    }
+
+    #[test]
+    fn config_hash_stable() {
+        assert_eq!(config_hash(VALID), config_hash(VALID));
+        assert_eq!(
+            "fc5742b42abec4bd66e270731263d2d58a3efdc872e65a855cad66d8f43fa9d7",
+            config_hash(VALID)
+        );
+    }
+
+    #[test]
+    fn config_hash_differs() {
+        let other = r#"[{"name": "5_micron_Al", "radius": 6.0, "proportion": 100}]"#;
+        assert_ne!(config_hash(VALID), config_hash(other));
+    }
+}
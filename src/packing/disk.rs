@@ -0,0 +1,197 @@
+//! Advancing front packing of disks into a rectangle, for two dimensional packings.
+//!
+//! This follows the same scheme as the sphere packer in `front`: starting from two tangent disks,
+//! a disk on the front is chosen at random and a new disk is placed touching both it and one of
+//! its neighbours, with the chosen disk leaving the front once no new disk fits against it.
+use nalgebra::{Point2, Point3, Vector2};
+use rand::distributions::Distribution;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use spherical_cow::errors::SphericalCowError;
+use spherical_cow::shapes::Sphere;
+
+use crate::packing::Placements;
+
+/// Relative amount two disks may overlap by and still be treated as touching, so that disks
+/// placed tangent to each other aren't rejected because of rounding.
+const TOLERANCE: f32 = 1e-5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Disk {
+    center: Point2<f32>,
+    radius: f32,
+}
+
+impl Disk {
+    fn overlaps(&self, other: &Disk) -> bool {
+        nalgebra::distance(&self.center, &other.center)
+            < (self.radius + other.radius) * (1. - TOLERANCE)
+    }
+
+    /// Whether this disk is inside the origin-centered rectangle with `half_extents`.
+    fn inside(&self, half_extents: [f32; 2]) -> bool {
+        self.center.x.abs() + self.radius <= half_extents[0]
+            && self.center.y.abs() + self.radius <= half_extents[1]
+    }
+}
+
+/// Pack disks with radii drawn from `sizes` into the origin-centered rectangle with
+/// `half_extents` as densely as possible, using `rng` for every random choice.
+///
+/// Disks are provided as spheres centered in the plane `z = 0`, so that they can be analysed and
+/// written out like a three dimensional packing. Every position tried for a new disk is counted in
+/// `placements`.
+pub(crate) fn pack_disks<D, R>(
+    half_extents: [f32; 2],
+    sizes: &mut D,
+    rng: &mut R,
+    placements: &mut Placements,
+) -> Result<Vec<Sphere>, SphericalCowError>
+where
+    D: Distribution<f64>,
+    R: Rng,
+{
+    let (radius_a, radius_b) = (sizes.sample(rng) as f32, sizes.sample(rng) as f32);
+    let offset = (radius_a + radius_b) / 2.;
+    let mut disks = vec![
+        Disk {
+            center: Point2::new(-offset, 0.),
+            radius: radius_a,
+        },
+        Disk {
+            center: Point2::new(offset, 0.),
+            radius: radius_b,
+        },
+    ];
+    if !disks.iter().all(|d| d.inside(half_extents)) {
+        return Err(SphericalCowError::Uncontained);
+    }
+    let mut front = disks.clone();
+    let mut new_radius = sizes.sample(rng) as f32;
+
+    let mut candidates = Vec::new();
+    'outer: while !front.is_empty() {
+        let curr_disk = *front.choose(rng).ok_or(SphericalCowError::NoneFront)?;
+        // Disks close enough to `curr_disk` that the new disk could touch both.
+        let set_v: Vec<Disk> = disks
+            .iter()
+            .filter(|d| {
+                **d != curr_disk
+                    && nalgebra::distance(&curr_disk.center, &d.center)
+                        <= curr_disk.radius + d.radius + 2. * new_radius
+            })
+            .copied()
+            .collect();
+
+        for neighbour in &set_v {
+            candidates.clear();
+            candidates.extend(
+                tangent_disks(&curr_disk, neighbour, new_radius)
+                    .iter()
+                    .flatten()
+                    .filter(|d| {
+                        placements.record(d.inside(half_extents), || {
+                            set_v.iter().any(|v| v.overlaps(d))
+                        })
+                    }),
+            );
+            if let Some(new_disk) = candidates.choose(rng) {
+                placements.placed += 1;
+                front.push(*new_disk);
+                disks.push(*new_disk);
+                new_radius = sizes.sample(rng) as f32;
+                continue 'outer;
+            }
+        }
+        if let Some(i) = front.iter().position(|d| d == &curr_disk) {
+            front.remove(i);
+        }
+    }
+    disks
+        .into_iter()
+        .map(|d| Sphere::new(Point3::new(d.center.x, d.center.y, 0.), d.radius))
+        .collect()
+}
+
+/// Provides the (at most two) disks with `radius` in outer contact with both `a` and `b`.
+fn tangent_disks(a: &Disk, b: &Disk, radius: f32) -> [Option<Disk>; 2] {
+    // The new center is where the circles of radius `a.radius + radius` around `a` and
+    // `b.radius + radius` around `b` intersect.
+    let (to_a, to_b) = (a.radius + radius, b.radius + radius);
+    let between = b.center - a.center;
+    let distance = between.norm();
+    if distance == 0. || distance > to_a + to_b || distance < (to_a - to_b).abs() {
+        return [None, None];
+    }
+    let along = (to_a.powi(2) - to_b.powi(2) + distance.powi(2)) / (2. * distance);
+    let across = (to_a.powi(2) - along.powi(2)).max(0.).sqrt();
+    let unit = between / distance;
+    let normal = Vector2::new(-unit.y, unit.x);
+    let base = a.center + unit * along;
+    [1., -1.].map(|side| {
+        Some(Disk {
+            center: base + normal * (side * across),
+            radius,
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point2;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::packing::disk::{pack_disks, tangent_disks, Disk};
+    use crate::packing::Placements;
+
+    #[test]
+    fn tangent_disks_touch_both() {
+        let a = Disk {
+            center: Point2::new(-1., 0.),
+            radius: 1.,
+        };
+        let b = Disk {
+            center: Point2::new(1.5, 0.5),
+            radius: 1.5,
+        };
+        let found = tangent_disks(&a, &b, 0.5);
+        assert!(found.iter().all(Option::is_some));
+        for disk in found.iter().flatten() {
+            for other in [a, b] {
+                let distance = nalgebra::distance(&disk.center, &other.center);
+                assert!((distance - (disk.radius + other.radius)).abs() < 1e-5);
+            }
+        }
+        let far = Disk {
+            center: Point2::new(10., 0.),
+            radius: 1.,
+        };
+        assert_eq!([None, None], tangent_disks(&a, &far, 0.5));
+    }
+
+    #[test]
+    fn packs_disks_without_overlap() {
+        let sizes = rand::distributions::Uniform::new(0.5, 1.0);
+        let pack = |seed| {
+            pack_disks(
+                [6., 4.],
+                &mut sizes.clone(),
+                &mut ChaCha8Rng::seed_from_u64(seed),
+                &mut Placements::default(),
+            )
+            .unwrap()
+        };
+        let packed = pack(3);
+        assert_eq!(packed, pack(3));
+        assert!(packed.len() > 10);
+        for (i, a) in packed.iter().enumerate() {
+            assert_eq!(0., a.center.z);
+            assert!(a.center.x.abs() + a.radius <= 6. && a.center.y.abs() + a.radius <= 4.);
+            for b in &packed[i + 1..] {
+                let distance = nalgebra::distance(&a.center, &b.center);
+                assert!(distance >= (a.radius + b.radius) * (1. - 1e-5));
+            }
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//! Advancing front sphere packing driven by a caller-provided random number generator.
+//!
+//! This is a port of `spherical_cow::pack_spheres` (Valera *et al.*, Computational Particle
+//! Mechanics 2, 161 (2015)), which draws its random numbers from `rand::thread_rng` and so can't be
+//! reproduced from a seed. The algorithm itself is unchanged.
+use nalgebra::{Matrix, Point3, Vector3};
+use rand::distributions::Distribution;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use spherical_cow::errors::SphericalCowError;
+use spherical_cow::shapes::Sphere;
+use spherical_cow::Container;
+
+use crate::packing::{packed_volume, Placements};
+
+/// Pack spheres with radii drawn from `sizes` into `container` as densely as possible, using `rng`
+/// for every random choice so that a seeded `rng` gives a reproducible packing.
+///
+/// If `initial` is empty, packing starts from three tangent spheres at the origin; otherwise it
+/// grows from `initial`, which must fit in `container` without overlapping. The front only grows
+/// from a sphere with at least two others nearby, so an initial set that is too sparse may not be
+/// added to.
+///
+/// Every position tried for a new sphere is counted in `placements`.
+///
+/// If `settle` is set, the front instead always grows from its lowest sphere, and each new sphere
+/// is put in the lowest position found for it rather than a random one, as if settling under
+/// gravity along the negative z axis. The packing then grows down to the floor of the container
+/// before building up from it.
+///
+/// If `volume_limit` is given, packing also stops as soon as the total volume of the spheres
+/// (including `initial`) reaches it.
+pub(crate) fn pack_spheres<C, D, R>(
+    container: &C,
+    initial: Vec<Sphere>,
+    sizes: &mut D,
+    rng: &mut R,
+    placements: &mut Placements,
+    settle: bool,
+    volume_limit: Option<f64>,
+) -> Result<Vec<Sphere>, SphericalCowError>
+where
+    C: Container,
+    D: Distribution<f64>,
+    R: Rng,
+{
+    let mut spheres = if initial.is_empty() {
+        let init_radii = [
+            sizes.sample(rng) as f32,
+            sizes.sample(rng) as f32,
+            sizes.sample(rng) as f32,
+        ];
+        init_spheres(&init_radii, container)?
+    } else {
+        initial
+    };
+    let mut front = spheres.clone();
+    let mut new_radius = sizes.sample(rng) as f32;
+    let mut volume = packed_volume(&spheres);
+
+    let lowest = |a: &&Sphere, b: &&Sphere| a.center.z.total_cmp(&b.center.z);
+    let mut set_f = Vec::new();
+    'outer: while !front.is_empty() && volume_limit.is_none_or(|limit| volume < limit) {
+        let curr_sphere = if settle {
+            front.iter().min_by(lowest)
+        } else {
+            front.choose(rng)
+        }
+        .ok_or(SphericalCowError::NoneFront)?
+        .clone();
+        // Spheres close enough to `curr_sphere` that the new sphere could touch both.
+        let set_v: Vec<Sphere> = spheres
+            .iter()
+            .filter(|s| {
+                *s != &curr_sphere
+                    && nalgebra::distance(&curr_sphere.center, &s.center)
+                        <= curr_sphere.radius + s.radius + 2. * new_radius
+            })
+            .cloned()
+            .collect();
+
+        for (i, s_i) in set_v.iter().enumerate() {
+            for s_j in &set_v[i + 1..] {
+                set_f.clear();
+                identify_f(&mut set_f, &curr_sphere, s_i, s_j, new_radius)?;
+                set_f.retain(|s| {
+                    placements.record(container.contains(s), || {
+                        set_v.iter().any(|v| v.overlaps(s))
+                    })
+                });
+                let s_new = if settle {
+                    set_f.iter().min_by(lowest)
+                } else {
+                    set_f.choose(rng)
+                };
+                if let Some(s_new) = s_new {
+                    placements.placed += 1;
+                    front.push(s_new.clone());
+                    spheres.push(s_new.clone());
+                    volume += packed_volume(std::slice::from_ref(s_new));
+                    new_radius = sizes.sample(rng) as f32;
+                    continue 'outer;
+                }
+            }
+        }
+        if let Some(i) = front.iter().position(|s| s == &curr_sphere) {
+            front.remove(i);
+        }
+    }
+    Ok(spheres)
+}
+
+/// Number of positions tried for the spheres packing starts from before giving up, if they don't
+/// fit at the origin.
+const SEED_ATTEMPTS: usize = 1000;
+
+/// Provides three pairwise tangent spheres for packing to start from among `obstacles`, with radii
+/// drawn from `sizes`, or None if no room was found for them.
+///
+/// They are centered on the origin as `pack_spheres` would place them if they fit there in
+/// `container` without overlapping an obstacle, and are otherwise moved to the first of up to
+/// `SEED_ATTEMPTS` positions drawn uniformly from the box with `half_extents` where they do.
+pub(crate) fn seed_spheres<C, D, R>(
+    container: &C,
+    half_extents: [f32; 3],
+    obstacles: &[Sphere],
+    sizes: &mut D,
+    rng: &mut R,
+) -> Option<Vec<Sphere>>
+where
+    C: Container,
+    D: Distribution<f64>,
+    R: Rng,
+{
+    let radii = [
+        sizes.sample(rng) as f32,
+        sizes.sample(rng) as f32,
+        sizes.sample(rng) as f32,
+    ];
+    let centered = tangent_spheres(&radii).ok()?;
+    let fits = |seed: &[Sphere]| {
+        seed.iter()
+            .all(|s| container.contains(s) && !obstacles.iter().any(|o| o.overlaps(s)))
+    };
+    if fits(&centered) {
+        return Some(centered);
+    }
+    (0..SEED_ATTEMPTS).find_map(|_| {
+        let offset = Vector3::from(half_extents.map(|h| rng.gen_range(-h..=h)));
+        let moved: Vec<Sphere> = centered
+            .iter()
+            .map(|s| {
+                let mut moved = s.clone();
+                moved.center += offset;
+                moved
+            })
+            .collect();
+        fits(&moved).then_some(moved)
+    })
+}
+
+/// Create three pairwise tangent spheres with `radii` as `tangent_spheres` does, checking that
+/// they fit in `container`.
+fn init_spheres<C: Container>(
+    radii: &[f32; 3],
+    container: &C,
+) -> Result<Vec<Sphere>, SphericalCowError> {
+    let init = tangent_spheres(radii)?;
+    if init.iter().all(|s| container.contains(s)) {
+        Ok(init)
+    } else {
+        Err(SphericalCowError::Uncontained)
+    }
+}
+
+/// Create three pairwise tangent spheres with `radii`, positioned so that the incenter of the
+/// triangle formed by their centers is at the origin.
+fn tangent_spheres(radii: &[f32; 3]) -> Result<Vec<Sphere>, SphericalCowError> {
+    let [radius_a, radius_b, radius_c] = *radii;
+    let distance_c = radius_a + radius_b;
+    let distance_b = radius_a + radius_c;
+    let distance_a = radius_c + radius_b;
+
+    let b_2 = distance_b.powi(2);
+    let x = (b_2 + distance_c.powi(2) - distance_a.powi(2)) / (2. * distance_c);
+    let y = (b_2 - x.powi(2)).sqrt();
+
+    let perimeter = distance_a + distance_b + distance_c;
+    let incenter_x = (distance_b * distance_c + distance_c * x) / perimeter;
+    let incenter_y = (distance_c * y) / perimeter;
+
+    Ok(vec![
+        Sphere::new(Point3::new(-incenter_x, -incenter_y, 0.), radius_a)?,
+        Sphere::new(
+            Point3::new(distance_c - incenter_x, -incenter_y, 0.),
+            radius_b,
+        )?,
+        Sphere::new(Point3::new(x - incenter_x, y - incenter_y, 0.), radius_c)?,
+    ])
+}
+
+/// Push into `set_f` each sphere (of which there are at most two) with `radius` which is in outer
+/// contact with `s_1`, `s_2` and `s_3`.
+fn identify_f(
+    set_f: &mut Vec<Sphere>,
+    s_1: &Sphere,
+    s_2: &Sphere,
+    s_3: &Sphere,
+    radius: f32,
+) -> Result<(), SphericalCowError> {
+    // The new center is the fourth vertex of a tetrahedron with known edge lengths to the other
+    // three centers; see https://axiomatic.neophilus.net/posts/2018-01-16-clustering-tangent-spheres.html
+    let distance_14 = s_1.radius + radius;
+    let distance_24 = s_2.radius + radius;
+    let distance_34 = s_3.radius + radius;
+
+    let vector_u = s_1.center - s_2.center;
+    let unitvector_u = vector_u / Matrix::norm(&vector_u);
+    let vector_v = s_1.center - s_3.center;
+    let unitvector_v = vector_v / Matrix::norm(&vector_v);
+    let cross_uv = Matrix::cross(&vector_u, &vector_v);
+    let unitvector_t = cross_uv / Matrix::norm(&cross_uv);
+    let vector_w = -2. * s_1.center.coords;
+
+    let distance_c =
+        distance_14.powi(2) - s_1.center.x.powi(2) - s_1.center.y.powi(2) - s_1.center.z.powi(2);
+    let distance_a = (distance_24.powi(2)
+        - distance_c
+        - s_2.center.x.powi(2)
+        - s_2.center.y.powi(2)
+        - s_2.center.z.powi(2))
+        / (2. * Matrix::norm(&vector_u));
+    let distance_b = (distance_34.powi(2)
+        - distance_c
+        - s_3.center.x.powi(2)
+        - s_3.center.y.powi(2)
+        - s_3.center.z.powi(2))
+        / (2. * Matrix::norm(&vector_v));
+
+    let dot_uv = Matrix::dot(&unitvector_u, &unitvector_v);
+    let dot_wt = Matrix::dot(&vector_w, &unitvector_t);
+    let dot_uw = Matrix::dot(&unitvector_u, &vector_w);
+    let dot_vw = Matrix::dot(&unitvector_v, &vector_w);
+
+    let denominator = 1. - dot_uv.powi(2);
+    let alpha = (distance_a - distance_b * dot_uv) / denominator;
+    let beta = (distance_b - distance_a * dot_uv) / denominator;
+    let value_d =
+        alpha.powi(2) + beta.powi(2) + 2. * alpha * beta * dot_uv + alpha * dot_uw + beta * dot_vw
+            - distance_c;
+
+    // Imaginary solutions mean no sphere of this radius touches all three.
+    let dot_wt_2 = dot_wt.powi(2);
+    let value_4d = 4. * value_d;
+    if dot_wt_2 > value_4d {
+        for gamma in [
+            0.5 * (-dot_wt + (dot_wt_2 - value_4d).sqrt()),
+            0.5 * (-dot_wt - (dot_wt_2 - value_4d).sqrt()),
+        ] {
+            set_f.push(Sphere::new(
+                Point3::from(alpha * unitvector_u + beta * unitvector_v + gamma * unitvector_t),
+                radius,
+            )?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point3;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use spherical_cow::shapes::{Cuboid, Sphere};
+    use spherical_cow::Container;
+
+    use crate::packing::front::{identify_f, init_spheres, pack_spheres};
+    use crate::packing::{packed_volume, Placements};
+
+    #[test]
+    fn init_spheres_uncontained() {
+        let container = Sphere::new(Point3::origin(), 0.1).unwrap();
+        assert!(init_spheres(&[10., 15., 20.], &container).is_err());
+    }
+
+    #[test]
+    fn identify_f_known() {
+        let one = Sphere::new(Point3::new(0.5, -0.28112677, 0.0), 0.5).unwrap();
+        let two = Sphere::new(Point3::new(0.058333218, 0.44511732, 0.0), 0.35).unwrap();
+        let three = Sphere::new(Point3::new(-0.70000005, -0.28112677, 0.0), 0.7).unwrap();
+
+        let mut found = Vec::new();
+        identify_f(&mut found, &one, &two, &three, 0.4).unwrap();
+        assert!(found
+            .contains(&Sphere::new(Point3::new(0.06666666, 0.12316025, 0.6773287), 0.4).unwrap()));
+        assert!(found
+            .contains(&Sphere::new(Point3::new(0.06666666, 0.12316025, -0.6773287), 0.4).unwrap()));
+    }
+
+    #[test]
+    fn seeded_packing_reproducible() {
+        let container = Cuboid::new(3., 3., 3.).unwrap();
+        let sizes = rand::distributions::Uniform::new(0.5, 1.0);
+        let pack = |seed| {
+            pack_spheres(
+                &container,
+                Vec::new(),
+                &mut sizes.clone(),
+                &mut ChaCha8Rng::seed_from_u64(seed),
+                &mut Placements::default(),
+                false,
+                None,
+            )
+            .unwrap()
+        };
+        assert_eq!(pack(7), pack(7));
+        assert!(pack(7).len() > 3);
+    }
+
+    #[test]
+    fn settles_downward() {
+        let container = Cuboid::new(3., 3., 6.).unwrap();
+        let sizes = rand::distributions::Uniform::new(0.5, 1.0);
+        let pack = |settle| {
+            pack_spheres(
+                &container,
+                Vec::new(),
+                &mut sizes.clone(),
+                &mut ChaCha8Rng::seed_from_u64(7),
+                &mut Placements::default(),
+                settle,
+                None,
+            )
+            .unwrap()
+        };
+        let settled = pack(true);
+        assert_eq!(settled, pack(true));
+        assert_ne!(settled, pack(false));
+        // The fourth sphere is put below the first three, in the plane z = 0, not above them.
+        assert!(settled[3].center.z < 0.);
+        assert!(settled.iter().all(|s| container.contains(s)));
+    }
+
+    #[test]
+    fn grows_from_initial() {
+        let container = Cuboid::new(3., 3., 3.).unwrap();
+        let sizes = rand::distributions::Uniform::new(0.5, 1.0);
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let initial = init_spheres(&[0.75, 0.75, 0.75], &container).unwrap();
+        let packed = pack_spheres(
+            &container,
+            initial.clone(),
+            &mut sizes.clone(),
+            &mut rng,
+            &mut Placements::default(),
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(initial[..], packed[..3]);
+        assert!(packed.len() > 3);
+    }
+
+    #[test]
+    fn stops_at_volume_limit() {
+        let container = Cuboid::new(3., 3., 3.).unwrap();
+        let sizes = rand::distributions::Uniform::new(0.5, 1.0);
+        let pack = |volume_limit| {
+            pack_spheres(
+                &container,
+                Vec::new(),
+                &mut sizes.clone(),
+                &mut ChaCha8Rng::seed_from_u64(7),
+                &mut Placements::default(),
+                false,
+                volume_limit,
+            )
+            .unwrap()
+        };
+        let unlimited = pack(None);
+        let limit = packed_volume(&unlimited) / 2.;
+        let limited = pack(Some(limit));
+        // The packing is the same up to the first sphere to take it past the limit.
+        assert_eq!(unlimited[..limited.len()], limited[..]);
+        assert!(packed_volume(&limited) >= limit);
+        assert!(packed_volume(&limited[..limited.len() - 1]) < limit);
+    }
+}
@@ -0,0 +1,238 @@
+//! A convex polyhedron container bounded by half-spaces.
+use nalgebra::{Matrix3, Vector3};
+use spherical_cow::shapes::Sphere;
+use spherical_cow::Container;
+
+use crate::parsing::HalfSpace;
+
+/// Tolerance, relative to the scale of the polyhedron, within which a point is treated as on a
+/// plane.
+const EPSILON: f64 = 1e-9;
+
+/// How much further than the furthest plane from the origin the box which detects unbounded
+/// polyhedra is.
+const BOUNDING_SCALE: f64 = 1e6;
+
+/// A convex polyhedron, the intersection of a set of half-spaces, which contains the origin.
+#[derive(Debug, Clone)]
+pub(crate) struct Polyhedron {
+    /// Unit normal and offset of each plane, with points inside satisfying `normal · p <= offset`
+    planes: Vec<(Vector3<f64>, f64)>,
+    vertices: Vec<Vector3<f64>>,
+    volume: f64,
+}
+
+impl Polyhedron {
+    /// Create the polyhedron bounded by `half_spaces`, or provide why it isn't a valid container.
+    pub(crate) fn new(half_spaces: &[HalfSpace]) -> Result<Self, &'static str> {
+        let mut planes = Vec::with_capacity(half_spaces.len());
+        for half_space in half_spaces {
+            let normal = Vector3::from(half_space.normal);
+            let norm = normal.norm();
+            if !(norm > 0. && norm.is_finite() && half_space.offset.is_finite()) {
+                return Err("every normal must be non-zero and finite");
+            }
+            planes.push((normal / norm, half_space.offset / norm));
+        }
+        if planes.iter().any(|(_, offset)| *offset <= 0.) {
+            return Err("the origin must be strictly inside every half-space");
+        }
+        let scale = planes.iter().map(|(_, offset)| *offset).fold(1., f64::max);
+        // Clipping by a box far outside every plane bounds the vertices of an unbounded
+        // polyhedron, some of which are then on the box.
+        let far = BOUNDING_SCALE * scale;
+        let mut clipped = planes.clone();
+        for axis in 0..3 {
+            for sign in [1., -1.] {
+                let mut normal = Vector3::zeros();
+                normal[axis] = sign;
+                clipped.push((normal, far));
+            }
+        }
+        let vertices = vertices(&clipped, EPSILON * far);
+        if vertices
+            .iter()
+            .any(|v| v.iter().any(|c| c.abs() > far * (1. - EPSILON)))
+        {
+            return Err("the half-spaces must bound a finite region");
+        }
+        let volume = volume(&planes, &vertices, EPSILON * scale);
+        Ok(Polyhedron {
+            planes,
+            vertices,
+            volume,
+        })
+    }
+
+    /// Provides the volume enclosed by this polyhedron.
+    pub(crate) fn volume_f64(&self) -> f64 {
+        self.volume
+    }
+
+    /// Provides the volume in which the center of a sphere with `radius` can be while the sphere
+    /// stays inside this polyhedron, which is the polyhedron with every plane moved `radius` in.
+    pub(crate) fn accessible_volume(&self, radius: f64) -> f64 {
+        let planes: Vec<_> = self
+            .planes
+            .iter()
+            .map(|(normal, offset)| (*normal, offset - radius.max(0.)))
+            .collect();
+        let tolerance = EPSILON * self.planes.iter().map(|(_, o)| *o).fold(1., f64::max);
+        let vertices = vertices(&planes, tolerance);
+        // A polyhedron with fewer than four vertices is flat, or there is no room at all.
+        if vertices.len() < 4 {
+            return 0.;
+        }
+        volume(&planes, &vertices, tolerance).max(0.)
+    }
+
+    /// Provides the half-side lengths of the smallest origin-centered cuboid which encloses this
+    /// polyhedron.
+    pub(crate) fn half_extents(&self) -> [f32; 3] {
+        [0, 1, 2].map(|axis| {
+            self.vertices
+                .iter()
+                .map(|v| v[axis].abs())
+                .fold(0., f64::max) as f32
+        })
+    }
+}
+
+impl Container for Polyhedron {
+    fn contains(&self, sphere: &Sphere) -> bool {
+        let center = Vector3::new(
+            sphere.center.x as f64,
+            sphere.center.y as f64,
+            sphere.center.z as f64,
+        );
+        self.planes
+            .iter()
+            .all(|(normal, offset)| normal.dot(&center) + sphere.radius as f64 <= *offset)
+    }
+
+    fn volume(&self) -> f32 {
+        self.volume as f32
+    }
+}
+
+/// Provides the distinct vertices of the polyhedron bounded by `planes`: the points where three
+/// planes meet which are inside every other plane, to within `tolerance`.
+fn vertices(planes: &[(Vector3<f64>, f64)], tolerance: f64) -> Vec<Vector3<f64>> {
+    let mut vertices: Vec<Vector3<f64>> = Vec::new();
+    for (i, a) in planes.iter().enumerate() {
+        for (j, b) in planes.iter().enumerate().skip(i + 1) {
+            for c in &planes[j + 1..] {
+                let normals =
+                    Matrix3::from_rows(&[a.0.transpose(), b.0.transpose(), c.0.transpose()]);
+                let vertex = match normals.try_inverse() {
+                    Some(inverse) => inverse * Vector3::new(a.1, b.1, c.1),
+                    None => continue,
+                };
+                let inside = planes
+                    .iter()
+                    .all(|(normal, offset)| normal.dot(&vertex) <= offset + tolerance);
+                if inside && !vertices.iter().any(|v| (v - vertex).norm() <= tolerance) {
+                    vertices.push(vertex);
+                }
+            }
+        }
+    }
+    vertices
+}
+
+/// Provides the volume of the polyhedron bounded by `planes` with `vertices`, as the sum of the
+/// pyramids from its centroid to each face.
+fn volume(planes: &[(Vector3<f64>, f64)], vertices: &[Vector3<f64>], tolerance: f64) -> f64 {
+    let centroid = vertices.iter().sum::<Vector3<f64>>() / vertices.len() as f64;
+    planes
+        .iter()
+        .map(|(normal, offset)| {
+            let mut face: Vec<Vector3<f64>> = vertices
+                .iter()
+                .filter(|v| (normal.dot(v) - offset).abs() <= tolerance)
+                .copied()
+                .collect();
+            if face.len() < 3 {
+                return 0.;
+            }
+            // Order the face's vertices by angle around its center, in the plane of the face.
+            let center = face.iter().sum::<Vector3<f64>>() / face.len() as f64;
+            let u = (face[0] - center).normalize();
+            let w = normal.cross(&u);
+            let angle = |v: &Vector3<f64>| (v - center).dot(&w).atan2((v - center).dot(&u));
+            face.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+            let area: f64 = (0..face.len())
+                .map(|k| {
+                    let (a, b) = (face[k] - center, face[(k + 1) % face.len()] - center);
+                    normal.dot(&a.cross(&b)) / 2.
+                })
+                .sum();
+            area * (offset - normal.dot(&centroid)) / 3.
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point3;
+    use spherical_cow::shapes::Sphere;
+    use spherical_cow::Container;
+
+    use crate::packing::polyhedron::Polyhedron;
+    use crate::parsing::HalfSpace;
+
+    fn half_space(normal: [f64; 3], offset: f64) -> HalfSpace {
+        HalfSpace { normal, offset }
+    }
+
+    #[test]
+    fn cuboid_volume_and_containment() {
+        let cuboid = Polyhedron::new(&[
+            half_space([1., 0., 0.], 1.),
+            half_space([-2., 0., 0.], 2.),
+            half_space([0., 1., 0.], 2.),
+            half_space([0., -1., 0.], 2.),
+            half_space([0., 0., 1.], 3.),
+            half_space([0., 0., -1.], 3.),
+        ])
+        .unwrap();
+        assert!((cuboid.volume_f64() - 2. * 4. * 6.).abs() < 1e-9);
+        assert_eq!([1., 2., 3.], cuboid.half_extents());
+        assert!(cuboid.contains(&Sphere::new(Point3::new(0., 1., 2.), 1.).unwrap()));
+        assert!(!cuboid.contains(&Sphere::new(Point3::new(0.5, 0., 0.), 1.).unwrap()));
+        assert!((cuboid.accessible_volume(0.) - 2. * 4. * 6.).abs() < 1e-9);
+        assert!((cuboid.accessible_volume(0.5) - 1. * 3. * 5.).abs() < 1e-9);
+        assert_eq!(0., cuboid.accessible_volume(1.5));
+    }
+
+    #[test]
+    fn tetrahedron_volume() {
+        // The tetrahedron with vertices at the origin and the unit points on each axis, shifted
+        // so that it contains the origin.
+        let shift = 0.1;
+        let tetrahedron = Polyhedron::new(&[
+            half_space([-1., 0., 0.], shift),
+            half_space([0., -1., 0.], shift),
+            half_space([0., 0., -1.], shift),
+            half_space([1., 1., 1.], 1. - 3. * shift),
+        ])
+        .unwrap();
+        assert!((tetrahedron.volume_f64() - 1. / 6.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_polyhedra() {
+        let open = [half_space([1., 0., 0.], 1.), half_space([-1., 0., 0.], 1.)];
+        assert!(Polyhedron::new(&open).is_err());
+        let excludes_origin = [
+            half_space([1., 0., 0.], -1.),
+            half_space([-1., 0., 0.], 2.),
+            half_space([0., 1., 0.], 1.),
+            half_space([0., -1., 0.], 1.),
+            half_space([0., 0., 1.], 1.),
+            half_space([0., 0., -1.], 1.),
+        ];
+        assert!(Polyhedron::new(&excludes_origin).is_err());
+        assert!(Polyhedron::new(&[half_space([0., 0., 0.], 1.)]).is_err());
+    }
+}
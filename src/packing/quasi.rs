@@ -0,0 +1,97 @@
+//! Low-discrepancy sequences which can stand in for a random number generator.
+//!
+//! A low-discrepancy sequence covers `[0, 1)` more evenly than independent uniform draws do, so
+//! that, for example, the proportion of each sphere type drawn converges on its expected value
+//! faster. Such a sequence is deterministic rather than random, and only evenly distributed along
+//! the one dimension it is drawn from.
+use rand::RngCore;
+
+/// One dimension of a Halton sequence: the radical inverses of 0, 1, 2, ... in some base, each
+/// shifted by a constant offset (a Cranley-Patterson rotation) so that different offsets give
+/// different sequences while keeping them evenly distributed.
+#[derive(Debug, Clone)]
+pub(crate) struct Halton {
+    base: u64,
+    index: u64,
+    shift: f64,
+}
+
+impl Halton {
+    /// Create the Halton sequence in `base`, which should be prime, shifted by `shift` modulo 1.
+    pub(crate) fn new(base: u64, shift: f64) -> Self {
+        Halton {
+            base,
+            index: 0,
+            shift,
+        }
+    }
+
+    /// Provides the next element of this sequence, which is in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        let (mut n, mut inverse, mut place) = (self.index, 0., 1.);
+        while n > 0 {
+            place /= self.base as f64;
+            inverse += (n % self.base) as f64 * place;
+            n /= self.base;
+        }
+        self.index += 1;
+        (inverse + self.shift).fract()
+    }
+}
+
+/// Elements of the sequence are provided as the leading bits of each integer, which is where
+/// `rand` takes floating point numbers and ranges of integers from.
+impl RngCore for Halton {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_f64() * 2f64.powi(32)) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (self.next_f64() * 2f64.powi(64)) as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use crate::packing::quasi::Halton;
+
+    #[test]
+    fn radical_inverses() {
+        let mut halton = Halton::new(2, 0.);
+        let first: Vec<f64> = (0..5).map(|_| halton.next_f64()).collect();
+        assert_eq!(vec![0., 0.5, 0.25, 0.75, 0.125], first);
+        let mut shifted = Halton::new(3, 0.5);
+        assert_eq!(0.5, shifted.next_f64());
+        assert!((shifted.next_f64() - (1. / 3. + 0.5)).abs() < 1e-12);
+        assert!((shifted.next_f64() - (2. / 3. + 0.5 - 1.)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn covers_ranges_evenly() {
+        let mut halton = Halton::new(3, 0.3);
+        let mut counts = [0; 4];
+        for _ in 0..400 {
+            counts[halton.gen_range(0..4)] += 1;
+        }
+        // Independent draws would typically be off by about 9 from 100.
+        assert!(
+            counts.iter().all(|&c| (99..=101).contains(&c)),
+            "{:?}",
+            counts
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Compaction of a packing, pulling spheres towards the center of the container to open up space
+//! at its walls which more spheres can then be packed into.
+//!
+//! This is a heuristic soft-sphere relaxation: each pass pulls every sphere a little towards the
+//! center, which makes neighbouring spheres overlap, and then pushes overlapping pairs apart until
+//! none do. Spheres settle into gaps as they are pushed around, but the result is not guaranteed
+//! to be any denser than the packing that was relaxed.
+use nalgebra::{Point3, Vector3};
+use spherical_cow::shapes::Sphere;
+
+use crate::analysis::grid::Grid;
+
+/// Distance each sphere is pulled towards the center per pass, relative to the largest radius.
+const PULL: f32 = 0.1;
+
+/// Most rounds of pushing overlapping spheres apart in each pass.
+const MAX_ROUNDS: usize = 50;
+
+/// Most rounds of pushing overlapping spheres apart once the last pass has finished.
+const SETTLE_ROUNDS: usize = 1000;
+
+/// Relative amount two spheres may overlap by and still be treated as only touching.
+const TOLERANCE: f32 = 1e-5;
+
+/// Run `iterations` relaxation passes over `spheres` in the cuboid container with `half_extents`.
+///
+/// Any sphere still overlapping another once the last pass has finished is removed, so the result
+/// is always a valid packing. `after_pass` is called with the number of each pass, counting from
+/// 1, and the spheres as they are once it has finished, which may still overlap.
+pub(crate) fn compact<F: FnMut(usize, &[Sphere])>(
+    spheres: &mut Vec<Sphere>,
+    half_extents: [f32; 3],
+    iterations: usize,
+    mut after_pass: F,
+) {
+    let max_radius = spheres.iter().map(|s| s.radius).fold(0., f32::max);
+    if max_radius <= 0. || iterations == 0 {
+        return;
+    }
+    for iteration in 1..=iterations {
+        for sphere in spheres.iter_mut() {
+            let distance = sphere.center.coords.norm();
+            if distance > 0. {
+                let pull = distance.min(PULL * max_radius);
+                sphere.center -= sphere.center.coords / distance * pull;
+            }
+        }
+        for _ in 0..MAX_ROUNDS {
+            if !separate(spheres, half_extents, max_radius) {
+                break;
+            }
+        }
+        after_pass(iteration, spheres);
+    }
+    // Let the spheres settle without being pulled before giving up on those which still overlap.
+    for _ in 0..SETTLE_ROUNDS {
+        if !separate(spheres, half_extents, max_radius) {
+            break;
+        }
+    }
+    remove_overlapping(spheres, half_extents, max_radius);
+}
+
+/// Whether `a` and `b` overlap by more than `TOLERANCE`.
+fn overlapping(a: &Sphere, b: &Sphere) -> bool {
+    nalgebra::distance(&a.center, &b.center) < (a.radius + b.radius) * (1. - TOLERANCE)
+}
+
+/// Push every overlapping pair of `spheres` apart along the line between their centers, keeping
+/// both inside the container with `half_extents`, providing whether any pair overlapped.
+fn separate(spheres: &mut [Sphere], half_extents: [f32; 3], max_radius: f32) -> bool {
+    // Spheres only move by a fraction of a radius in a round, so neighbours are looked up from
+    // where they started it.
+    let grid = Grid::new(spheres, half_extents, 2. * max_radius as f64);
+    let start = spheres.to_vec();
+    let mut any = false;
+    for i in 0..spheres.len() {
+        for j in grid.near(&start[i]).filter(|&j| j > i) {
+            if !overlapping(&spheres[i], &spheres[j]) {
+                continue;
+            }
+            any = true;
+            let between = spheres[j].center - spheres[i].center;
+            let distance = between.norm();
+            let depth = spheres[i].radius + spheres[j].radius - distance;
+            // Coincident centers have no line between them, so those are pushed apart along x.
+            let normal = if distance > 0. {
+                between / distance
+            } else {
+                Vector3::x()
+            };
+            let push = normal * (depth / 2. * (1. + 10. * TOLERANCE));
+            spheres[i].center -= push;
+            spheres[j].center += push;
+            clamp(&mut spheres[i], half_extents);
+            clamp(&mut spheres[j], half_extents);
+        }
+    }
+    any
+}
+
+/// Move `sphere` the least distance needed for it to be inside the container with `half_extents`.
+fn clamp(sphere: &mut Sphere, half_extents: [f32; 3]) {
+    let r = sphere.radius;
+    let c = sphere.center;
+    sphere.center = Point3::new(
+        c.x.clamp(r - half_extents[0], half_extents[0] - r),
+        c.y.clamp(r - half_extents[1], half_extents[1] - r),
+        c.z.clamp(r - half_extents[2], half_extents[2] - r),
+    );
+}
+
+/// Remove the later sphere of every pair in `spheres` which overlaps.
+fn remove_overlapping(spheres: &mut Vec<Sphere>, half_extents: [f32; 3], max_radius: f32) {
+    let grid = Grid::new(spheres, half_extents, 2. * max_radius as f64);
+    let mut removed = vec![false; spheres.len()];
+    for i in 0..spheres.len() {
+        if removed[i] {
+            continue;
+        }
+        for j in grid.near(&spheres[i]).filter(|&j| j > i) {
+            if overlapping(&spheres[i], &spheres[j]) {
+                removed[j] = true;
+            }
+        }
+    }
+    let mut removed = removed.into_iter();
+    spheres.retain(|_| !removed.next().unwrap());
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Point3;
+    use spherical_cow::shapes::Sphere;
+
+    use crate::packing::relax::{compact, overlapping};
+
+    #[test]
+    fn compacts_without_overlap() {
+        let mut spheres = vec![
+            Sphere::new(Point3::new(0., 0., 0.), 1.).unwrap(),
+            Sphere::new(Point3::new(6., 0., 0.), 1.).unwrap(),
+            Sphere::new(Point3::new(0., -7., 0.), 2.).unwrap(),
+            Sphere::new(Point3::new(0., 0., 0.5), 1.).unwrap(),
+        ];
+        let mut passes = Vec::new();
+        compact(&mut spheres, [10., 10., 10.], 100, |pass, spheres| {
+            passes.push((pass, spheres.len()))
+        });
+        assert_eq!((1..=100).map(|pass| (pass, 4)).collect::<Vec<_>>(), passes);
+        assert_eq!(4, spheres.len());
+        for (i, a) in spheres.iter().enumerate() {
+            assert!(a.center.coords.iter().all(|c| c.abs() + a.radius <= 10.));
+            for b in &spheres[i + 1..] {
+                assert!(!overlapping(a, b), "{:?} {:?}", a, b);
+            }
+        }
+        // Pulled together, the spheres end up clustered around the center.
+        assert!(
+            spheres.iter().all(|s| s.center.coords.norm() < 4.),
+            "{:?}",
+            spheres
+        );
+    }
+}
@@ -1,47 +1,353 @@
-//! Module used for parsing JSON into sphere packing parameters.
+//! Module used for parsing JSON and other input formats into sphere packing parameters.
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::f64::consts::PI;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+use std::path::Path;
 use std::str::FromStr;
 
-use serde::{Deserialize, Serialize};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, DeserializeOwned, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
+use crate::packing;
+
 #[derive(Error, Debug)]
 /// An enumeration of the different errors that can occur while parsing a string into a Spheres
 /// struct.
-pub(crate) enum ParsingError {
+pub enum ParsingError {
     #[error("failed to parse string")]
     FailedToParse(#[from] serde_json::Error),
+    #[error("failed to parse YAML")]
+    FailedToParseYaml(#[from] serde_yaml::Error),
+    #[error("failed to parse TOML")]
+    FailedToParseToml(#[from] toml::de::Error),
+    #[error("failed to parse CSV")]
+    FailedToParseCsv(#[from] csv::Error),
+    #[error("failed to parse JSON5")]
+    FailedToParseJson5(#[from] json5::Error),
+    #[error("failed to read input")]
+    FailedToRead(#[from] std::io::Error),
+    #[error("unknown input format '{0}'")]
+    UnknownFormat(String),
     #[error("non-positive values for radius are not allowed")]
     NonPositive,
+    #[error("radii, positions and planes must be finite at the precision they are used in")]
+    NonFinite,
+    #[error("input is nested more than {0} levels deep")]
+    TooDeep(usize),
     #[error("invalid proportions: did not sum to 100")]
     InvalidProportions,
+    #[error("sphere name '{0}' appears in more than one merged file")]
+    DuplicateName(String),
+    #[error("merge weights must be positive and finite")]
+    InvalidWeight,
+    #[error("no sphere radii are between {0} and {1}")]
+    EmptyFilter(f64, f64),
+    #[error("splitting at radius {0} leaves no sphere types on one side")]
+    EmptySplit(f64),
+    #[error("scaling by {0} made the radius of '{1}' zero, negative or infinite")]
+    BadScale(f64, String),
+    #[error("sphere counts must be non-negative and finite, and not all 0")]
+    InvalidCounts,
+    #[error("sphere name '{0}' appears more than once in the {1}")]
+    RepeatedName(String, &'static str),
+    #[error(
+        "radii and proportions name different spheres: radii without a proportion: {}; \
+         proportions without a radius: {}",
+        list_names(.0),
+        list_names(.1)
+    )]
+    MismatchedNames(Vec<String>, Vec<String>),
+    #[error("sphere '{0}' has radius {1}, which isn't one of the allowed radii")]
+    DisallowedRadius(String, f64),
+    #[error("input has {0} sphere types, more than the limit of {1}")]
+    TooManyTypes(usize, usize),
+    #[error("sphere '{0}' has density {1}, but densities must be positive and finite")]
+    InvalidDensity(String, f64),
+    #[error("sphere '{0}' has proportion {1}, but proportions must be whole numbers")]
+    NonIntegerProportion(String, f64),
+    #[error("invalid proportions: fractions summed to {0}, which isn't within {1} of 1")]
+    InvalidFractions(f64, f64),
+    #[error("invalid {0} distribution: {1}")]
+    InvalidDistribution(&'static str, &'static str),
+}
+
+/// Provides `names` as a comma-separated list, or `none` if there are none.
+fn list_names(names: &[String]) -> String {
+    if names.is_empty() {
+        String::from("none")
+    } else {
+        names.join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An enumeration of the formats spheres can be read from.
+///
+/// Every format but CSV accepts either a list of spheres or an object of parallel arrays; TOML
+/// requires the list be given as an array of tables under a `spheres` key. CSV requires a header
+/// row naming the `name`, `radius` and `proportion` columns.
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+    Csv,
+    Json5,
+}
+
+impl FromStr for Format {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name || format.aliases().contains(&name.as_str()))
+            .ok_or_else(|| ParsingError::UnknownFormat(s.to_string()))
+    }
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
-/// A struct representing a sphere that has not been validated yet.
-struct SpheresRaw(Vec<ParsedSphere>);
+impl Format {
+    /// Every format spheres can be read from.
+    pub const ALL: [Format; 5] = [
+        Format::Json,
+        Format::Yaml,
+        Format::Toml,
+        Format::Csv,
+        Format::Json5,
+    ];
+
+    /// Provides the name this format is given by, with `--format` or as an extension.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Csv => "csv",
+            Format::Json5 => "json5",
+        }
+    }
+
+    /// Provides the other names this format can be given by.
+    pub fn aliases(self) -> &'static [&'static str] {
+        match self {
+            Format::Yaml => &["yml"],
+            Format::Json | Format::Toml | Format::Csv | Format::Json5 => &[],
+        }
+    }
+
+    /// Provides the format implied by the extension of `path`, if it has a recognized extension.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Format> {
+        path.as_ref().extension()?.to_str()?.parse().ok()
+    }
+
+    /// Split a directive of the form `# format: <format>` off the first line of `contents`,
+    /// providing the declared format, if there is such a directive, and the rest of `contents`.
+    ///
+    /// Returns an error if the directive names an unknown format.
+    pub fn split_directive(contents: &str) -> Result<(Option<Format>, &str), ParsingError> {
+        let (first, rest) = contents.split_once('\n').unwrap_or((contents, ""));
+        let declared = first
+            .trim()
+            .strip_prefix('#')
+            .and_then(|comment| comment.trim_start().strip_prefix("format:"));
+        match declared {
+            Some(format) => Ok((Some(format.trim().parse()?), rest)),
+            None => Ok((None, contents)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// A struct representing spheres that have not been validated yet, with proportions of type `P`.
+struct SpheresRaw<P = u8>(Vec<ParsedSphere<P>>);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "SpheresColumns")]
+/// A struct representing spheres given as parallel arrays rather than as a list of objects.
+struct SpheresColumns<P> {
+    names: Vec<String>,
+    #[schemars(extend("items" = {"type": "number", "exclusiveMinimum": 0}))]
+    radii: Vec<f64>,
+    proportions: Vec<P>,
+    #[serde(default)]
+    min_counts: Option<Vec<Option<u64>>>,
+    #[serde(default)]
+    densities: Option<Vec<Option<f64>>>,
+}
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl<P> SpheresColumns<P> {
+    /// Zip the columns into a SpheresRaw, providing a message describing the mismatch if the
+    /// columns differ in length.
+    fn zip(self) -> Result<SpheresRaw<P>, String> {
+        let (n, r, p) = (self.names.len(), self.radii.len(), self.proportions.len());
+        let min_counts = self.min_counts.unwrap_or_else(|| vec![None; n]);
+        let densities = self.densities.unwrap_or_else(|| vec![None; n]);
+        if n == r && r == p && p == min_counts.len() && p == densities.len() {
+            Ok(SpheresRaw(
+                self.names
+                    .into_iter()
+                    .zip(self.radii)
+                    .zip(self.proportions)
+                    .zip(min_counts)
+                    .zip(densities)
+                    .map(
+                        |((((name, radius), proportion), min_count), density)| ParsedSphere {
+                            name,
+                            radius,
+                            proportion,
+                            min_count,
+                            density,
+                        },
+                    )
+                    .collect(),
+            ))
+        } else {
+            Err(format!(
+                "column lengths differ: {} names, {} radii, {} proportions, {} min_counts, {} \
+                 densities",
+                n,
+                r,
+                p,
+                min_counts.len(),
+                densities.len()
+            ))
+        }
+    }
+}
+
+/// Visitor accepting either a list of sphere objects or an object of parallel arrays.
+struct SpheresRawVisitor<P>(PhantomData<P>);
+
+impl<'de, P: Deserialize<'de>> Visitor<'de> for SpheresRawVisitor<P> {
+    type Value = SpheresRaw<P>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a list of spheres or an object of names, radii and proportions")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        Vec::deserialize(SeqAccessDeserializer::new(seq)).map(SpheresRaw)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        SpheresColumns::deserialize(MapAccessDeserializer::new(map))?
+            .zip()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl<'de, P: Deserialize<'de>> Deserialize<'de> for SpheresRaw<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(SpheresRawVisitor(PhantomData))
+    }
+}
+
+impl JsonSchema for SpheresRaw {
+    fn schema_name() -> Cow<'static, str> {
+        "Spheres".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A list of sphere types, or an object of parallel arrays of the same \
+                length describing them. The proportions must sum to exactly 100.",
+            "anyOf": [
+                {"type": "array", "items": generator.subschema_for::<ParsedSphere<u8>>()},
+                generator.subschema_for::<SpheresColumns<u8>>(),
+            ],
+        })
+    }
+}
+
+/// Provides a JSON Schema describing inputs in JSON, YAML or JSON5.
+///
+/// The requirement that proportions sum to 100 can't be expressed in the schema, so inputs which
+/// satisfy the schema may still fail validation.
+pub fn input_schema() -> Schema {
+    schemars::schema_for!(SpheresRaw)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[schemars(rename = "ParsedSphere")]
 /// A struct representing the properties of a single sphere type.
-pub(crate) struct ParsedSphere {
+///
+/// Proportions are read as `u8` percentages, or as other types (such as `f64` counts) to be
+/// normalized into percentages, and are `f64` percentages once validated, so that normalizing
+/// doesn't round a rare type's share away.
+#[serde(bound(serialize = "P: Copy + Into<f64>"))]
+pub struct ParsedSphere<P = f64> {
     name: String,
+    #[schemars(extend("exclusiveMinimum" = 0))]
     radius: f64,
-    proportion: u8,
+    #[serde(serialize_with = "serialize_proportion")]
+    proportion: P,
+    /// Fewest spheres of this type to place, which are placed before any others
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_count: Option<u64>,
+    /// Density of the material, which proportions by mass are converted with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(extend("exclusiveMinimum" = 0))]
+    density: Option<f64>,
+}
+
+/// Serialize `proportion` as an integer if it's a whole number, so that percentages read as
+/// integers are written (and hashed) as they were read.
+fn serialize_proportion<P: Copy + Into<f64>, S: Serializer>(
+    proportion: &P,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let proportion: f64 = (*proportion).into();
+    if proportion.fract() == 0.0 && (0.0..=u64::MAX as f64).contains(&proportion) {
+        serializer.serialize_u64(proportion as u64)
+    } else {
+        serializer.serialize_f64(proportion)
+    }
+}
+
+impl<P> ParsedSphere<P> {
+    /// Provides this sphere type with `proportion` in place of its own.
+    fn with_proportion<Q>(self, proportion: Q) -> ParsedSphere<Q> {
+        ParsedSphere {
+            name: self.name,
+            radius: self.radius,
+            proportion,
+            min_count: self.min_count,
+            density: self.density,
+        }
+    }
 }
 
 impl ParsedSphere {
-    pub(crate) fn radius(&self) -> f64 {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn radius(&self) -> f64 {
         self.radius
     }
 
-    pub(crate) fn proportion(&self) -> u8 {
+    pub fn proportion(&self) -> f64 {
         self.proportion
     }
+
+    pub fn min_count(&self) -> Option<u64> {
+        self.min_count
+    }
+
+    pub fn density(&self) -> Option<f64> {
+        self.density
+    }
 }
 
 #[derive(Debug, Serialize, PartialEq)]
 /// A struct representing multiple spheres to attempt to pack, after validation.
-pub(crate) struct Spheres(Vec<ParsedSphere>);
+pub struct Spheres(Vec<ParsedSphere>);
 
 impl FromStr for SpheresRaw {
     type Err = ParsingError;
@@ -51,20 +357,192 @@ impl FromStr for SpheresRaw {
     }
 }
 
+/// A struct representing a TOML document, which must be a table, listing spheres.
+#[derive(Deserialize)]
+#[serde(bound = "P: DeserializeOwned")]
+struct TomlSpheres<P> {
+    spheres: SpheresRaw<P>,
+}
+
+impl<P: DeserializeOwned> SpheresRaw<P> {
+    /// Read `reader` to completion and parse its contents as `format`.
+    fn from_reader<R: Read>(mut reader: R, format: Format) -> Result<Self, ParsingError> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Ok(match format {
+            Format::Json => serde_json::from_str(&contents)?,
+            Format::Yaml => from_yaml(&contents)?,
+            Format::Json5 => json5::from_str(&contents)?,
+            Format::Toml => match toml::from_str::<TomlSpheres<P>>(&contents) {
+                Ok(listed) => listed.spheres,
+                Err(_) => toml::from_str(&contents)?,
+            },
+            Format::Csv => SpheresRaw(
+                csv::Reader::from_reader(contents.as_bytes())
+                    .deserialize()
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
 /// Validate `raw`, providing the corresponding validated Spheres struct or an error if `raw` is
 /// invalid.
 ///
-/// `raw` is invalid if the sum of its proportions is not exactly 100, or if any radii are less than
-/// or equal to 0 (proportions must be at least 0).
+/// `raw` is invalid if the sum of its proportions is not exactly 100, or if its sphere types are
+/// invalid as `check_types` describes.
 fn validate(raw: SpheresRaw) -> Result<Spheres, ParsingError> {
-    if raw.0.iter().all(|s| s.radius > 0.0) {
-        if raw.0.iter().map(|s| s.proportion as u32).sum::<u32>() == 100 {
-            Ok(Spheres(raw.0))
+    check_types(&raw.0)?;
+    if raw.0.iter().map(|s| s.proportion as u32).sum::<u32>() == 100 {
+        Ok(percentages(raw))
+    } else {
+        Err(ParsingError::InvalidProportions)
+    }
+}
+
+/// Validate `normalized`, whose proportions have already been scaled to sum to 100, providing the
+/// corresponding validated Spheres struct or an error if its sphere types are invalid as
+/// `check_types` describes.
+fn validate_normalized(normalized: SpheresRaw<f64>) -> Result<Spheres, ParsingError> {
+    check_types(&normalized.0)?;
+    Ok(Spheres(normalized.0))
+}
+
+/// Check the sphere types of an input other than by their proportions, providing an error if any
+/// radii are non-finite or less than or equal to 0, any densities aren't positive and finite, or
+/// any name appears more than once.
+fn check_types<P>(spheres: &[ParsedSphere<P>]) -> Result<(), ParsingError> {
+    if !spheres.iter().all(|s| (s.radius as f32).is_finite()) {
+        return Err(ParsingError::NonFinite);
+    }
+    if let Some(s) = spheres
+        .iter()
+        .find(|s| s.density.is_some_and(|d| !(d > 0.0 && d.is_finite())))
+    {
+        return Err(ParsingError::InvalidDensity(
+            s.name.clone(),
+            s.density.unwrap_or_default(),
+        ));
+    }
+    let mut names = HashSet::new();
+    if let Some(s) = spheres.iter().find(|s| !names.insert(s.name.as_str())) {
+        return Err(ParsingError::RepeatedName(s.name.clone(), "input"));
+    }
+    if spheres.iter().all(|s| s.radius > 0.0) {
+        Ok(())
+    } else {
+        Err(ParsingError::NonPositive)
+    }
+}
+
+/// Provide the Spheres struct for `raw`, whose proportions are percentages, without checking them.
+fn percentages(raw: SpheresRaw) -> Spheres {
+    Spheres(
+        raw.0
+            .into_iter()
+            .map(|s| {
+                let proportion = s.proportion as f64;
+                s.with_proportion(proportion)
+            })
+            .collect(),
+    )
+}
+
+/// Provide the Spheres struct for `raw` without validating it, at the caller's own risk.
+///
+/// Only non-finite radii, which the packer can't place spheres with at all, are rejected.
+fn unchecked(raw: SpheresRaw) -> Result<Spheres, ParsingError> {
+    if !raw.0.iter().all(|s| (s.radius as f32).is_finite()) {
+        return Err(ParsingError::NonFinite);
+    }
+    Ok(percentages(raw))
+}
+
+/// Validation of spheres one at a time as they're read, accumulating what later spheres are checked
+/// against, so that a list needn't be held whole before it's validated.
+///
+/// Spheres accepted by `validate` are exactly those accepted here, though an input with more than
+/// one problem may be rejected for a different one of them.
+#[derive(Default)]
+struct StreamingValidation {
+    spheres: Vec<ParsedSphere>,
+    names: HashSet<String>,
+    proportion_sum: u32,
+    /// The first invalid sphere's error, which has to be kept aside while the reader stops.
+    error: Option<ParsingError>,
+}
+
+impl StreamingValidation {
+    /// Check `sphere` against those before it, keeping it if it's valid.
+    fn push(&mut self, sphere: ParsedSphere<u8>) -> Result<(), ParsingError> {
+        if !(sphere.radius as f32).is_finite() {
+            return Err(ParsingError::NonFinite);
+        }
+        if let Some(d) = sphere.density.filter(|d| !(*d > 0.0 && d.is_finite())) {
+            return Err(ParsingError::InvalidDensity(sphere.name, d));
+        }
+        if sphere.radius <= 0.0 {
+            return Err(ParsingError::NonPositive);
+        }
+        if !self.names.insert(sphere.name.clone()) {
+            return Err(ParsingError::RepeatedName(sphere.name, "input"));
+        }
+        // Once the sum is past 100 no later sphere can fix it, so there's no need to read on.
+        self.proportion_sum += sphere.proportion as u32;
+        if self.proportion_sum > 100 {
+            return Err(ParsingError::InvalidProportions);
+        }
+        let proportion = sphere.proportion as f64;
+        self.spheres.push(sphere.with_proportion(proportion));
+        Ok(())
+    }
+
+    /// Provides the spheres read, if their proportions sum to exactly 100.
+    fn finish(self) -> Result<Spheres, ParsingError> {
+        if self.proportion_sum == 100 {
+            Ok(Spheres(self.spheres))
         } else {
             Err(ParsingError::InvalidProportions)
         }
-    } else {
-        Err(ParsingError::NonPositive)
+    }
+}
+
+/// Visitor validating each sphere of a list as it's deserialized. An object of parallel arrays is
+/// validated sphere by sphere too, though its columns are necessarily read whole first.
+struct StreamingVisitor<'a>(&'a mut StreamingValidation);
+
+impl<'a> StreamingVisitor<'a> {
+    /// Push `sphere`, setting the error aside and stopping the deserializer if it's invalid.
+    fn push<E: de::Error>(&mut self, sphere: ParsedSphere<u8>) -> Result<(), E> {
+        self.0.push(sphere).map_err(|e| {
+            self.0.error = Some(e);
+            E::custom("invalid sphere")
+        })
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for StreamingVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a list of spheres or an object of names, radii and proportions")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(sphere) = seq.next_element()? {
+            self.push(sphere)?;
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, map: A) -> Result<(), A::Error> {
+        let columns = SpheresColumns::deserialize(MapAccessDeserializer::new(map))?
+            .zip()
+            .map_err(de::Error::custom)?;
+        for sphere in columns.0 {
+            self.push(sphere)?;
+        }
+        Ok(())
     }
 }
 
@@ -76,30 +554,759 @@ impl FromStr for Spheres {
     }
 }
 
+/// Scale `weights` so that they sum to 100, keeping their ratios rather than rounding them.
+///
+/// Returns None if `weights` do not have a positive, finite sum.
+fn scale_to_percentages(weights: &[f64]) -> Option<Vec<f64>> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 || !total.is_finite() {
+        return None;
+    }
+    Some(weights.iter().map(|w| w / total * 100.).collect())
+}
+
+/// Merge `blends` into a single Spheres struct, where each element of `blends` is a validated
+/// Spheres struct and the relative weight its proportions should be given.
+///
+/// Each proportion is scaled by the weight of the Spheres it came from before all proportions are
+/// re-normalized to sum to 100, without rounding, so that a Spheres given a small weight keeps its
+/// share. Sphere names must be unique across all of `blends`.
+pub fn merge<I>(blends: I) -> Result<Spheres, ParsingError>
+where
+    I: IntoIterator<Item = (Spheres, f64)>,
+{
+    let mut names = HashSet::new();
+    let mut merged = Vec::new();
+    let mut weights = Vec::new();
+    for (spheres, weight) in blends {
+        if !(weight.is_finite() && weight > 0.0) {
+            return Err(ParsingError::InvalidWeight);
+        }
+        for sphere in spheres.0 {
+            if !names.insert(sphere.name.clone()) {
+                return Err(ParsingError::DuplicateName(sphere.name));
+            }
+            weights.push(sphere.proportion * weight);
+            merged.push(sphere);
+        }
+    }
+    let proportions = scale_to_percentages(&weights).ok_or(ParsingError::InvalidProportions)?;
+    for (sphere, proportion) in merged.iter_mut().zip(proportions) {
+        sphere.proportion = proportion;
+    }
+    validate_normalized(SpheresRaw(merged))
+}
+
+/// Scale the proportions of `kept`, some of the spheres of a valid input, to sum to 100 again,
+/// keeping their ratios, and validate the result.
+fn renormalize(mut kept: Vec<ParsedSphere>) -> Result<Spheres, ParsingError> {
+    let weights: Vec<f64> = kept.iter().map(|s| s.proportion).collect();
+    let proportions = scale_to_percentages(&weights).ok_or(ParsingError::InvalidProportions)?;
+    for (sphere, proportion) in kept.iter_mut().zip(proportions) {
+        sphere.proportion = proportion;
+    }
+    validate_normalized(SpheresRaw(kept))
+}
+
+/// Normalize the proportions of `counted`, which are absolute counts of spheres of each type, into
+/// percentages and validate the result. If `integer` is set, every count must be a whole number.
+///
+/// The percentages keep the ratios of the counts exactly, so they needn't be whole numbers.
+fn normalize_counts(mut counted: SpheresRaw<f64>, integer: bool) -> Result<Spheres, ParsingError> {
+    if !counted
+        .0
+        .iter()
+        .all(|s| s.proportion.is_finite() && s.proportion >= 0.0)
+    {
+        return Err(ParsingError::InvalidCounts);
+    }
+    if let Some(s) = counted
+        .0
+        .iter()
+        .find(|s| integer && s.proportion.fract() != 0.0)
+    {
+        return Err(ParsingError::NonIntegerProportion(
+            s.name.clone(),
+            s.proportion,
+        ));
+    }
+    let counts: Vec<f64> = counted.0.iter().map(|s| s.proportion).collect();
+    let proportions = scale_to_percentages(&counts).ok_or(ParsingError::InvalidCounts)?;
+    for (sphere, proportion) in counted.0.iter_mut().zip(proportions) {
+        sphere.proportion = proportion;
+    }
+    validate_normalized(counted)
+}
+
+/// Check that the proportions of `fractions`, which are fractions of the spheres of each type, sum
+/// to 1 to within `epsilon`, and normalize them into percentages and validate the result.
+///
+/// Fractions such as 0.1 and 0.2 can't be represented exactly, so their sum is rarely exactly 1.
+fn normalize_fractions(fractions: SpheresRaw<f64>, epsilon: f64) -> Result<Spheres, ParsingError> {
+    if !fractions
+        .0
+        .iter()
+        .all(|s| s.proportion.is_finite() && s.proportion >= 0.0)
+    {
+        return Err(ParsingError::InvalidCounts);
+    }
+    let sum: f64 = fractions.0.iter().map(|s| s.proportion).sum();
+    if epsilon.is_nan() || (sum - 1.0).abs() >= epsilon {
+        return Err(ParsingError::InvalidFractions(sum, epsilon));
+    }
+    normalize_counts(fractions, false)
+}
+
 impl Spheres {
+    /// Read `reader` to completion and parse and validate its contents as `format`.
+    pub fn from_reader<R: Read>(reader: R, format: Format) -> Result<Spheres, ParsingError> {
+        validate(SpheresRaw::from_reader(reader, format)?)
+    }
+
+    /// Parse and validate JSON from `reader` incrementally, as `from_reader` does for JSON.
+    ///
+    /// This is for inputs too large to comfortably hold twice: rather than reading the whole input
+    /// and then every sphere before validating them, each sphere is validated as it's read and the
+    /// input text is never held whole. (`serde_json::StreamDeserializer` only separates whole
+    /// top-level values, so the elements of the list are visited one at a time instead.) Reading
+    /// stops at the first invalid sphere.
+    pub fn from_json_stream<R: Read>(reader: R) -> Result<Spheres, ParsingError> {
+        let mut validation = StreamingValidation::default();
+        let mut deserializer = serde_json::Deserializer::from_reader(io::BufReader::new(reader));
+        let read = deserializer
+            .deserialize_any(StreamingVisitor(&mut validation))
+            .and_then(|()| deserializer.end());
+        match (read, validation.error.take()) {
+            (_, Some(invalid)) => Err(invalid),
+            (Err(e), None) => Err(e.into()),
+            (Ok(()), None) => validation.finish(),
+        }
+    }
+
+    /// Read `reader` to completion and parse its contents as `format` without validating them, so
+    /// that proportions needn't sum to 100 and radii needn't be positive. Packing such spheres can
+    /// fail or give meaningless results; only non-finite radii are still rejected.
+    pub fn from_reader_unchecked<R: Read>(
+        reader: R,
+        format: Format,
+    ) -> Result<Spheres, ParsingError> {
+        unchecked(SpheresRaw::from_reader(reader, format)?)
+    }
+
+    /// Read `reader` to completion and parse its contents as `format`, treating each proportion as
+    /// an absolute count of spheres of that type which is normalized into a percentage.
+    ///
+    /// Counts can be fractional, as only their ratios matter, unless `integer` is set, in which
+    /// case any count which isn't a whole number is rejected. (Percentages read by `from_reader`
+    /// are always whole numbers.)
+    pub fn from_reader_counts<R: Read>(
+        reader: R,
+        format: Format,
+        integer: bool,
+    ) -> Result<Spheres, ParsingError> {
+        normalize_counts(SpheresRaw::from_reader(reader, format)?, integer)
+    }
+
+    /// Read `reader` to completion and parse its contents as `format`, treating each proportion as
+    /// the fraction of spheres of that type, which must sum to 1 to within `epsilon` and are then
+    /// normalized into percentages.
+    pub fn from_reader_fractions<R: Read>(
+        reader: R,
+        format: Format,
+        epsilon: f64,
+    ) -> Result<Spheres, ParsingError> {
+        normalize_fractions(SpheresRaw::from_reader(reader, format)?, epsilon)
+    }
+
+    /// Join `radii` and `proportions`, read separately, by name into validated spheres, in the
+    /// order of `radii`.
+    pub fn join(
+        radii: Vec<NamedRadius>,
+        proportions: Vec<NamedProportion>,
+    ) -> Result<Spheres, ParsingError> {
+        validate(join_by_name(radii, proportions)?)
+    }
+
+    /// Join `radii` and `proportions` by name as `join` does, without validating the result as
+    /// `from_reader_unchecked` does.
+    pub fn join_unchecked(
+        radii: Vec<NamedRadius>,
+        proportions: Vec<NamedProportion>,
+    ) -> Result<Spheres, ParsingError> {
+        unchecked(join_by_name(radii, proportions)?)
+    }
+
+    /// Join `radii` and `proportions` by name as `join` does, treating each proportion as an
+    /// absolute count of spheres of that type which is normalized into a percentage, and rejecting
+    /// any count which isn't a whole number if `integer` is set, as `from_reader_counts` does.
+    pub fn join_counts(
+        radii: Vec<NamedRadius>,
+        proportions: Vec<NamedProportion<f64>>,
+        integer: bool,
+    ) -> Result<Spheres, ParsingError> {
+        normalize_counts(join_by_name(radii, proportions)?, integer)
+    }
+
+    /// Join `radii` and `proportions` by name as `join` does, treating each proportion as a
+    /// fraction of the spheres which must sum to 1 to within `epsilon`, as `from_reader_fractions`
+    /// does.
+    pub fn join_fractions(
+        radii: Vec<NamedRadius>,
+        proportions: Vec<NamedProportion<f64>>,
+        epsilon: f64,
+    ) -> Result<Spheres, ParsingError> {
+        normalize_fractions(join_by_name(radii, proportions)?, epsilon)
+    }
+
+    /// Multiply every radius by `factor`, checking that each scaled radius is still positive and
+    /// finite once converted to the `f32` the packer works in, so that a tiny radius doesn't
+    /// silently underflow to 0.
+    pub fn scale(mut self, factor: f64) -> Result<Spheres, ParsingError> {
+        for sphere in &mut self.0 {
+            sphere.radius *= factor;
+            let packed = sphere.radius as f32;
+            if !(packed > 0.0 && packed.is_finite()) {
+                return Err(ParsingError::BadScale(factor, sphere.name.clone()));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Keep only the spheres with radii between `min` and `max` inclusive, re-normalizing their
+    /// proportions to sum to 100 in the same ratios.
+    pub fn filter_radius(self, min: f64, max: f64) -> Result<Spheres, ParsingError> {
+        let kept: Vec<ParsedSphere> = self
+            .0
+            .into_iter()
+            .filter(|s| min <= s.radius && s.radius <= max)
+            .collect();
+        if kept.is_empty() {
+            return Err(ParsingError::EmptyFilter(min, max));
+        }
+        renormalize(kept)
+    }
+
+    /// Split these spheres into those with radii of at least `threshold` and those with smaller
+    /// radii, re-normalizing the proportions of each to sum to 100.
+    pub fn split_at_radius(&self, threshold: f64) -> Result<(Spheres, Spheres), ParsingError> {
+        let (large, small): (Vec<ParsedSphere>, Vec<ParsedSphere>) =
+            self.0.iter().cloned().partition(|s| s.radius >= threshold);
+        if large.is_empty() || small.is_empty() {
+            return Err(ParsingError::EmptySplit(threshold));
+        }
+        Ok((renormalize(large)?, renormalize(small)?))
+    }
+
+    /// Check that the radius of every sphere is one of `allowed`, to within `tolerance` of it as a
+    /// fraction of the allowed radius, providing an error naming the first sphere whose isn't.
+    pub fn check_allowed_radii(&self, allowed: &[f64], tolerance: f64) -> Result<(), ParsingError> {
+        match self.iter().find(|s| {
+            !allowed
+                .iter()
+                .any(|a| (s.radius - a).abs() <= tolerance * a.abs())
+        }) {
+            Some(s) => Err(ParsingError::DisallowedRadius(s.name.clone(), s.radius)),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that there are at most `max` sphere types, since far more than any real mix has
+    /// usually means the input is malformed and would be slow to draw radii from.
+    pub fn check_max_types(&self, max: usize) -> Result<(), ParsingError> {
+        if self.0.len() > max {
+            Err(ParsingError::TooManyTypes(self.0.len(), max))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Provides the index of the sphere type whose radius is nearest to `radius`.
+    pub fn nearest_type(&self, radius: f64) -> usize {
+        self.0
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                (a.radius - radius)
+                    .abs()
+                    .partial_cmp(&(b.radius - radius).abs())
+                    .unwrap()
+            })
+            .map(|(i, _)| i)
+            .expect("Spheres are never empty")
+    }
+
+    /// Provides the smallest and largest radii of the spheres contained by this struct.
+    pub fn radius_range(&self) -> (f64, f64) {
+        self.iter().fold((f64::INFINITY, 0.), |(min, max), s| {
+            (min.min(s.radius), max.max(s.radius))
+        })
+    }
+
     /// Provides an iterator over the spheres contained by this struct.
-    pub(crate) fn iter(&self) -> impl Iterator<Item = &ParsedSphere> {
+    pub fn iter(&self) -> impl Iterator<Item = &ParsedSphere> {
         self.0.iter()
     }
 
-    pub(crate) fn avg_volume(&self) -> f64 {
+    /// Provides an iterator over the names of the spheres contained by this struct.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(ParsedSphere::name)
+    }
+
+    /// Provides the Shannon entropy, in nats, of the distribution of proportions, which is 0 for a
+    /// single sphere type and grows as proportions are spread over more types.
+    pub fn proportion_entropy(&self) -> f64 {
         self.0
             .iter()
-            .map(|s| 4.0 / 3.0 * PI * s.radius.powi(3) * (s.proportion as f64 / 100.))
+            .map(|s| s.proportion / 100.)
+            .filter(|&p| p > 0.)
+            .map(|p| p * (1. / p).ln())
             .sum()
     }
 
-    pub(crate) fn avg_surface_area(&self) -> f64 {
+    pub fn avg_volume(&self) -> f64 {
         self.0
             .iter()
-            .map(|s| 4.0 * PI * s.radius.powi(2) * (s.proportion as f64 / 100.))
+            .map(|s| 4.0 / 3.0 * PI * s.radius.powi(3) * (s.proportion / 100.))
             .sum()
     }
+
+    pub fn avg_surface_area(&self) -> f64 {
+        self.0
+            .iter()
+            .map(|s| 4.0 * PI * s.radius.powi(2) * (s.proportion / 100.))
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// A struct representing the radius of the sphere type `name`, read separately from its proportion.
+pub struct NamedRadius {
+    pub name: String,
+    pub radius: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// A struct representing the proportion of the sphere type `name`, read separately from its
+/// radius. Proportions may be read as types other than `u8` percentages, as for `ParsedSphere`.
+pub struct NamedProportion<P = u8> {
+    pub name: String,
+    pub proportion: P,
+}
+
+/// A struct representing a TOML document, which must be a table, listing radii.
+#[derive(Deserialize)]
+struct TomlRadii {
+    radii: Vec<NamedRadius>,
+}
+
+/// A struct representing a TOML document, which must be a table, listing proportions.
+#[derive(Deserialize)]
+#[serde(bound = "P: DeserializeOwned")]
+struct TomlProportions<P> {
+    proportions: Vec<NamedProportion<P>>,
+}
+
+/// Read `reader` to completion and parse its contents as `format` into a list of sphere types'
+/// radii, each with a `name` and a `radius`. TOML lists them under a `radii` key, and CSV needs a
+/// `name,radius` header row.
+pub fn read_radii<R: Read>(
+    mut reader: R,
+    format: Format,
+) -> Result<Vec<NamedRadius>, ParsingError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    Ok(match format {
+        Format::Json => serde_json::from_str(&contents)?,
+        Format::Yaml => from_yaml(&contents)?,
+        Format::Json5 => json5::from_str(&contents)?,
+        Format::Toml => toml::from_str::<TomlRadii>(&contents)?.radii,
+        Format::Csv => csv::Reader::from_reader(contents.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Read `reader` to completion and parse its contents as `format` into a list of sphere types'
+/// proportions, each with a `name` and a `proportion`. TOML lists them under a `proportions` key,
+/// and CSV needs a `name,proportion` header row.
+pub fn read_proportions<R: Read, P: DeserializeOwned>(
+    mut reader: R,
+    format: Format,
+) -> Result<Vec<NamedProportion<P>>, ParsingError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    Ok(match format {
+        Format::Json => serde_json::from_str(&contents)?,
+        Format::Yaml => from_yaml(&contents)?,
+        Format::Json5 => json5::from_str(&contents)?,
+        Format::Toml => toml::from_str::<TomlProportions<P>>(&contents)?.proportions,
+        Format::Csv => csv::Reader::from_reader(contents.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Join `radii` and `proportions` by name into the sphere types they describe, in the order of
+/// `radii`, providing an error naming every sphere type which is in only one of them.
+fn join_by_name<P>(
+    radii: Vec<NamedRadius>,
+    proportions: Vec<NamedProportion<P>>,
+) -> Result<SpheresRaw<P>, ParsingError> {
+    let mut by_name = HashMap::new();
+    for named in proportions {
+        if by_name.contains_key(&named.name) {
+            return Err(ParsingError::RepeatedName(named.name, "proportions"));
+        }
+        by_name.insert(named.name, named.proportion);
+    }
+    let mut names = HashSet::new();
+    let (mut joined, mut without_proportions) = (Vec::new(), Vec::new());
+    for named in radii {
+        if !names.insert(named.name.clone()) {
+            return Err(ParsingError::RepeatedName(named.name, "radii"));
+        }
+        match by_name.remove(&named.name) {
+            Some(proportion) => joined.push(ParsedSphere {
+                name: named.name,
+                radius: named.radius,
+                proportion,
+                min_count: None,
+                density: None,
+            }),
+            None => without_proportions.push(named.name),
+        }
+    }
+    let mut without_radii: Vec<String> = by_name.into_keys().collect();
+    without_radii.sort();
+    if without_proportions.is_empty() && without_radii.is_empty() {
+        Ok(SpheresRaw(joined))
+    } else {
+        Err(ParsingError::MismatchedNames(
+            without_proportions,
+            without_radii,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// A struct representing a sphere already placed at a position, such as one from a previous
+/// packing.
+pub struct PlacedSphere {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub radius: f32,
+}
+
+/// Deepest nesting of YAML flow collections which is parsed, which is the most `serde_yaml` will
+/// deserialize.
+const MAX_YAML_DEPTH: usize = 128;
+
+/// Parse `contents` as YAML, first checking that its flow collections (`[...]` and `{...}`) aren't
+/// nested more than `MAX_YAML_DEPTH` deep, as the time taken to parse them grows with the square
+/// of their depth.
+///
+/// Brackets inside quoted strings are counted too, which only matters for inputs with implausibly
+/// many brackets in sphere names.
+fn from_yaml<T: DeserializeOwned>(contents: &str) -> Result<T, ParsingError> {
+    let mut depth: usize = 0;
+    for c in contents.chars() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if depth > MAX_YAML_DEPTH {
+            return Err(ParsingError::TooDeep(MAX_YAML_DEPTH));
+        }
+    }
+    Ok(serde_yaml::from_str(contents)?)
+}
+
+/// A struct representing a TOML document, which must be a table, listing placed spheres.
+#[derive(Deserialize)]
+struct TomlPositions {
+    spheres: Vec<PlacedSphere>,
+}
+
+/// Read `reader` to completion and parse its contents as `format` into a list of placed spheres,
+/// each with an `x`, `y`, `z` and `radius`. This accepts the output of `--output-format
+/// csv-spheres` as CSV.
+pub fn read_positions<R: Read>(
+    mut reader: R,
+    format: Format,
+) -> Result<Vec<PlacedSphere>, ParsingError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    let placed: Vec<PlacedSphere> = match format {
+        Format::Json => serde_json::from_str(&contents)?,
+        Format::Yaml => from_yaml(&contents)?,
+        Format::Json5 => json5::from_str(&contents)?,
+        Format::Toml => toml::from_str::<TomlPositions>(&contents)?.spheres,
+        Format::Csv => csv::Reader::from_reader(contents.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()?,
+    };
+    let finite = |s: &PlacedSphere| [s.x, s.y, s.z, s.radius].iter().all(|c| c.is_finite());
+    if !placed.iter().all(finite) {
+        Err(ParsingError::NonFinite)
+    } else if placed.iter().all(|s| s.radius > 0.0) {
+        Ok(placed)
+    } else {
+        Err(ParsingError::NonPositive)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// A struct representing the half-space of points `p` with `normal · p <= offset`, one of the
+/// constraints bounding a convex polyhedron.
+pub struct HalfSpace {
+    pub normal: [f64; 3],
+    pub offset: f64,
+}
+
+/// A struct representing one row of a CSV file of half-spaces, which can't hold arrays.
+#[derive(Deserialize)]
+struct HalfSpaceRow {
+    nx: f64,
+    ny: f64,
+    nz: f64,
+    offset: f64,
+}
+
+/// A struct representing a TOML document, which must be a table, listing half-spaces.
+#[derive(Deserialize)]
+struct TomlHalfSpaces {
+    planes: Vec<HalfSpace>,
+}
+
+/// Read `reader` to completion and parse its contents as `format` into a list of half-spaces, each
+/// with a `normal` and an `offset`. TOML lists them under a `planes` key, and CSV needs an
+/// `nx,ny,nz,offset` header row naming the normal's components and the offset.
+pub fn read_half_spaces<R: Read>(
+    mut reader: R,
+    format: Format,
+) -> Result<Vec<HalfSpace>, ParsingError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    let planes: Vec<HalfSpace> = match format {
+        Format::Json => serde_json::from_str(&contents)?,
+        Format::Yaml => from_yaml(&contents)?,
+        Format::Json5 => json5::from_str(&contents)?,
+        Format::Toml => toml::from_str::<TomlHalfSpaces>(&contents)?.planes,
+        Format::Csv => csv::Reader::from_reader(contents.as_bytes())
+            .deserialize()
+            .map(|row| {
+                row.map(|r: HalfSpaceRow| HalfSpace {
+                    normal: [r.nx, r.ny, r.nz],
+                    offset: r.offset,
+                })
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    let finite = |h: &HalfSpace| {
+        h.normal
+            .iter()
+            .chain([h.offset].iter())
+            .all(|c| c.is_finite())
+    };
+    if planes.iter().all(finite) {
+        Ok(planes)
+    } else {
+        Err(ParsingError::NonFinite)
+    }
+}
+
+/// A struct representing several scenarios of proportions for the same sphere types, given as
+/// parallel arrays with an array of proportions for each scenario.
+#[derive(Deserialize)]
+struct ScenarioColumns {
+    names: Vec<String>,
+    radii: Vec<f64>,
+    proportions: Vec<Vec<serde_json::Number>>,
+    #[serde(default)]
+    min_counts: Option<Vec<Option<u64>>>,
+    #[serde(default)]
+    densities: Option<Vec<Option<f64>>>,
+}
+
+/// Read `reader` to completion and parse its contents as `format` into the proportions scenarios
+/// they give, if they give several: an object (or TOML table) of parallel arrays whose
+/// `proportions` is an array of arrays, one for each scenario, sharing the other arrays.
+///
+/// Each scenario is provided as the JSON text of an object of parallel arrays with that scenario's
+/// proportions, so that it can be read and validated as any JSON input is. Providing None means the
+/// contents don't give scenarios, so are likely an ordinary input instead; CSV can't give them.
+pub fn read_scenarios<R: Read>(
+    mut reader: R,
+    format: Format,
+) -> Result<Option<Vec<String>>, ParsingError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    let columns: Option<ScenarioColumns> = match format {
+        Format::Json => serde_json::from_str(&contents).ok(),
+        Format::Yaml => from_yaml(&contents).ok(),
+        Format::Json5 => json5::from_str(&contents).ok(),
+        Format::Toml => toml::from_str(&contents).ok(),
+        Format::Csv => None,
+    };
+    let columns = match columns {
+        Some(columns) if !columns.proportions.is_empty() => columns,
+        _ => return Ok(None),
+    };
+    let scenarios = columns
+        .proportions
+        .iter()
+        .map(|proportions| {
+            let mut scenario = serde_json::json!({
+                "names": columns.names,
+                "radii": columns.radii,
+                "proportions": proportions,
+            });
+            if let Some(min_counts) = &columns.min_counts {
+                scenario["min_counts"] = serde_json::json!(min_counts);
+            }
+            if let Some(densities) = &columns.densities {
+                scenario["densities"] = serde_json::json!(densities);
+            }
+            scenario.to_string()
+        })
+        .collect();
+    Ok(Some(scenarios))
+}
+
+/// Number of sphere types a continuous radius distribution is bucketed into for reporting.
+pub const DISTRIBUTION_BUCKETS: usize = 10;
+
+/// Standard normal quantile beyond which no radius is drawn from a distribution, well past those of
+/// the most extreme numbers a generator draws.
+const MAX_NORMAL_QUANTILE: f64 = 9.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "distribution", rename_all = "lowercase", deny_unknown_fields)]
+/// An enumeration of the continuous distributions radii can be drawn from instead of from discrete
+/// sphere types.
+pub enum RadiusDistribution {
+    /// Radii whose logarithms are normally distributed, with a mean radius of `mean` and a standard
+    /// deviation of `sigma` for their logarithms
+    Lognormal { mean: f64, sigma: f64 },
+    /// Radii uniformly distributed between `min` and `max`
+    Uniform { min: f64, max: f64 },
+}
+
+impl RadiusDistribution {
+    /// Provides the name the distribution is given by in inputs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RadiusDistribution::Lognormal { .. } => "lognormal",
+            RadiusDistribution::Uniform { .. } => "uniform",
+        }
+    }
+
+    /// Check that the parameters of this distribution describe positive radii which are finite at
+    /// single precision, providing what is wrong with them if they don't.
+    pub fn validate(&self) -> Result<(), ParsingError> {
+        let invalid = |reason| Err(ParsingError::InvalidDistribution(self.name(), reason));
+        match *self {
+            RadiusDistribution::Lognormal { mean, sigma } => {
+                if !(mean > 0. && mean.is_finite()) {
+                    return invalid("mean must be positive and finite");
+                }
+                if !(sigma >= 0. && sigma.is_finite()) {
+                    return invalid("sigma must be non-negative and finite");
+                }
+                let tails = [-MAX_NORMAL_QUANTILE, MAX_NORMAL_QUANTILE]
+                    .map(|z| (mean.ln() - sigma.powi(2) / 2. + sigma * z).exp() as f32);
+                if !tails.iter().all(|r| r.is_normal()) {
+                    return invalid("sigma is too large for the radii drawn to be finite");
+                }
+            }
+            RadiusDistribution::Uniform { min, max } => {
+                if !(min > 0. && (min as f32).is_finite()) {
+                    return invalid("min must be positive and finite");
+                }
+                if !(max >= min && (max as f32).is_finite()) {
+                    return invalid("max must be finite and at least min");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Provides the radius which a fraction `p`, in `(0, 1)`, of the radii drawn from this
+    /// distribution are smaller than.
+    pub fn quantile(&self, p: f64) -> f64 {
+        match *self {
+            RadiusDistribution::Lognormal { mean, sigma } => {
+                let z =
+                    packing::normal_quantile(p).clamp(-MAX_NORMAL_QUANTILE, MAX_NORMAL_QUANTILE);
+                (mean.ln() - sigma.powi(2) / 2. + sigma * z).exp()
+            }
+            RadiusDistribution::Uniform { min, max } => min + p * (max - min),
+        }
+    }
+
+    /// Provides the sphere types radii drawn from this distribution are reported in: one for each
+    /// of `DISTRIBUTION_BUCKETS` equally likely ranges of radii, named `bucket-1` for the smallest
+    /// up, with the median radius of its range and an equal proportion.
+    pub fn buckets(&self) -> Spheres {
+        let n = DISTRIBUTION_BUCKETS;
+        let proportions = scale_to_percentages(&vec![1.; n]).expect("the bucket weights sum to n");
+        Spheres(
+            proportions
+                .into_iter()
+                .enumerate()
+                .map(|(k, proportion)| ParsedSphere {
+                    name: format!("bucket-{}", k + 1),
+                    radius: self.quantile((k as f64 + 0.5) / n as f64),
+                    proportion,
+                    min_count: None,
+                    density: None,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Read `reader` to completion and parse its contents as `format` into a continuous radius
+/// distribution, if it is one: an object (or TOML table) with a `distribution` property naming it,
+/// along with its parameters. Providing None means the contents aren't a distribution, so are
+/// likely sphere types instead; CSV can't give a distribution.
+pub fn read_distribution<R: Read>(
+    mut reader: R,
+    format: Format,
+) -> Result<Option<RadiusDistribution>, ParsingError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    type Probe = HashMap<String, de::IgnoredAny>;
+    let probe: Option<Probe> = match format {
+        Format::Json => serde_json::from_str(&contents).ok(),
+        Format::Yaml => from_yaml(&contents).ok(),
+        Format::Json5 => json5::from_str(&contents).ok(),
+        Format::Toml => toml::from_str(&contents).ok(),
+        Format::Csv => None,
+    };
+    if !probe.is_some_and(|properties| properties.contains_key("distribution")) {
+        return Ok(None);
+    }
+    let distribution: RadiusDistribution = match format {
+        Format::Json => serde_json::from_str(&contents)?,
+        Format::Yaml => from_yaml(&contents)?,
+        Format::Json5 => json5::from_str(&contents)?,
+        Format::Toml => toml::from_str(&contents)?,
+        Format::Csv => unreachable!("CSV is never probed as a distribution"),
+    };
+    distribution.validate()?;
+    Ok(Some(distribution))
 }
 
 #[cfg(test)]
 mod test {
-    use crate::parsing::{validate, ParsedSphere, ParsingError, Spheres, SpheresRaw};
+    use crate::parsing::{
+        merge, percentages, read_distribution, read_half_spaces, read_positions, read_proportions,
+        read_radii, read_scenarios, validate, Format, HalfSpace, NamedProportion, ParsedSphere,
+        ParsingError, PlacedSphere, RadiusDistribution, Spheres, SpheresRaw,
+    };
 
     static VALID: &str = r#"
 [
@@ -121,11 +1328,15 @@ mod test {
                 name: String::from("5_micron_Al"),
                 radius: 5.0,
                 proportion: 66,
+                min_count: None,
+                density: None,
             },
             ParsedSphere {
                 name: String::from("400_AP"),
                 radius: 400.0,
                 proportion: 34,
+                min_count: None,
+                density: None,
             },
         ])
     }
@@ -135,12 +1346,16 @@ mod test {
             ParsedSphere {
                 name: String::from("5_micron_Al"),
                 radius: 5.0,
-                proportion: 66,
+                proportion: 66.,
+                min_count: None,
+                density: None,
             },
             ParsedSphere {
                 name: String::from("400_AP"),
                 radius: 400.0,
-                proportion: 34,
+                proportion: 34.,
+                min_count: None,
+                density: None,
             },
         ])
     }
@@ -165,11 +1380,15 @@ mod test {
                 name: String::from("5_micron_Al"),
                 radius: 5.0,
                 proportion: 66,
+                min_count: None,
+                density: None,
             },
             ParsedSphere {
                 name: String::from("400_AP"),
                 radius: 400.0,
                 proportion: 32,
+                min_count: None,
+                density: None,
             },
         ])
     }
@@ -197,6 +1416,20 @@ mod test {
     ]
     "#;
 
+    static COLUMNS: &str = r#"
+{
+  "names": ["5_micron_Al", "400_AP"],
+  "radii": [5.0, 400],
+  "proportions": [66, 34]
+}"#;
+
+    static MISMATCHED_COLUMNS: &str = r#"
+{
+  "names": ["5_micron_Al", "400_AP"],
+  "radii": [5.0],
+  "proportions": [66, 34]
+}"#;
+
     #[test]
     fn parse_well_formed() {
         assert_eq!(valid_spheres_raw(), VALID.parse().unwrap())
@@ -228,6 +1461,19 @@ mod test {
         ))
     }
 
+    #[test]
+    fn unchecked_spheres() {
+        let unchecked = Spheres::from_reader_unchecked(INVALID.as_bytes(), Format::Json).unwrap();
+        assert_eq!(percentages(invalid_spheres()).0, unchecked.0);
+        let non_positive = r#"[{"name": "a", "radius": -1, "proportion": 0}]"#;
+        assert!(Spheres::from_reader_unchecked(non_positive.as_bytes(), Format::Json).is_ok());
+        let infinite = r#"[{"name": "a", "radius": 1e39, "proportion": 100}]"#;
+        assert!(matches!(
+            Spheres::from_reader_unchecked(infinite.as_bytes(), Format::Json),
+            Err(ParsingError::NonFinite)
+        ));
+    }
+
     #[test]
     fn try_into_valid() {
         assert_eq!(valid_spheres(), VALID.parse().unwrap());
@@ -249,6 +1495,378 @@ mod test {
         ))
     }
 
+    #[test]
+    fn parse_columns() {
+        assert_eq!(valid_spheres_raw(), COLUMNS.parse().unwrap());
+        assert_eq!(valid_spheres(), COLUMNS.parse().unwrap());
+    }
+
+    #[test]
+    fn parse_mismatched_columns() {
+        assert!(matches!(
+            MISMATCHED_COLUMNS.parse::<SpheresRaw>(),
+            Err(ParsingError::FailedToParse(_))
+        ))
+    }
+
+    #[test]
+    fn parse_min_counts() {
+        let listed = r#"[
+            {"name": "small", "radius": 1, "proportion": 90},
+            {"name": "large", "radius": 3, "proportion": 10, "min_count": 20}
+        ]"#;
+        let columns = r#"{"names": ["small", "large"], "radii": [1, 3],
+            "proportions": [90, 10], "min_counts": [null, 20]}"#;
+        let csv = "name,radius,proportion,min_count\nsmall,1,90,\nlarge,3,10,20\n";
+        for spheres in [
+            listed.parse::<Spheres>().unwrap(),
+            columns.parse().unwrap(),
+            Spheres::from_reader(csv.as_bytes(), Format::Csv).unwrap(),
+        ] {
+            assert_eq!(
+                vec![None, Some(20)],
+                spheres
+                    .iter()
+                    .map(ParsedSphere::min_count)
+                    .collect::<Vec<_>>()
+            );
+        }
+        let mismatched = r#"{"names": ["a"], "radii": [1], "proportions": [100],
+            "min_counts": [1, 2]}"#;
+        assert!(mismatched.parse::<Spheres>().is_err());
+    }
+
+    #[test]
+    fn streams_json() {
+        assert_eq!(
+            valid_spheres(),
+            Spheres::from_json_stream(VALID.as_bytes()).unwrap()
+        );
+        let columns = r#"{"names": ["a", "b"], "radii": [1, 2], "proportions": [60, 40]}"#;
+        assert_eq!(
+            columns.parse::<Spheres>().unwrap(),
+            Spheres::from_json_stream(columns.as_bytes()).unwrap()
+        );
+        let many: Vec<String> = (0..10_000)
+            .map(|i| {
+                format!(
+                    r#"{{"name": "s{}", "radius": {}, "proportion": 0}}"#,
+                    i,
+                    i + 1
+                )
+            })
+            .chain([String::from(
+                r#"{"name": "last", "radius": 1, "proportion": 100}"#,
+            )])
+            .collect();
+        let many = format!("[{}]", many.join(","));
+        assert_eq!(
+            many.parse::<Spheres>().unwrap(),
+            Spheres::from_json_stream(many.as_bytes()).unwrap()
+        );
+        assert!(matches!(
+            Spheres::from_json_stream(INVALID.as_bytes()),
+            Err(ParsingError::InvalidProportions)
+        ));
+        // Reading stops as soon as the proportions are over 100, before the malformed rest.
+        assert!(matches!(
+            Spheres::from_json_stream(
+                r#"[{"name": "a", "radius": 1, "proportion": 101}, {"#.as_bytes()
+            ),
+            Err(ParsingError::InvalidProportions)
+        ));
+        let repeated = r#"[{"name": "a", "radius": 1, "proportion": 50},
+            {"name": "a", "radius": 2, "proportion": 50}]"#;
+        for result in [
+            repeated.parse::<Spheres>(),
+            Spheres::from_json_stream(repeated.as_bytes()),
+        ] {
+            assert!(
+                matches!(result, Err(ParsingError::RepeatedName(name, "input")) if name == "a")
+            );
+        }
+        assert!(matches!(
+            Spheres::from_json_stream(
+                r#"[{"name": "a", "radius": -1, "proportion": 100}]"#.as_bytes()
+            ),
+            Err(ParsingError::NonPositive)
+        ));
+        assert!(matches!(
+            Spheres::from_json_stream(format!("{} []", VALID).as_bytes()),
+            Err(ParsingError::FailedToParse(_))
+        ));
+    }
+
+    #[test]
+    fn parse_densities() {
+        let listed = r#"[
+            {"name": "al", "radius": 1, "proportion": 20, "density": 2.7},
+            {"name": "ap", "radius": 3, "proportion": 80}
+        ]"#;
+        let columns = r#"{"names": ["al", "ap"], "radii": [1, 3],
+            "proportions": [20, 80], "densities": [2.7, null]}"#;
+        let csv = "name,radius,proportion,density\nal,1,20,2.7\nap,3,80,\n";
+        for spheres in [
+            listed.parse::<Spheres>().unwrap(),
+            columns.parse().unwrap(),
+            Spheres::from_reader(csv.as_bytes(), Format::Csv).unwrap(),
+        ] {
+            assert_eq!(
+                vec![Some(2.7), None],
+                spheres
+                    .iter()
+                    .map(ParsedSphere::density)
+                    .collect::<Vec<_>>()
+            );
+        }
+        for density in ["0", "-1.5"] {
+            let json = format!(
+                r#"[{{"name": "a", "radius": 1, "proportion": 100, "density": {}}}]"#,
+                density
+            );
+            assert!(matches!(
+                json.parse::<Spheres>(),
+                Err(ParsingError::InvalidDensity(name, _)) if name == "a"
+            ));
+        }
+    }
+
+    #[test]
+    fn join_radii_and_proportions() {
+        let radii = read_radii("name,radius\nsmall,1\nlarge,3\n".as_bytes(), Format::Csv).unwrap();
+        let proportions: Vec<NamedProportion> = read_proportions(
+            r#"[{"name": "large", "proportion": 10}, {"name": "small", "proportion": 90}]"#
+                .as_bytes(),
+            Format::Json,
+        )
+        .unwrap();
+        let expected: Spheres = r#"[
+            {"name": "small", "radius": 1, "proportion": 90},
+            {"name": "large", "radius": 3, "proportion": 10}
+        ]"#
+        .parse()
+        .unwrap();
+        assert_eq!(expected, Spheres::join(radii.clone(), proportions).unwrap());
+        let counts = read_proportions(
+            "[[proportions]]\nname = \"small\"\nproportion = 900.0\n\
+             [[proportions]]\nname = \"large\"\nproportion = 100.0\n"
+                .as_bytes(),
+            Format::Toml,
+        )
+        .unwrap();
+        assert_eq!(
+            expected,
+            Spheres::join_counts(radii.clone(), counts, false).unwrap()
+        );
+
+        let mismatched = vec![
+            NamedProportion {
+                name: String::from("small"),
+                proportion: 90,
+            },
+            NamedProportion {
+                name: String::from("medium"),
+                proportion: 10,
+            },
+        ];
+        match Spheres::join(radii.clone(), mismatched) {
+            Err(e @ ParsingError::MismatchedNames(..)) => assert_eq!(
+                "radii and proportions name different spheres: radii without a proportion: \
+                 large; proportions without a radius: medium",
+                e.to_string()
+            ),
+            other => panic!("expected mismatched names, got {:?}", other),
+        }
+        let repeated = vec![radii[0].clone(), radii[0].clone()];
+        assert!(matches!(
+            Spheres::join(repeated, Vec::new()),
+            Err(ParsingError::RepeatedName(_, "radii"))
+        ));
+    }
+
+    #[test]
+    fn merge_weighted() {
+        let metal = r#"[{"name": "5_micron_Al", "radius": 5.0, "proportion": 100}]"#;
+        let oxidizer = r#"[{"name": "400_AP", "radius": 400, "proportion": 100}]"#;
+        let merged = merge(vec![
+            (metal.parse().unwrap(), 2.0),
+            (oxidizer.parse().unwrap(), 1.0),
+        ])
+        .unwrap();
+        let proportions: Vec<f64> = merged.iter().map(|s| s.proportion).collect();
+        assert!((proportions[0] - 200. / 3.).abs() < 1e-9);
+        assert!((proportions[1] - 100. / 3.).abs() < 1e-9);
+        // A file blended in at a small weight isn't rounded away.
+        let trace = merge(vec![
+            (metal.parse().unwrap(), 1.0),
+            (oxidizer.parse().unwrap(), 0.004),
+        ])
+        .unwrap();
+        let trace = trace.iter().last().unwrap().proportion();
+        assert!((trace - 0.4 / 1.004).abs() < 1e-9);
+    }
+
+    #[test]
+    fn merge_duplicate_names() {
+        assert!(matches!(
+            merge(vec![(valid_spheres(), 1.0), (valid_spheres(), 1.0)]),
+            Err(ParsingError::DuplicateName(name)) if name == "5_micron_Al"
+        ))
+    }
+
+    #[test]
+    fn split_at_radius() {
+        let three: Spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 20},
+            {"name": "medium", "radius": 5.0, "proportion": 20},
+            {"name": "large", "radius": 10.0, "proportion": 60}
+        ]"#
+        .parse()
+        .unwrap();
+        let (large, small) = three.split_at_radius(5.).unwrap();
+        let described = |spheres: &Spheres| {
+            spheres
+                .iter()
+                .map(|s| (s.name().to_string(), s.proportion()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(String::from("medium"), 25.), (String::from("large"), 75.)],
+            described(&large)
+        );
+        assert_eq!(vec![(String::from("small"), 100.)], described(&small));
+        assert!(matches!(
+            three.split_at_radius(0.5),
+            Err(ParsingError::EmptySplit(_))
+        ));
+    }
+
+    #[test]
+    fn filter_radius() {
+        let three = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 20},
+            {"name": "medium", "radius": 5.0, "proportion": 20},
+            {"name": "large", "radius": 10.0, "proportion": 60}
+        ]"#;
+        let filtered = three
+            .parse::<Spheres>()
+            .unwrap()
+            .filter_radius(2.0, 10.0)
+            .unwrap();
+        assert_eq!(
+            vec![("medium", 25.), ("large", 75.)],
+            filtered
+                .iter()
+                .map(|s| (s.name.as_str(), s.proportion))
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            three.parse::<Spheres>().unwrap().filter_radius(11.0, 20.0),
+            Err(ParsingError::EmptyFilter(_, _))
+        ));
+        // The kept proportions stay in their input ratio of 33 to 34, which whole percentages can't
+        // give.
+        let thirds: Spheres = r#"[
+            {"name": "small", "radius": 1.0, "proportion": 33},
+            {"name": "medium", "radius": 5.0, "proportion": 33},
+            {"name": "large", "radius": 10.0, "proportion": 34}
+        ]"#
+        .parse()
+        .unwrap();
+        let (large, _) = thirds.split_at_radius(5.).unwrap();
+        let filtered = thirds.filter_radius(2.0, 10.0).unwrap();
+        for kept in [large, filtered] {
+            let proportions: Vec<f64> = kept.iter().map(|s| s.proportion).collect();
+            assert!((proportions[0] - 3300. / 67.).abs() < 1e-9);
+            assert!((proportions[1] - 3400. / 67.).abs() < 1e-9);
+        }
+    }
+
+    fn read(input: &str, format: Format) -> Result<Spheres, ParsingError> {
+        Spheres::from_reader(input.as_bytes(), format)
+    }
+
+    #[test]
+    fn read_formats() {
+        let yaml = "
+- name: 5_micron_Al
+  radius: 5.0
+  proportion: 66
+- name: 400_AP
+  radius: 400
+  proportion: 34
+";
+        let toml = r#"
+[[spheres]]
+name = "5_micron_Al"
+radius = 5.0
+proportion = 66
+
+[[spheres]]
+name = "400_AP"
+radius = 400.0
+proportion = 34
+"#;
+        let toml_columns = r#"
+names = ["5_micron_Al", "400_AP"]
+radii = [5.0, 400.0]
+proportions = [66, 34]
+"#;
+        let csv = "name,radius,proportion\n5_micron_Al,5.0,66\n400_AP,400,34\n";
+        let json5 = "[{name: '5_micron_Al', radius: 5, proportion: 66}, \
+                     {name: '400_AP', radius: 400, proportion: 34,},]";
+        assert_eq!(valid_spheres(), read(VALID, Format::Json).unwrap());
+        assert_eq!(valid_spheres(), read(yaml, Format::Yaml).unwrap());
+        assert_eq!(valid_spheres(), read(toml, Format::Toml).unwrap());
+        assert_eq!(valid_spheres(), read(toml_columns, Format::Toml).unwrap());
+        assert_eq!(valid_spheres(), read(csv, Format::Csv).unwrap());
+        assert_eq!(valid_spheres(), read(json5, Format::Json5).unwrap());
+    }
+
+    #[test]
+    fn read_invalid_format() {
+        assert!(matches!(
+            read(INVALID, Format::Json),
+            Err(ParsingError::InvalidProportions)
+        ));
+        assert!(matches!(
+            read("name,radius\na,1.0\n", Format::Csv),
+            Err(ParsingError::FailedToParseCsv(_))
+        ));
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Some(Format::Yaml), Format::from_extension("spheres.yml"));
+        assert_eq!(Some(Format::Csv), Format::from_extension("dir/spheres.CSV"));
+        assert_eq!(None, Format::from_extension("spheres"));
+        assert_eq!(None, Format::from_extension("spheres.txt"));
+        assert!(matches!(
+            "xml".parse::<Format>(),
+            Err(ParsingError::UnknownFormat(_))
+        ));
+        for format in Format::ALL {
+            assert_eq!(format, format.name().parse().unwrap());
+            for alias in format.aliases() {
+                assert_eq!(format, alias.to_uppercase().parse().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn proportion_entropy() {
+        let even = r#"[
+            {"name": "a", "radius": 1.0, "proportion": 50},
+            {"name": "b", "radius": 2.0, "proportion": 50}
+        ]"#;
+        let single = r#"[{"name": "a", "radius": 1.0, "proportion": 100}]"#;
+        let entropy = |json: &str| json.parse::<Spheres>().unwrap().proportion_entropy();
+        assert!((entropy(even) - 2f64.ln()).abs() < 1e-12);
+        assert!(entropy(single).is_sign_positive() && entropy(single) == 0.);
+        let skewed = -(0.66f64 * 0.66f64.ln() + 0.34 * 0.34f64.ln());
+        assert!((valid_spheres().proportion_entropy() - skewed).abs() < 1e-12);
+    }
+
     #[test]
     fn negative_radius() {
         let neg_rad = NEG_RADIUS.parse();
@@ -262,4 +1880,450 @@ mod test {
             Err(ParsingError::NonPositive)
         ))
     }
+
+    #[test]
+    fn read_positions_formats() {
+        let expected = vec![
+            PlacedSphere {
+                x: 1.0,
+                y: -2.0,
+                z: 0.5,
+                radius: 3.0,
+            },
+            PlacedSphere {
+                x: -4.0,
+                y: 0.0,
+                z: 2.5,
+                radius: 1.5,
+            },
+        ];
+        let json = r#"[
+            {"x": 1.0, "y": -2.0, "z": 0.5, "radius": 3.0},
+            {"x": -4.0, "y": 0.0, "z": 2.5, "radius": 1.5}
+        ]"#;
+        let csv = "x,y,z,radius\n1,-2,0.5,3\n-4,0,2.5,1.5\n";
+        assert_eq!(
+            expected,
+            read_positions(json.as_bytes(), Format::Json).unwrap()
+        );
+        assert_eq!(
+            expected,
+            read_positions(csv.as_bytes(), Format::Csv).unwrap()
+        );
+        assert!(matches!(
+            read_positions("x,y,z,radius\n0,0,0,0\n".as_bytes(), Format::Csv),
+            Err(ParsingError::NonPositive)
+        ));
+    }
+
+    #[test]
+    fn half_spaces_formats() {
+        let expected = vec![
+            HalfSpace {
+                normal: [1., 0., 0.],
+                offset: 2.,
+            },
+            HalfSpace {
+                normal: [0., -1., 1.],
+                offset: 0.5,
+            },
+        ];
+        let json = r#"[{"normal": [1, 0, 0], "offset": 2}, {"normal": [0, -1, 1], "offset": 0.5}]"#;
+        let toml = "[[planes]]\nnormal = [1.0, 0.0, 0.0]\noffset = 2.0\n\
+                    [[planes]]\nnormal = [0.0, -1.0, 1.0]\noffset = 0.5\n";
+        let csv = "nx,ny,nz,offset\n1,0,0,2\n0,-1,1,0.5\n";
+        let read = |contents: &str, format| read_half_spaces(contents.as_bytes(), format).unwrap();
+        assert_eq!(expected, read(json, Format::Json));
+        assert_eq!(expected, read(json, Format::Json5));
+        assert_eq!(expected, read(json, Format::Yaml));
+        assert_eq!(expected, read(toml, Format::Toml));
+        assert_eq!(expected, read(csv, Format::Csv));
+    }
+
+    #[test]
+    fn non_finite_rejected() {
+        let json5 = "[{name: 'a', radius: Infinity, proportion: 100}]";
+        assert!(matches!(
+            Spheres::from_reader(json5.as_bytes(), Format::Json5),
+            Err(ParsingError::NonFinite)
+        ));
+        // Finite in double precision, but not once packed in single precision.
+        let huge = r#"[{"name": "a", "radius": 1e39, "proportion": 100}]"#;
+        assert!(matches!(
+            huge.parse::<Spheres>(),
+            Err(ParsingError::NonFinite)
+        ));
+        let yaml = "- {x: .nan, y: 0, z: 0, radius: 1}";
+        assert!(matches!(
+            read_positions(yaml.as_bytes(), Format::Yaml),
+            Err(ParsingError::NonFinite)
+        ));
+    }
+
+    #[test]
+    fn deep_yaml_rejected() {
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert!(matches!(
+            Spheres::from_reader(deep.as_bytes(), Format::Yaml),
+            Err(ParsingError::TooDeep(_))
+        ));
+        let flow = "[{name: a, radius: 1.0, proportion: 100}]";
+        assert!(Spheres::from_reader(flow.as_bytes(), Format::Yaml).is_ok());
+    }
+
+    #[test]
+    fn radius_range() {
+        assert_eq!((5., 400.), valid_spheres().radius_range());
+    }
+
+    #[test]
+    fn names() {
+        let spheres = valid_spheres();
+        assert_eq!(
+            vec!["5_micron_Al", "400_AP"],
+            spheres.names().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn name_accessor() {
+        let sphere = ParsedSphere {
+            name: String::from("400_AP"),
+            radius: 400.0,
+            proportion: 34.,
+            min_count: None,
+            density: None,
+        };
+        assert_eq!("400_AP", sphere.name());
+    }
+
+    #[test]
+    fn radius_notations_agree() {
+        let parse = |radius: &str| {
+            let json = format!(
+                r#"[{{"name": "a", "radius": {}, "proportion": 100}}]"#,
+                radius
+            );
+            json.parse::<Spheres>().unwrap()
+        };
+        for radius in ["5", "5.0", "5e0", "500e-2", "0.5E1"] {
+            assert_eq!(parse("5.0"), parse(radius), "radius {}", radius);
+        }
+        for radius in ["0", "0e3", "-5e0"] {
+            let json = format!(
+                r#"[{{"name": "a", "radius": {}, "proportion": 100}}]"#,
+                radius
+            );
+            assert!(matches!(
+                json.parse::<Spheres>(),
+                Err(ParsingError::NonPositive)
+            ));
+        }
+    }
+
+    #[test]
+    fn scale_revalidates() {
+        let scaled = valid_spheres().scale(2.).unwrap();
+        assert_eq!(
+            vec![10., 800.],
+            scaled.iter().map(|s| s.radius()).collect::<Vec<_>>()
+        );
+        let tiny = r#"[{"name": "tiny", "radius": 1e-30, "proportion": 100}]"#;
+        assert!(matches!(
+            tiny.parse::<Spheres>().unwrap().scale(1e-30),
+            Err(ParsingError::BadScale(_, name)) if name == "tiny"
+        ));
+        assert!(matches!(
+            valid_spheres().scale(1e300),
+            Err(ParsingError::BadScale(..))
+        ));
+        assert!(matches!(
+            valid_spheres().scale(-1.),
+            Err(ParsingError::BadScale(..))
+        ));
+    }
+
+    #[test]
+    fn allowed_radii() {
+        let spheres = valid_spheres();
+        assert!(spheres.check_allowed_radii(&[400., 5., 10.], 0.).is_ok());
+        assert!(spheres
+            .check_allowed_radii(&[5.000001, 399.9999], 1e-6)
+            .is_ok());
+        assert!(matches!(
+            spheres.check_allowed_radii(&[5.000001, 399.9999], 1e-9),
+            Err(ParsingError::DisallowedRadius(name, _)) if name == "5_micron_Al"
+        ));
+        assert!(matches!(
+            spheres.check_allowed_radii(&[5.], 1e-6),
+            Err(ParsingError::DisallowedRadius(name, radius)) if name == "400_AP" && radius == 400.
+        ));
+    }
+
+    #[test]
+    fn max_types() {
+        let spheres = valid_spheres();
+        assert!(spheres.check_max_types(2).is_ok());
+        assert!(matches!(
+            spheres.check_max_types(1),
+            Err(ParsingError::TooManyTypes(2, 1))
+        ));
+    }
+
+    #[test]
+    fn format_directive() {
+        let yaml = "# format: yaml\n- name: a\n  radius: 1.0\n  proportion: 100\n";
+        let (format, rest) = Format::split_directive(yaml).unwrap();
+        assert_eq!(Some(Format::Yaml), format);
+        assert_eq!(1, read(rest, Format::Yaml).unwrap().iter().count());
+        assert_eq!(
+            (Some(Format::Json), "[]"),
+            Format::split_directive("  #format:JSON  \r\n[]").unwrap()
+        );
+        assert_eq!((None, VALID), Format::split_directive(VALID).unwrap());
+        let comment = "# a comment\n- name: a\n";
+        assert_eq!((None, comment), Format::split_directive(comment).unwrap());
+        assert!(matches!(
+            Format::split_directive("# format: xml\n"),
+            Err(ParsingError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn distributions() {
+        let lognormal = r#"{"distribution": "lognormal", "mean": 10, "sigma": 0.3}"#;
+        let distribution = read_distribution(lognormal.as_bytes(), Format::Json)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            RadiusDistribution::Lognormal {
+                mean: 10.,
+                sigma: 0.3
+            },
+            distribution
+        );
+        // The median of a lognormal distribution is below its mean.
+        let median = 10. * (-0.3f64.powi(2) / 2.).exp();
+        assert!((distribution.quantile(0.5) - median).abs() < 1e-9);
+        let buckets = distribution.buckets();
+        assert_eq!(10, buckets.iter().count());
+        assert!(buckets.iter().all(|s| s.proportion() == 10.));
+        assert_eq!(Some("bucket-1"), buckets.names().next());
+        let radii: Vec<f64> = buckets.iter().map(|s| s.radius()).collect();
+        assert!(radii.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let toml = "distribution = \"uniform\"\nmin = 1.0\nmax = 3.0\n";
+        let uniform = read_distribution(toml.as_bytes(), Format::Toml)
+            .unwrap()
+            .unwrap();
+        assert_eq!(2., uniform.quantile(0.5));
+        assert_eq!(
+            vec![1.1, 1.3],
+            uniform
+                .buckets()
+                .iter()
+                .take(2)
+                .map(|s| (s.radius() * 1e9).round() / 1e9)
+                .collect::<Vec<_>>()
+        );
+
+        // Sphere types aren't a distribution.
+        assert_eq!(
+            None,
+            read_distribution(VALID.as_bytes(), Format::Json).unwrap()
+        );
+        let csv = "name,radius,proportion\na,1,100\n";
+        assert_eq!(
+            None,
+            read_distribution(csv.as_bytes(), Format::Csv).unwrap()
+        );
+        for invalid in [
+            r#"{"distribution": "lognormal", "mean": 0, "sigma": 0.3}"#,
+            r#"{"distribution": "lognormal", "mean": 10, "sigma": -1}"#,
+            r#"{"distribution": "lognormal", "mean": 10, "sigma": 100}"#,
+            r#"{"distribution": "uniform", "min": 2, "max": 1}"#,
+            r#"{"distribution": "uniform", "min": -1, "max": 1}"#,
+        ] {
+            assert!(matches!(
+                read_distribution(invalid.as_bytes(), Format::Json),
+                Err(ParsingError::InvalidDistribution(..))
+            ));
+        }
+        for unparseable in [
+            r#"{"distribution": "gamma", "shape": 2}"#,
+            r#"{"distribution": "uniform", "min": 1}"#,
+            r#"{"distribution": "uniform", "min": 1, "max": 2, "mean": 3}"#,
+        ] {
+            assert!(matches!(
+                read_distribution(unparseable.as_bytes(), Format::Json),
+                Err(ParsingError::FailedToParse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn scenarios() {
+        let input = r#"{
+            "names": ["a", "b"],
+            "radii": [1.0, 2.0],
+            "proportions": [[60, 40], [10, 90], [50, 60]],
+            "densities": [1.0, null]
+        }"#;
+        let scenarios = read_scenarios(input.as_bytes(), Format::Json)
+            .unwrap()
+            .unwrap();
+        assert_eq!(3, scenarios.len());
+        let first: Spheres = scenarios[0].parse().unwrap();
+        assert_eq!(
+            vec![60., 40.],
+            first.iter().map(|s| s.proportion()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(1.0), first.iter().next().unwrap().density());
+        // Each scenario is validated on its own.
+        assert!(matches!(
+            scenarios[2].parse::<Spheres>(),
+            Err(ParsingError::InvalidProportions)
+        ));
+        let toml =
+            "names = [\"a\", \"b\"]\nradii = [1.0, 2.0]\nproportions = [[1.5, 2.5], [3, 4]]\n";
+        let scenarios = read_scenarios(toml.as_bytes(), Format::Toml)
+            .unwrap()
+            .unwrap();
+        let counted = Spheres::from_reader_counts(scenarios[0].as_bytes(), Format::Json, false);
+        assert_eq!(
+            vec![37.5, 62.5],
+            counted
+                .unwrap()
+                .iter()
+                .map(|s| s.proportion())
+                .collect::<Vec<_>>()
+        );
+
+        // Ordinary inputs don't give scenarios.
+        assert_eq!(
+            None,
+            read_scenarios(VALID.as_bytes(), Format::Json).unwrap()
+        );
+        let columns = r#"{"names": ["a"], "radii": [1.0], "proportions": [100]}"#;
+        assert_eq!(
+            None,
+            read_scenarios(columns.as_bytes(), Format::Json).unwrap()
+        );
+    }
+
+    #[test]
+    fn schema_describes_both_shapes() {
+        let schema = serde_json::to_value(crate::parsing::input_schema()).unwrap();
+        let shapes = schema["anyOf"].as_array().unwrap();
+        assert_eq!(2, shapes.len());
+        assert_eq!("array", shapes[0]["type"]);
+        let sphere = &schema["$defs"]["ParsedSphere"];
+        assert_eq!(0, sphere["properties"]["radius"]["exclusiveMinimum"]);
+        assert_eq!(255, sphere["properties"]["proportion"]["maximum"]);
+        let columns = &schema["$defs"]["SpheresColumns"]["properties"];
+        assert_eq!(0, columns["radii"]["items"]["exclusiveMinimum"]);
+    }
+
+    #[test]
+    fn counts_normalized() {
+        let counts = r#"[
+            {"name": "a", "radius": 1.0, "proportion": 500},
+            {"name": "b", "radius": 2.0, "proportion": 300},
+            {"name": "c", "radius": 3.0, "proportion": 0}
+        ]"#;
+        let spheres = Spheres::from_reader_counts(counts.as_bytes(), Format::Json, false).unwrap();
+        assert_eq!(
+            vec![62.5, 37.5, 0.],
+            spheres.iter().map(|s| s.proportion()).collect::<Vec<_>>()
+        );
+        // A type with well under 1% of the spheres keeps its share rather than rounding to 0.
+        let rare = "name,radius,proportion\na,1.0,500\nb,2.0,300\nc,3.0,1\n";
+        let spheres = Spheres::from_reader_counts(rare.as_bytes(), Format::Csv, false).unwrap();
+        let c = spheres.iter().last().unwrap().proportion();
+        assert!((c - 100. / 801.).abs() < 1e-12);
+        let csv = "name,radius,proportion\na,1.0,2.5\nb,2.0,7.5\n";
+        let spheres = Spheres::from_reader_counts(csv.as_bytes(), Format::Csv, false).unwrap();
+        assert_eq!(
+            vec![25., 75.],
+            spheres.iter().map(|s| s.proportion()).collect::<Vec<_>>()
+        );
+        for invalid in [
+            r#"{"names": ["a", "b"], "radii": [1.0, 2.0], "proportions": [-1, 2]}"#,
+            r#"{"names": ["a", "b"], "radii": [1.0, 2.0], "proportions": [0, 0]}"#,
+        ] {
+            assert!(matches!(
+                Spheres::from_reader_counts(invalid.as_bytes(), Format::Json, false),
+                Err(ParsingError::InvalidCounts)
+            ));
+        }
+    }
+
+    #[test]
+    fn integer_counts() {
+        let whole = "name,radius,proportion\na,1.0,500\nb,2.0,300.0\n";
+        assert_eq!(
+            Spheres::from_reader_counts(whole.as_bytes(), Format::Csv, false).unwrap(),
+            Spheres::from_reader_counts(whole.as_bytes(), Format::Csv, true).unwrap()
+        );
+        let fractional = "name,radius,proportion\na,1.0,2.5\nb,2.0,7.5\n";
+        assert!(Spheres::from_reader_counts(fractional.as_bytes(), Format::Csv, false).is_ok());
+        assert!(matches!(
+            Spheres::from_reader_counts(fractional.as_bytes(), Format::Csv, true),
+            Err(ParsingError::NonIntegerProportion(name, p)) if name == "a" && p == 2.5
+        ));
+        let radii = read_radii("name,radius\na,1\nb,2\n".as_bytes(), Format::Csv).unwrap();
+        let counts = |text: &str| read_proportions(text.as_bytes(), Format::Csv).unwrap();
+        assert!(
+            Spheres::join_counts(radii.clone(), counts("name,proportion\na,3\nb,1\n"), true)
+                .is_ok()
+        );
+        assert!(matches!(
+            Spheres::join_counts(radii, counts("name,proportion\na,3\nb,0.5\n"), true),
+            Err(ParsingError::NonIntegerProportion(name, _)) if name == "b"
+        ));
+    }
+
+    #[test]
+    fn inexact_fractions() {
+        // Neither sums to exactly 1 in binary floating point.
+        assert_ne!(1.0, 0.7 + 0.2 + 0.1);
+        let inexact = "name,radius,proportion\na,1.0,0.7\nb,2.0,0.2\nc,3.0,0.1\n";
+        let spheres =
+            Spheres::from_reader_fractions(inexact.as_bytes(), Format::Csv, 1e-9).unwrap();
+        for (expected, sphere) in [70., 20., 10.].iter().zip(spheres.iter()) {
+            assert!((expected - sphere.proportion()).abs() < 1e-9);
+        }
+        assert!(matches!(
+            Spheres::from_reader_fractions(inexact.as_bytes(), Format::Csv, 0.0),
+            Err(ParsingError::InvalidFractions(_, epsilon)) if epsilon == 0.0
+        ));
+        let tenths = format!(
+            "name,radius,proportion\n{}",
+            (0..10)
+                .map(|i| format!("s{},{},0.1\n", i, i + 1))
+                .collect::<String>()
+        );
+        assert_ne!(1.0, [0.1; 10].iter().sum::<f64>());
+        let spheres = Spheres::from_reader_fractions(tenths.as_bytes(), Format::Csv, 1e-9).unwrap();
+        assert!(spheres.iter().all(|s| (s.proportion() - 10.).abs() < 1e-9));
+        // A fraction under half a percent survives rather than rounding to 0.
+        let lopsided = "name,radius,proportion\na,1.0,0.996\nb,2.0,0.004\n";
+        let spheres =
+            Spheres::from_reader_fractions(lopsided.as_bytes(), Format::Csv, 1e-9).unwrap();
+        let proportions: Vec<f64> = spheres.iter().map(|s| s.proportion()).collect();
+        assert!((proportions[0] - 99.6).abs() < 1e-9 && (proportions[1] - 0.4).abs() < 1e-9);
+        let entropy = -(0.996f64 * 0.996f64.ln() + 0.004 * 0.004f64.ln());
+        assert!((spheres.proportion_entropy() - entropy).abs() < 1e-9);
+        let volume = 4. / 3. * std::f64::consts::PI * (0.996 + 0.004 * 8.);
+        assert!((spheres.avg_volume() - volume).abs() < 1e-9);
+        let short = "name,radius,proportion\na,1.0,0.3\nb,2.0,0.6\n";
+        assert!(matches!(
+            Spheres::from_reader_fractions(short.as_bytes(), Format::Csv, 1e-9),
+            Err(ParsingError::InvalidFractions(sum, _)) if (sum - 0.9).abs() < 1e-12
+        ));
+        assert!(Spheres::from_reader_fractions(short.as_bytes(), Format::Csv, 0.2).is_ok());
+        let radii = read_radii("name,radius\na,1\nb,2\n".as_bytes(), Format::Csv).unwrap();
+        let fractions = read_proportions("name,proportion\na,0.3\nb,0.7\n".as_bytes(), Format::Csv);
+        assert!(Spheres::join_fractions(radii, fractions.unwrap(), 1e-9).is_ok());
+    }
 }
@@ -0,0 +1,97 @@
+//! Packs the sample inputs in `tests/fixtures` with fixed seeds and compares the summaries against
+//! the golden outputs checked in beside them, so that refactors which change what is packed are
+//! caught.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the golden outputs after an intended change, and review
+//! the difference before committing it.
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use sphere_pack_from_json::packing::{self, PackOptions, ProportionBasis};
+use sphere_pack_from_json::parsing::{Format, Spheres};
+
+/// Relative difference within which numbers in an output match the golden output, since the last
+/// digits of some metrics may differ between platforms.
+const TOLERANCE: f64 = 1e-9;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Pack the fixture `input` with `options` and compare the summary against the fixture `golden`.
+fn check_golden(input: &str, options: PackOptions, golden: &str) {
+    let path = fixture(input);
+    let format = Format::from_extension(&path).unwrap();
+    let spheres = Spheres::from_reader(File::open(&path).unwrap(), format).unwrap();
+    let output = packing::pack(&spheres, &options).unwrap();
+    let actual = serde_json::to_value(&output).unwrap();
+    let golden = fixture(golden);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let mut written = serde_json::to_string_pretty(&actual).unwrap();
+        written.push('\n');
+        fs::write(&golden, written).unwrap();
+        return;
+    }
+    let expected: Value = serde_json::from_reader(File::open(&golden).unwrap()).unwrap();
+    if let Err(at) = matches(&expected, &actual, String::from("$")) {
+        panic!(
+            "{} differs from {} at {}:\n{}",
+            input,
+            golden.display(),
+            at,
+            serde_json::to_string_pretty(&actual).unwrap()
+        );
+    }
+}
+
+/// Check that `actual` matches `expected` exactly, but for numbers within `TOLERANCE` of each
+/// other, providing the path to the first difference otherwise.
+fn matches(expected: &Value, actual: &Value, path: String) -> Result<(), String> {
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) => {
+            let (e, a) = (e.as_f64().unwrap(), a.as_f64().unwrap());
+            if (e - a).abs() <= TOLERANCE * e.abs().max(a.abs()) {
+                Ok(())
+            } else {
+                Err(format!("{} (expected {}, found {})", path, e, a))
+            }
+        }
+        (Value::Array(e), Value::Array(a)) if e.len() == a.len() => e
+            .iter()
+            .zip(a)
+            .enumerate()
+            .try_for_each(|(i, (e, a))| matches(e, a, format!("{}[{}]", path, i))),
+        (Value::Object(e), Value::Object(a)) if e.keys().eq(a.keys()) => e
+            .iter()
+            .try_for_each(|(key, e)| matches(e, &a[key], format!("{}.{}", path, key))),
+        (e, a) if e == a => Ok(()),
+        _ => Err(path),
+    }
+}
+
+#[test]
+fn bimodal() {
+    let options = PackOptions {
+        dims: Some([8., 8., 8.]),
+        seed: 7,
+        ..Default::default()
+    };
+    check_golden("bimodal.json", options, "bimodal.golden.json");
+}
+
+#[test]
+fn trimodal_by_volume() {
+    let options = PackOptions {
+        dims: Some([6., 6., 6.]),
+        seed: 11,
+        basis: ProportionBasis::Volume,
+        explain: true,
+        ..Default::default()
+    };
+    check_golden("trimodal.yaml", options, "trimodal.golden.json");
+}
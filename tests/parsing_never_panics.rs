@@ -0,0 +1,98 @@
+//! Property tests checking that every parser returns an error, rather than panicking, on
+//! arbitrary and adversarial inputs.
+use proptest::prelude::*;
+
+use sphere_pack_from_json::parsing::{self, Format, Spheres};
+
+const FORMATS: [Format; 5] = [
+    Format::Json,
+    Format::Yaml,
+    Format::Toml,
+    Format::Csv,
+    Format::Json5,
+];
+
+/// Parse `bytes` as spheres, sphere positions, half-spaces, radii, proportions and radius
+/// distributions in every format, which must not panic.
+fn parse_everything(bytes: &[u8]) {
+    for format in FORMATS {
+        let _ = Spheres::from_reader(bytes, format);
+        let _ = Spheres::from_reader_counts(bytes, format, false);
+        let _ = parsing::read_positions(bytes, format);
+        let _ = parsing::read_half_spaces(bytes, format);
+        let _ = parsing::read_radii(bytes, format);
+        let _ = parsing::read_proportions::<_, f64>(bytes, format);
+        let _ = parsing::read_distribution(bytes, format);
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        let _ = text.parse::<Spheres>();
+        let _ = Format::split_directive(text);
+    }
+}
+
+/// A number which is likely to be at or beyond the edge of what some parser or later check can
+/// represent.
+fn extreme_number() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::from("1e309")),
+        Just(String::from("-1e309")),
+        Just(String::from("1e-320")),
+        Just(String::from("0")),
+        Just(String::from("-0")),
+        Just(String::from("NaN")),
+        Just(String::from("Infinity")),
+        Just(String::from("18446744073709551616")),
+        any::<f64>().prop_map(|f| f.to_string()),
+        any::<i64>().prop_map(|i| i.to_string()),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+        parse_everything(&bytes);
+    }
+
+    #[test]
+    fn arbitrary_text(text in "\\PC{0,256}") {
+        parse_everything(text.as_bytes());
+    }
+
+    #[test]
+    fn extreme_numbers(
+        radii in proptest::collection::vec(extreme_number(), 1..4),
+        proportions in proptest::collection::vec(extreme_number(), 1..4),
+    ) {
+        let objects: Vec<String> = radii
+            .iter()
+            .zip(proportions.iter())
+            .enumerate()
+            .map(|(i, (r, p))| format!(r#"{{"name": "s{}", "radius": {}, "proportion": {}}}"#, i, r, p))
+            .collect();
+        parse_everything(format!("[{}]", objects.join(",")).as_bytes());
+        let rows: Vec<String> = radii
+            .iter()
+            .zip(proportions.iter())
+            .enumerate()
+            .map(|(i, (r, p))| format!("s{},{},{}", i, r, p))
+            .collect();
+        parse_everything(format!("name,radius,proportion\n{}", rows.join("\n")).as_bytes());
+        let positions: Vec<String> = radii
+            .iter()
+            .map(|r| format!(r#"{{"x": {0}, "y": 0, "z": {0}, "radius": {0}}}"#, r))
+            .collect();
+        parse_everything(format!("[{}]", positions.join(",")).as_bytes());
+    }
+}
+
+proptest! {
+    // Each case parses inputs of up to a few hundred kilobytes, so fewer are run.
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn deeply_nested(depth in 1usize..100_000) {
+        parse_everything(format!("{}{}", "[".repeat(depth), "]".repeat(depth)).as_bytes());
+        parse_everything("{\"a\":".repeat(depth).as_bytes());
+        parse_everything("- ".repeat(depth).as_bytes());
+    }
+}
@@ -0,0 +1,64 @@
+//! Compares the peak memory of streaming sphere positions against collecting them first.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write as _;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use nalgebra::Point3;
+use spherical_cow::shapes::Sphere;
+
+use sphere_pack_from_json::output::write_csv_spheres;
+
+/// An allocator which tracks the current and peak number of bytes allocated.
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(current, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc;
+
+/// Provides the peak number of bytes allocated while running `f`, beyond those already allocated.
+fn peak_during<F: FnOnce()>(f: F) -> usize {
+    let before = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    f();
+    PEAK.load(Ordering::SeqCst) - before
+}
+
+#[test]
+fn streaming_bounds_memory() {
+    let packed: Vec<Sphere> = (0..100_000)
+        .map(|i| Sphere::new(Point3::new(i as f32, 0.5, -0.25), 1.5).unwrap())
+        .collect();
+
+    let streamed = peak_during(|| write_csv_spheres(io::sink(), &packed, None).unwrap());
+    let collected = peak_during(|| {
+        let mut rows = String::new();
+        for sphere in &packed {
+            let c = sphere.center;
+            writeln!(rows, "{},{},{},{}", c.x, c.y, c.z, sphere.radius).unwrap();
+        }
+        io::Write::write_all(&mut io::sink(), rows.as_bytes()).unwrap();
+    });
+
+    assert!(streamed < 1024, "streaming allocated {} bytes", streamed);
+    assert!(
+        collected > 1_000_000,
+        "collecting allocated {} bytes",
+        collected
+    );
+}